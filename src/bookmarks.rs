@@ -0,0 +1,137 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// A named position (in ms) in a music.
+#[derive(Clone, Debug)]
+pub struct Bookmark {
+    pub time: usize,
+    pub name: String,
+}
+
+impl Bookmark {
+    pub fn new(time: usize, name: &str) -> Bookmark {
+        Bookmark {
+            time: time,
+            name: name.to_owned(),
+        }
+    }
+
+    /// Reads a bookmark written as "time name".
+    pub fn from_str(value: &str) -> Result<Bookmark, String> {
+        let mut parts = value.splitn(2, ' ');
+        let time = parts.next().unwrap_or("");
+
+        match time.parse() {
+            Ok(t) => Ok(Bookmark::new(t, parts.next().unwrap_or("").trim())),
+            Err(_) => Err(format!("\"{}\" isn't a valid bookmark", value)),
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        format!("{} {}", self.time, self.name)
+    }
+}
+
+/// Returns the index of the last bookmark before `position`, leaving some
+/// time to go back to the previous one when a bookmark has just been passed.
+pub fn get_prev(bookmarks: &[Bookmark], position: usize) -> Option<usize> {
+    bookmarks.iter().rposition(|b| b.time + 2000 < position)
+}
+
+pub fn get_next(bookmarks: &[Bookmark], position: usize) -> Option<usize> {
+    bookmarks.iter().position(|b| b.time > position)
+}
+
+fn write_file(path: &Path, content: &str) -> Result<(), String> {
+    match File::create(path) {
+        Ok(mut f) => match f.write_all(content.as_bytes()) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Cannot write {} : {}", path.display(), e)),
+        },
+        Err(e) => Err(format!("Cannot create {} : {}", path.display(), e)),
+    }
+}
+
+/// Writes the bookmarks next to the music as a CUE sheet, each bookmark
+/// becoming a track. Returns the path of the created file.
+pub fn export_cue(music: &str, bookmarks: &[Bookmark]) -> Result<String, String> {
+    let path = Path::new(music);
+    let file_name = match path.file_name() {
+        Some(f) => f.to_string_lossy().into_owned(),
+        None => return Err(format!("{} isn't a file", music)),
+    };
+    let file_type = match path.extension() {
+        Some(e) => match e.to_string_lossy().to_lowercase().as_str() {
+            "mp3" => "MP3",
+            "aif" | "aiff" => "AIFF",
+            _ => "WAVE",
+        },
+        None => "WAVE",
+    };
+    let mut out = format!("FILE \"{}\" {}\n", file_name, file_type);
+
+    for (pos, bookmark) in bookmarks.iter().enumerate() {
+        // CUE sheets count in frames of 1/75 second
+        let frames = bookmark.time * 75 / 1000;
+
+        out.push_str(&format!("  TRACK {:02} AUDIO\n", pos + 1));
+        out.push_str(&format!("    TITLE \"{}\"\n", bookmark.name.replace('"', "'")));
+        out.push_str(&format!(
+            "    INDEX 01 {:02}:{:02}:{:02}\n",
+            frames / 75 / 60,
+            frames / 75 % 60,
+            frames % 75
+        ));
+    }
+    let cue = path.with_extension("cue");
+
+    try!(write_file(&cue, &out));
+    Ok(cue.to_string_lossy().into_owned())
+}
+
+/// Writes the bookmarks next to the music as an OGM chapters file (the one
+/// read by mkvmerge or MP4Box). Returns the path of the created file.
+pub fn export_chapters(music: &str, bookmarks: &[Bookmark]) -> Result<String, String> {
+    let mut out = String::new();
+
+    for (pos, bookmark) in bookmarks.iter().enumerate() {
+        out.push_str(&format!(
+            "CHAPTER{:02}={:02}:{:02}:{:02}.{:03}\n",
+            pos + 1,
+            bookmark.time / 3600000,
+            bookmark.time / 60000 % 60,
+            bookmark.time / 1000 % 60,
+            bookmark.time % 1000
+        ));
+        out.push_str(&format!("CHAPTER{:02}NAME={}\n", pos + 1, bookmark.name));
+    }
+    let chapters = Path::new(music).with_extension("chapters.txt");
+
+    try!(write_file(&chapters, &out));
+    Ok(chapters.to_string_lossy().into_owned())
+}
@@ -0,0 +1,414 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+
+#![allow(dead_code)]
+
+use decoder::Decoder;
+use fft::{self, FftWindow};
+use library::Library;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+// range the tempo is folded into, so a half or a double tempo isn't reported
+const MIN_BPM: f32 = 70f32;
+const MAX_BPM: f32 = 180f32;
+// range of the tempos looked for in the autocorrelation
+const LOWEST_BPM: f32 = 40f32;
+const HIGHEST_BPM: f32 = 240f32;
+// tempo favoured when the autocorrelation has several peaks
+const USUAL_BPM: f32 = 120f32;
+// the onset envelope is resampled at this rate (in Hz) before the tempo is estimated
+const ENVELOPE_RATE: f32 = 100f32;
+// samples in each spectrum of the offline analysis
+const FRAME_SIZE: usize = 1024;
+// how much of the music (in ms) the live estimate looks at
+const HISTORY: usize = 12000;
+// the live estimate needs at least that much music (in ms)
+const MIN_HISTORY: usize = 6000;
+const ESTIMATE_INTERVAL: usize = 2000;
+// a gap (in ms) larger than this between two spectra means the music was seeked
+const MAX_GAP: usize = 500;
+// the flux has to be that many times the mean of the last second to be a beat
+const THRESHOLD: f32 = 1.5f32;
+const MIN_BEAT_INTERVAL: usize = 250;
+// what is left of the pulse after each frame
+const PULSE_DECAY: f32 = 0.8f32;
+/// Time (in ms) the live estimate needs before it is stored in the library.
+pub const STORE_TIME: usize = 30000;
+// files looked for in the folders given to the offline analysis
+const MUSIC_EXTENSIONS: [&str; 10] = [
+    "mp3", "flac", "ogg", "oga", "opus", "wav", "m4a", "aac", "wma", "aiff",
+];
+// the library is saved every few musics so a long analysis can be stopped
+const SAVE_INTERVAL: usize = 20;
+
+/// Finds the beats in the spectra of the music being played and estimates
+/// its tempo.
+pub struct BeatDetector {
+    // left and right spectra of the previous frame
+    previous: Vec<f32>,
+    // position (in ms) and spectral flux of the last frames
+    history: VecDeque<(usize, f32)>,
+    last_position: Option<usize>,
+    last_beat: Option<usize>,
+    last_estimate: usize,
+    pulse: f32,
+    bpm: Option<f32>,
+    // time (in ms) of music analysed without a gap
+    analysed: usize,
+}
+
+impl BeatDetector {
+    pub fn new() -> BeatDetector {
+        BeatDetector {
+            previous: Vec::new(),
+            history: VecDeque::new(),
+            last_position: None,
+            last_beat: None,
+            last_estimate: 0usize,
+            pulse: 0f32,
+            bpm: None,
+            analysed: 0usize,
+        }
+    }
+
+    /// Forgets everything about the previous music.
+    pub fn reset(&mut self) {
+        *self = BeatDetector::new();
+    }
+
+    /// Adds the spectra of the music at `position` (in ms) and returns
+    /// whether there is a beat.
+    pub fn update(&mut self, left: &[f32], right: &[f32], position: usize) -> bool {
+        let spectrum: Vec<f32> = left.iter().chain(right.iter()).cloned().collect();
+
+        match self.last_position {
+            Some(p) if position >= p && position - p <= MAX_GAP => self.analysed += position - p,
+            _ => {
+                self.history.clear();
+                self.previous.clear();
+                self.last_beat = None;
+                self.last_estimate = position;
+            }
+        }
+        self.last_position = Some(position);
+        // the FFT size may have changed
+        let flux = if self.previous.len() == spectrum.len() {
+            get_flux(&spectrum, &self.previous)
+        } else {
+            0f32
+        };
+        let recent: Vec<f32> = self
+            .history
+            .iter()
+            .filter(|h| h.0 + 1000 >= position)
+            .map(|h| h.1)
+            .collect();
+        let mean = if recent.is_empty() {
+            flux
+        } else {
+            recent.iter().sum::<f32>() / recent.len() as f32
+        };
+        let beat = flux > mean * THRESHOLD && match self.last_beat {
+            Some(b) => position >= b + MIN_BEAT_INTERVAL,
+            None => true,
+        };
+
+        self.previous = spectrum;
+        self.history.push_back((position, flux));
+        while self.history.len() > 1 && self.history[0].0 + HISTORY < position {
+            self.history.pop_front();
+        }
+        self.pulse *= PULSE_DECAY;
+        if beat {
+            self.last_beat = Some(position);
+            self.pulse = 1f32;
+        }
+        if position >= self.last_estimate + ESTIMATE_INTERVAL {
+            self.last_estimate = position;
+            self.update_bpm();
+        }
+        beat
+    }
+
+    /// Resamples the flux history and estimates the tempo from it.
+    fn update_bpm(&mut self) {
+        let (first, last) = match (self.history.front(), self.history.back()) {
+            (Some(f), Some(l)) => (f.0, l.0),
+            _ => return,
+        };
+
+        if last < first + MIN_HISTORY {
+            return;
+        }
+        let step = 1000f32 / ENVELOPE_RATE;
+        let mut envelope = Vec::new();
+        let mut it = 0usize;
+
+        for index in 0..((last - first) as f32 / step) as usize {
+            let time = first as f32 + index as f32 * step;
+
+            while it + 2 < self.history.len() && (self.history[it + 1].0 as f32) < time {
+                it += 1;
+            }
+            let (t0, v0) = self.history[it];
+            let (t1, v1) = self.history[it + 1];
+            let ratio = if t1 > t0 {
+                ((time - t0 as f32) / (t1 - t0) as f32).max(0f32).min(1f32)
+            } else {
+                0f32
+            };
+
+            envelope.push(v0 + (v1 - v0) * ratio);
+        }
+        let bpm = match estimate(&envelope, ENVELOPE_RATE) {
+            Some(b) => b,
+            None => return,
+        };
+
+        // close estimates are smoothed, a different tempo replaces the old one
+        self.bpm = match self.bpm {
+            Some(old) if (bpm - old).abs() < old * 0.04f32 => Some(old * 0.7f32 + bpm * 0.3f32),
+            _ => Some(bpm),
+        };
+    }
+
+    /// Goes from 1, on a beat, to 0.
+    pub fn get_pulse(&self) -> f32 {
+        self.pulse
+    }
+
+    pub fn get_bpm(&self) -> Option<f32> {
+        self.bpm
+    }
+
+    /// Returns how much of the music (in ms) was analysed.
+    pub fn get_analysed_time(&self) -> usize {
+        self.analysed
+    }
+}
+
+/// How much louder the bins got since the `previous` spectrum, on a log
+/// scale so quiet instruments count too.
+fn get_flux(spectrum: &[f32], previous: &[f32]) -> f32 {
+    spectrum
+        .iter()
+        .zip(previous.iter())
+        .map(|(a, b)| ((1f32 + 1000f32 * a).ln() - (1f32 + 1000f32 * b).ln()).max(0f32))
+        .sum()
+}
+
+/// Estimates the tempo of an onset `envelope` sampled at `rate` Hz from
+/// the highest peak of its autocorrelation.
+pub fn estimate(envelope: &[f32], rate: f32) -> Option<f32> {
+    let min_lag = (rate * 60f32 / HIGHEST_BPM) as usize;
+    let max_lag = (rate * 60f32 / LOWEST_BPM) as usize + 1;
+
+    if min_lag < 1 || envelope.len() < max_lag * 2 {
+        return None;
+    }
+    let mean = envelope.iter().sum::<f32>() / envelope.len() as f32;
+    let values: Vec<f32> = envelope.iter().map(|v| v - mean).collect();
+    let correlation: Vec<f32> = (0..max_lag + 2)
+        .map(|lag| {
+            let sum: f32 = values
+                .iter()
+                .zip(values[lag..].iter())
+                .map(|(a, b)| a * b)
+                .sum();
+
+            sum / (values.len() - lag) as f32
+        })
+        .collect();
+    let mut best: Option<(usize, f32)> = None;
+
+    for lag in min_lag..max_lag + 1 {
+        let bpm = rate * 60f32 / lag as f32;
+        // a wide bell on a log scale around the usual tempo
+        let weight = (-0.5f32 * (bpm / USUAL_BPM).log2().powi(2)).exp();
+        let score = correlation[lag] * weight;
+
+        if correlation[lag] > 0f32 && best.map_or(true, |b| score > b.1) {
+            best = Some((lag, score));
+        }
+    }
+    let lag = match best {
+        Some((l, _)) => l,
+        None => return None,
+    };
+    // parabolic interpolation between the neighbours of the peak
+    let (a, b, c) = (correlation[lag - 1], correlation[lag], correlation[lag + 1]);
+    let denominator = a - 2f32 * b + c;
+    let offset = if denominator != 0f32 {
+        (0.5f32 * (a - c) / denominator).max(-0.5f32).min(0.5f32)
+    } else {
+        0f32
+    };
+    let mut bpm = rate * 60f32 / (lag as f32 + offset);
+
+    while bpm < MIN_BPM {
+        bpm *= 2f32;
+    }
+    while bpm >= MAX_BPM {
+        bpm /= 2f32;
+    }
+    Some(bpm)
+}
+
+/// Decodes the whole file and returns its tempo.
+pub fn analyze_file(path: &str) -> Result<f32, String> {
+    let mut decoder = try!(Decoder::new(path));
+    let channels = decoder.channels;
+    let hop = ((decoder.rate as f32 / ENVELOPE_RATE) as usize).max(1);
+    let mut pending: Vec<f32> = Vec::new();
+    let mut previous: Vec<f32> = Vec::new();
+    let mut envelope = Vec::new();
+
+    while let Some(samples) = decoder.read() {
+        // mixed down to mono
+        pending.extend(
+            samples
+                .chunks(channels)
+                .map(|c| c.iter().sum::<f32>() / channels as f32),
+        );
+        while pending.len() >= FRAME_SIZE {
+            let spectrum = fft::compute(&pending[..FRAME_SIZE], FftWindow::Hanning);
+
+            if !previous.is_empty() {
+                envelope.push(get_flux(&spectrum, &previous));
+            }
+            previous = spectrum;
+            pending.drain(..hop);
+        }
+    }
+    match estimate(&envelope, decoder.rate as f32 / hop as f32) {
+        Some(bpm) => Ok(bpm),
+        None => Err(format!("{} is too short or has no clear beat", path)),
+    }
+}
+
+/// Adds `path` to `files` or, if it is a folder, the musics in it.
+fn add_files(path: &Path, files: &mut Vec<String>) {
+    if !path.is_dir() {
+        files.push(path.to_string_lossy().into_owned());
+        return;
+    }
+    let mut entries: Vec<_> = match fs::read_dir(path) {
+        Ok(e) => e.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(e) => {
+            println!("Cannot read {} : {}", path.display(), e);
+            return;
+        }
+    };
+
+    entries.sort();
+    for entry in entries.iter() {
+        let is_music = match entry.extension() {
+            Some(e) => MUSIC_EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str()),
+            None => false,
+        };
+
+        if entry.is_dir() || is_music {
+            add_files(entry, files);
+        }
+    }
+}
+
+/// Stores the tempo of `musics` (files or folders) in the library. Without
+/// any music, every music of the library which has no tempo is analysed.
+pub fn analyze_library(musics: &[String]) {
+    let mut library = Library::load();
+    let mut files = Vec::new();
+
+    if musics.is_empty() {
+        for music in library.get_musics() {
+            match library.get(&music) {
+                Some(info) if info.bpm.is_none() => files.push(music),
+                _ => {}
+            }
+        }
+    } else {
+        for music in musics.iter() {
+            add_files(Path::new(music), &mut files);
+        }
+    }
+    let mut found = 0usize;
+
+    for (it, file) in files.iter().enumerate() {
+        match analyze_file(file) {
+            Ok(bpm) => {
+                println!("[{}/{}] {} : {:.1} BPM", it + 1, files.len(), file, bpm);
+                library.get_mut(file).bpm = Some(bpm);
+                found += 1;
+                if found % SAVE_INTERVAL == 0 {
+                    library.save();
+                }
+            }
+            Err(e) => println!("[{}/{}] {}", it + 1, files.len(), e),
+        }
+    }
+    library.save();
+    println!("Tempo found for {} of {} musics", found, files.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::estimate;
+
+    /// Onset envelope sampled at `rate` Hz with a pulse on each beat.
+    fn pulses(bpm: f32, rate: f32, seconds: usize) -> Vec<f32> {
+        let period = rate * 60f32 / bpm;
+        let mut envelope = vec![0f32; seconds * rate as usize];
+        let mut time = 0f32;
+
+        while (time as usize) < envelope.len() {
+            envelope[time as usize] = 1f32;
+            time += period;
+        }
+        envelope
+    }
+
+    #[test]
+    fn pulse_trains() {
+        for bpm in [120f32, 100f32, 90f32, 140f32].iter() {
+            let found = estimate(&pulses(*bpm, 100f32, 30), 100f32).unwrap();
+
+            assert!((found - bpm).abs() < 1.5f32, "{} found for {}", found, bpm);
+        }
+    }
+
+    #[test]
+    fn tempo_kept_in_range() {
+        // 60 BPM is doubled as it is below the usual range
+        let found = estimate(&pulses(60f32, 100f32, 30), 100f32).unwrap();
+
+        assert!((found - 120f32).abs() < 1.5f32, "{}", found);
+    }
+
+    #[test]
+    fn not_enough_data() {
+        assert!(estimate(&pulses(120f32, 100f32, 1), 100f32).is_none());
+        assert!(estimate(&[0f32; 1000], 100f32).is_none());
+    }
+}
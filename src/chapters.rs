@@ -0,0 +1,237 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use gapless;
+use mp4;
+use rfmod;
+use std::fs::File;
+use tags;
+
+#[derive(Clone, Debug)]
+pub struct Chapter {
+    /// Start of the chapter in ms.
+    pub time: usize,
+    pub title: String,
+}
+
+/// Reads the chapters from the ID3 CHAP frames or, for MP4 files, from the
+/// Nero chapter list ("chpl" atom).
+pub fn load(music: &str, sound: &rfmod::Sound) -> Vec<Chapter> {
+    let mut chapters = from_id3(music, sound);
+
+    if chapters.is_empty() {
+        chapters = from_mp4(music);
+    }
+    chapters.sort_by(|a, b| a.time.cmp(&b.time));
+    chapters
+}
+
+fn from_id3(music: &str, sound: &rfmod::Sound) -> Vec<Chapter> {
+    let mut chapters = Vec::new();
+    let mut index = 0i32;
+    let version = tags::read_id3_version(music).unwrap_or(3);
+
+    while let Some(data) = tags::get_tag_data_at(sound, "CHAP", index) {
+        index += 1;
+        match parse_chap(&data, version) {
+            Some(c) => chapters.push(c),
+            None => {}
+        }
+    }
+    chapters
+}
+
+/// A CHAP frame is made of the element id, the start and end times (in ms),
+/// the start and end offsets, then sub-frames (we only read TIT2). Their
+/// sizes are syncsafe integers since ID3v2.4.
+fn parse_chap(data: &[u8], version: u8) -> Option<Chapter> {
+    let id_end = match data.iter().position(|c| *c == 0) {
+        Some(p) => p,
+        None => return None,
+    };
+    let mut pos = id_end + 1;
+
+    if data.len() < pos + 16 {
+        return None;
+    }
+    let time = mp4::read_u32(&data[pos..]) as usize;
+    let mut title = String::from_utf8_lossy(&data[..id_end]).into_owned();
+
+    pos += 16;
+    while pos + 10 <= data.len() {
+        let frame_id = &data[pos..pos + 4];
+        let size = if version >= 4 {
+            gapless::read_syncsafe(&data[pos + 4..])
+        } else {
+            mp4::read_u32(&data[pos + 4..]) as usize
+        };
+        let start = pos + 10;
+
+        if size == 0 || start + size > data.len() {
+            break;
+        }
+        if frame_id == b"TIT2" {
+            let text = tags::decode_id3_text(data[start], &data[start + 1..start + size]);
+            let text = text.trim_matches('\0').trim();
+
+            if !text.is_empty() {
+                title = text.to_owned();
+            }
+        }
+        pos = start + size;
+    }
+    Some(Chapter {
+        time: time,
+        title: title,
+    })
+}
+
+/// Only the "moov" atom is read, the media data being skipped.
+fn from_mp4(music: &str) -> Vec<Chapter> {
+    let moov = match File::open(music) {
+        Ok(mut f) => match mp4::read_top_atom(&mut f, b"moov") {
+            Some(m) => m,
+            None => return Vec::new(),
+        },
+        Err(_) => return Vec::new(),
+    };
+
+    match mp4::find_atom(&moov, &[b"udta", b"chpl"]) {
+        Some(chpl) => parse_chpl(chpl),
+        None => Vec::new(),
+    }
+}
+
+/// The chapter times are in 100ns units.
+fn parse_chpl(data: &[u8]) -> Vec<Chapter> {
+    let mut chapters = Vec::new();
+
+    if data.len() < 5 {
+        return chapters;
+    }
+    let mut pos = if data[0] != 0 { 8usize } else { 4usize };
+
+    if pos >= data.len() {
+        return chapters;
+    }
+    let count = data[pos] as usize;
+
+    pos += 1;
+    for _ in 0..count {
+        if pos + 9 > data.len() {
+            break;
+        }
+        let time = mp4::read_u64(&data[pos..]) / 10000;
+        let len = data[pos + 8] as usize;
+
+        pos += 9;
+        if pos + len > data.len() {
+            break;
+        }
+        chapters.push(Chapter {
+            time: time as usize,
+            title: String::from_utf8_lossy(&data[pos..pos + len]).into_owned(),
+        });
+        pos += len;
+    }
+    chapters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_chap, parse_chpl};
+
+    /// CHAP frame starting at `time` ms with a TIT2 sub-frame.
+    fn chap(time: u32, title: &str, version: u8) -> Vec<u8> {
+        let size = title.len() + 1;
+        let mut data = b"ch1\x00".to_vec();
+
+        data.extend_from_slice(&[(time >> 24) as u8, (time >> 16) as u8, (time >> 8) as u8]);
+        data.push(time as u8);
+        data.extend_from_slice(&[0u8; 12]);
+        data.extend_from_slice(b"TIT2");
+        if version >= 4 {
+            data.extend_from_slice(&[0, 0, (size >> 7) as u8 & 0x7f, size as u8 & 0x7f]);
+        } else {
+            data.extend_from_slice(&[0, 0, (size >> 8) as u8, size as u8]);
+        }
+        data.extend_from_slice(&[0, 0, 3]);
+        data.extend_from_slice(title.as_bytes());
+        data
+    }
+
+    #[test]
+    fn chap_frames() {
+        let long: String = (0..200).map(|_| 'a').collect();
+
+        for version in 3..5 {
+            let chapter = parse_chap(&chap(61000, "Intro", version), version).unwrap();
+
+            assert_eq!(chapter.time, 61000);
+            assert_eq!(chapter.title, "Intro");
+            assert_eq!(
+                parse_chap(&chap(0, &long, version), version).unwrap().title,
+                long
+            );
+        }
+    }
+
+    #[test]
+    fn chap_without_title() {
+        let chapter = parse_chap(
+            b"ch2\x00\x00\x00\x03\xe8\x00\x00\x00\x00\xff\xff\xff\xff\xff\xff\xff\xff",
+            3,
+        );
+
+        assert_eq!(chapter.unwrap().title, "ch2");
+        assert!(parse_chap(b"ch3\x00\x00", 3).is_none());
+    }
+
+    #[test]
+    fn chpl() {
+        let mut data = vec![1u8, 0, 0, 0, 0, 0, 0, 0, 2];
+
+        data.extend_from_slice(&[0u8; 8]);
+        data.push(5);
+        data.extend_from_slice(b"Intro");
+        // 90 seconds in 100ns units
+        data.extend_from_slice(&[0, 0, 0, 0, 0x35, 0xa4, 0xe9, 0x00]);
+        data.push(3);
+        data.extend_from_slice(b"End");
+        let chapters = parse_chpl(&data);
+
+        assert_eq!(chapters.len(), 2);
+        assert_eq!((chapters[0].time, chapters[0].title.as_str()), (0, "Intro"));
+        assert_eq!(
+            (chapters[1].time, chapters[1].title.as_str()),
+            (90000, "End")
+        );
+    }
+
+    #[test]
+    fn chpl_truncated() {
+        assert!(parse_chpl(&[0, 0, 0, 0]).is_empty());
+        assert_eq!(parse_chpl(&[0, 0, 0, 0, 3, 0, 0]).len(), 0);
+    }
+}
@@ -0,0 +1,340 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use crossfade::FadeCurve;
+use fft::{self, FftChannels, FftSettings, FftWindow};
+use graphic_spectrogram::Colormap;
+use graphic_spectrum::SpectrumMode;
+use output;
+use recorder::RecordFormat;
+use replay_gain::ReplayGainMode;
+use rfmod;
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use volume::VolumeCurve;
+
+/// Returns the folder where the player keeps its files (`~/.music_player`).
+pub fn get_data_dir() -> PathBuf {
+    let mut path = match env::var("HOME") {
+        Ok(h) => PathBuf::from(h),
+        Err(_) => PathBuf::from("."),
+    };
+
+    path.push(".music_player");
+    if fs::metadata(&path).is_err() {
+        if let Err(e) = fs::create_dir_all(&path) {
+            println!("Cannot create {} : {}", path.display(), e);
+        }
+    }
+    path
+}
+
+pub struct Config {
+    /// Crossfade duration between two musics in seconds, 0 means gapless.
+    pub crossfade: f32,
+    /// Crossfade duration used when changing the music by hand.
+    pub skip_fade: f32,
+    pub fade_curve: FadeCurve,
+    pub replay_gain: ReplayGainMode,
+    /// Added to the ReplayGain, in dB.
+    pub replay_gain_preamp: f32,
+    /// Computes the gain of the musics without ReplayGain tags.
+    pub analyze_loudness: bool,
+    /// Name of the equalizer preset.
+    pub equalizer: String,
+    /// Keeps the pitch when the speed is changed.
+    pub time_stretch: bool,
+    /// Resumes musics where they were left.
+    pub audiobook: bool,
+    pub volume_curve: VolumeCurve,
+    /// Stereo balance, from -1 (left) to 1 (right).
+    pub balance: f32,
+    /// Id or part of the name of the output device.
+    pub output_driver: Option<String>,
+    pub sample_rate: Option<u32>,
+    /// Length (in samples) of FMOD's mixing buffers.
+    pub buffer_size: Option<u32>,
+    pub speaker_mode: Option<rfmod::SpeakerMode>,
+    pub record_format: RecordFormat,
+    /// Folder of the recordings, `~/.music_player/recordings` by default.
+    pub record_dir: Option<String>,
+    pub spectrum_mode: SpectrumMode,
+    /// Number of bands of the bar spectrum.
+    pub spectrum_bands: usize,
+    pub spectrogram_colormap: Colormap,
+    pub fft: FftSettings,
+    /// Shows the waveform of the music in the progress bar.
+    pub waveform: bool,
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            crossfade: 0f32,
+            skip_fade: 0f32,
+            fade_curve: FadeCurve::EqualPower,
+            replay_gain: ReplayGainMode::Track,
+            replay_gain_preamp: 0f32,
+            analyze_loudness: false,
+            equalizer: "flat".to_owned(),
+            time_stretch: true,
+            audiobook: false,
+            volume_curve: VolumeCurve::Cubic,
+            balance: 0f32,
+            output_driver: None,
+            sample_rate: None,
+            buffer_size: None,
+            speaker_mode: None,
+            record_format: RecordFormat::Wav,
+            record_dir: None,
+            spectrum_mode: SpectrumMode::Linear,
+            spectrum_bands: 32,
+            spectrogram_colormap: Colormap::Heat,
+            fft: FftSettings::new(),
+            waveform: false,
+        }
+    }
+
+    /// Reads `~/.music_player/config`. Each line is a `key = value` pair and
+    /// lines starting with `#` are ignored.
+    pub fn load() -> Config {
+        let mut config = Config::new();
+        let mut path = get_data_dir();
+        let mut content = String::new();
+
+        path.push("config");
+        match File::open(&path) {
+            Ok(mut f) => {
+                if let Err(e) = f.read_to_string(&mut content) {
+                    println!("Cannot read {} : {}", path.display(), e);
+                }
+            }
+            Err(_) => return config,
+        }
+        for (pos, line) in content.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+
+            if let Err(e) = config.set(key, value) {
+                println!("{}:{} : {}", path.display(), pos + 1, e);
+            }
+        }
+        config
+    }
+
+    /// Applies the `--key=value` arguments and returns the other ones.
+    pub fn parse_args(&mut self, args: &[String]) -> Vec<String> {
+        let mut others = Vec::new();
+
+        for arg in args.iter() {
+            if arg.starts_with("--") && arg.contains('=') {
+                let mut parts = arg[2..].splitn(2, '=');
+                let key = parts.next().unwrap_or("");
+                let value = parts.next().unwrap_or("");
+
+                if let Err(e) = self.set(key, value) {
+                    println!("{} : {}", arg, e);
+                }
+            } else {
+                others.push(arg.clone());
+            }
+        }
+        others
+    }
+
+    /// Writes `key = value` in `~/.music_player/config`, in place of the line
+    /// of this option if there is one. The other lines are kept as they are,
+    /// so the options given on the command line aren't stored.
+    pub fn save_option(key: &str, value: &str) {
+        let mut path = get_data_dir();
+        let mut content = String::new();
+
+        path.push("config");
+        if let Ok(mut f) = File::open(&path) {
+            if let Err(e) = f.read_to_string(&mut content) {
+                println!("Cannot read {} : {}", path.display(), e);
+                return;
+            }
+        }
+        let mut found = false;
+        let mut lines: Vec<String> = content
+            .lines()
+            .map(|line| {
+                let trimmed = line.trim();
+                let name = trimmed.splitn(2, '=').next().unwrap_or("").trim();
+
+                if trimmed.starts_with('#') || name != key {
+                    return line.to_owned();
+                }
+                found = true;
+                format!("{} = {}", key, value)
+            })
+            .collect();
+
+        if !found {
+            lines.push(format!("{} = {}", key, value));
+        }
+        let res = match File::create(&path) {
+            Ok(mut f) => f.write_all((lines.join("\n") + "\n").as_bytes()),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = res {
+            println!("Cannot write {} : {}", path.display(), e);
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "crossfade" => self.crossfade = try!(parse_seconds(value)),
+            "skip_fade" => self.skip_fade = try!(parse_seconds(value)),
+            "fade_curve" => {
+                self.fade_curve = match FadeCurve::from_str(value) {
+                    Some(c) => c,
+                    None => return Err(format!("unknown fade curve \"{}\"", value)),
+                }
+            }
+            "replaygain" => {
+                self.replay_gain = match ReplayGainMode::from_str(value) {
+                    Some(m) => m,
+                    None => return Err(format!("unknown ReplayGain mode \"{}\"", value)),
+                }
+            }
+            "replaygain_preamp" => {
+                self.replay_gain_preamp = match value.parse() {
+                    Ok(v) => v,
+                    Err(_) => return Err(format!("\"{}\" isn't a valid gain", value)),
+                }
+            }
+            "analyze_loudness" => self.analyze_loudness = try!(parse_bool(value)),
+            "equalizer" => self.equalizer = value.to_owned(),
+            "time_stretch" => self.time_stretch = try!(parse_bool(value)),
+            "audiobook" => self.audiobook = try!(parse_bool(value)),
+            "volume_curve" => {
+                self.volume_curve = match VolumeCurve::from_str(value) {
+                    Some(c) => c,
+                    None => return Err(format!("unknown volume curve \"{}\"", value)),
+                }
+            }
+            "balance" => {
+                self.balance = match value.parse::<f32>() {
+                    Ok(v) if v >= -1f32 && v <= 1f32 => v,
+                    _ => return Err(format!("\"{}\" isn't between -1 and 1", value)),
+                }
+            }
+            "output" => self.output_driver = Some(value.to_owned()),
+            "sample_rate" => self.sample_rate = Some(try!(parse_u32(value))),
+            "buffer_size" => self.buffer_size = Some(try!(parse_u32(value))),
+            "speaker_mode" => {
+                self.speaker_mode = match output::parse_speaker_mode(value) {
+                    Some(m) => Some(m),
+                    None => return Err(format!("unknown speaker mode \"{}\"", value)),
+                }
+            }
+            "record_format" => {
+                self.record_format = match RecordFormat::from_str(value) {
+                    Some(f) => f,
+                    None => return Err(format!("unknown record format \"{}\"", value)),
+                }
+            }
+            "record_dir" => self.record_dir = Some(value.to_owned()),
+            "spectrum" => {
+                self.spectrum_mode = match SpectrumMode::from_str(value) {
+                    Some(m) => m,
+                    None => return Err(format!("unknown spectrum mode \"{}\"", value)),
+                }
+            }
+            "spectrum_bands" => {
+                self.spectrum_bands = match value.parse::<usize>() {
+                    Ok(n) if n > 0 && n <= 256 => n,
+                    _ => return Err(format!("\"{}\" isn't between 1 and 256", value)),
+                }
+            }
+            "spectrogram_colormap" => {
+                self.spectrogram_colormap = match Colormap::from_str(value) {
+                    Some(c) => c,
+                    None => return Err(format!("unknown colormap \"{}\"", value)),
+                }
+            }
+            "fft_size" => self.fft.size = try!(fft::parse_size(value)),
+            "fft_window" => {
+                self.fft.window = match FftWindow::from_str(value) {
+                    Some(w) => w,
+                    None => return Err(format!("unknown FFT window \"{}\"", value)),
+                }
+            }
+            "fft_channels" => {
+                self.fft.channels = match FftChannels::from_str(value) {
+                    Some(c) => c,
+                    None => return Err(format!("unknown FFT channels \"{}\"", value)),
+                }
+            }
+            "waveform" => self.waveform = try!(parse_bool(value)),
+            _ => return Err(format!("unknown option \"{}\"", key)),
+        }
+        Ok(())
+    }
+
+    pub fn get_record_dir(&self) -> PathBuf {
+        match self.record_dir {
+            Some(ref d) => PathBuf::from(d),
+            None => {
+                let mut path = get_data_dir();
+
+                path.push("recordings");
+                path
+            }
+        }
+    }
+}
+
+fn parse_seconds(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(v) if v >= 0f32 => Ok(v),
+        _ => Err(format!("\"{}\" isn't a valid duration", value)),
+    }
+}
+
+fn parse_u32(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(v) if v > 0 => Ok(v),
+        _ => Err(format!("\"{}\" isn't a valid number", value)),
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(format!("\"{}\" isn't a boolean", value)),
+    }
+}
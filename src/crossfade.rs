@@ -0,0 +1,124 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+use std::time::Instant;
+use track::Track;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FadeCurve {
+    Linear,
+    EqualPower,
+    Log,
+}
+
+impl FadeCurve {
+    pub fn from_str(s: &str) -> Option<FadeCurve> {
+        match s {
+            "linear" => Some(FadeCurve::Linear),
+            "equal-power" | "equal_power" => Some(FadeCurve::EqualPower),
+            "log" => Some(FadeCurve::Log),
+            _ => None,
+        }
+    }
+
+    /// Returns the volume factor of the incoming music, `progress` going
+    /// from 0 to 1.
+    pub fn fade_in(&self, progress: f32) -> f32 {
+        let progress = progress.max(0f32).min(1f32);
+
+        match *self {
+            FadeCurve::Linear => progress,
+            FadeCurve::EqualPower => (progress * PI / 2f32).sin(),
+            // goes linearly from -60dB to 0dB
+            FadeCurve::Log => {
+                if progress <= 0f32 {
+                    0f32
+                } else {
+                    10f32.powf((progress - 1f32) * 3f32)
+                }
+            }
+        }
+    }
+
+    pub fn fade_out(&self, progress: f32) -> f32 {
+        self.fade_in(1f32 - progress)
+    }
+}
+
+/// The music being faded out while the current one is faded in.
+pub struct Crossfade {
+    pub outgoing: Track,
+    curve: FadeCurve,
+    start: Instant,
+    duration: f32,
+    // set when the time isn't the real one (offline rendering)
+    elapsed: Option<f32>,
+}
+
+impl Crossfade {
+    pub fn new(outgoing: Track, curve: FadeCurve, duration: f32) -> Crossfade {
+        Crossfade {
+            outgoing: outgoing,
+            curve: curve,
+            start: Instant::now(),
+            duration: duration,
+            elapsed: None,
+        }
+    }
+
+    /// Uses `elapsed` (in seconds) instead of the real time.
+    pub fn set_elapsed(&mut self, elapsed: f32) {
+        self.elapsed = Some(elapsed);
+    }
+
+    fn get_progress(&self) -> f32 {
+        let elapsed = match self.elapsed {
+            Some(e) => e,
+            None => {
+                let e = self.start.elapsed();
+
+                e.as_secs() as f32 + e.subsec_nanos() as f32 / 1_000_000_000f32
+            }
+        };
+
+        if self.duration <= 0f32 {
+            1f32
+        } else {
+            elapsed / self.duration
+        }
+    }
+
+    pub fn get_in_volume(&self) -> f32 {
+        self.curve.fade_in(self.get_progress())
+    }
+
+    pub fn get_out_volume(&self) -> f32 {
+        self.curve.fade_out(self.get_progress())
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.get_progress() >= 1f32
+    }
+}
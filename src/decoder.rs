@@ -0,0 +1,138 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use rfmod;
+
+const BUFFER_SIZE: usize = 16384;
+
+/// Decodes a whole file without playing it. It uses its own FMOD system so
+/// it can be run from another thread than the player.
+pub struct Decoder {
+    // has to be dropped before `fmod`
+    sound: rfmod::Sound,
+    fmod: rfmod::Sys,
+    pub channels: usize,
+    pub rate: u32,
+    bits: i32,
+    float: bool,
+    buffer: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new(path: &str) -> Result<Decoder, String> {
+        let fmod = match rfmod::Sys::new() {
+            Ok(f) => f,
+            Err(e) => return Err(format!("FmodSys.new : {:?}", e)),
+        };
+
+        fmod.set_output(rfmod::OutputType::NoSoundNRT);
+        match fmod.init() {
+            rfmod::Status::Ok => {}
+            e => return Err(format!("FmodSys.init : {:?}", e)),
+        }
+        let sound = match fmod.create_sound(
+            path,
+            Some(rfmod::Mode(rfmod::OPENONLY | rfmod::ACCURATETIME)),
+            None,
+        ) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("FmodSys::create_sound failed on {} : {:?}", path, e)),
+        };
+        let (format, channels, bits) = match sound.get_format() {
+            Ok((_, format, channels, bits)) => (format, channels, bits),
+            Err(e) => return Err(format!("Sound::get_format : {:?}", e)),
+        };
+        let rate = match sound.get_defaults() {
+            Ok((frequency, _, _, _)) => frequency as u32,
+            Err(e) => return Err(format!("Sound::get_defaults : {:?}", e)),
+        };
+
+        Ok(Decoder {
+            sound: sound,
+            fmod: fmod,
+            channels: if channels > 0 { channels as usize } else { 1usize },
+            rate: rate,
+            bits: bits,
+            float: format == rfmod::SoundFormat::PCMFloat,
+            buffer: vec![0u8; BUFFER_SIZE],
+        })
+    }
+
+    /// Returns the length of the file in PCM samples.
+    pub fn get_length(&self) -> usize {
+        match self.sound.get_length(rfmod::TIMEUNIT_PCM) {
+            Ok(l) => l as usize,
+            Err(_) => 0usize,
+        }
+    }
+
+    /// Returns the next interleaved samples (between -1 and 1) or `None`
+    /// once the end of the file has been reached.
+    pub fn read(&mut self) -> Option<Vec<f32>> {
+        let read = match self.sound.read_data(&mut self.buffer) {
+            Ok(r) => r as usize,
+            Err(_) => return None,
+        };
+
+        if read == 0 {
+            return None;
+        }
+        let data = &self.buffer[..read];
+
+        Some(match (self.bits, self.float) {
+            (32, true) => data
+                .chunks(4)
+                .filter(|c| c.len() == 4)
+                .map(|c| {
+                    let bits = c[0] as u32 | (c[1] as u32) << 8 | (c[2] as u32) << 16
+                        | (c[3] as u32) << 24;
+
+                    f32::from_bits(bits)
+                })
+                .collect(),
+            (32, false) => data
+                .chunks(4)
+                .filter(|c| c.len() == 4)
+                .map(|c| {
+                    (c[0] as u32 | (c[1] as u32) << 8 | (c[2] as u32) << 16
+                        | (c[3] as u32) << 24) as i32 as f32 / 2147483648f32
+                })
+                .collect(),
+            (24, _) => data
+                .chunks(3)
+                .filter(|c| c.len() == 3)
+                .map(|c| {
+                    (((c[0] as u32) << 8 | (c[1] as u32) << 16 | (c[2] as u32) << 24) as i32
+                        >> 8) as f32 / 8388608f32
+                })
+                .collect(),
+            (8, _) => data.iter().map(|c| (*c as i8) as f32 / 128f32).collect(),
+            _ => data
+                .chunks(2)
+                .filter(|c| c.len() == 2)
+                .map(|c| (c[0] as u16 | (c[1] as u16) << 8) as i16 as f32 / 32768f32)
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,275 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use config;
+use rfmod;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+pub const NB_BANDS: usize = 10;
+pub const BANDS: [f32; NB_BANDS] = [
+    31f32, 62f32, 125f32, 250f32, 500f32, 1000f32, 2000f32, 4000f32, 8000f32, 16000f32,
+];
+/// FMOD's parametric EQ accepts gains between 0.05 and 3.0, so a bit more
+/// than +9dB.
+pub const MAX_GAIN: f32 = 9f32;
+
+// FMOD_DSP_PARAMEQ parameters
+const PARAM_CENTER: i32 = 0;
+const PARAM_BANDWIDTH: i32 = 1;
+const PARAM_GAIN: i32 = 2;
+
+#[derive(Clone)]
+pub struct Preset {
+    pub name: String,
+    pub gains: [f32; NB_BANDS],
+}
+
+impl Preset {
+    fn new(name: &str, gains: [f32; NB_BANDS]) -> Preset {
+        Preset {
+            name: name.to_owned(),
+            gains: gains,
+        }
+    }
+}
+
+fn get_builtin_presets() -> Vec<Preset> {
+    vec![
+        Preset::new("flat", [0f32; NB_BANDS]),
+        Preset::new(
+            "rock",
+            [5f32, 4f32, 3f32, 1f32, -1f32, -1f32, 1f32, 3f32, 4f32, 5f32],
+        ),
+        Preset::new(
+            "pop",
+            [-1f32, 1f32, 3f32, 4f32, 3f32, 0f32, -1f32, -1f32, 0f32, 1f32],
+        ),
+        Preset::new(
+            "vocal",
+            [-3f32, -3f32, -1f32, 1f32, 3f32, 4f32, 3f32, 1f32, 0f32, -2f32],
+        ),
+        Preset::new(
+            "jazz",
+            [3f32, 2f32, 1f32, 2f32, -1f32, -1f32, 0f32, 1f32, 2f32, 3f32],
+        ),
+        Preset::new(
+            "classical",
+            [4f32, 3f32, 2f32, 1f32, -1f32, -1f32, 0f32, 2f32, 3f32, 4f32],
+        ),
+        Preset::new(
+            "bass boost",
+            [7f32, 6f32, 4f32, 2f32, 0f32, 0f32, 0f32, 0f32, 0f32, 0f32],
+        ),
+        Preset::new(
+            "treble boost",
+            [0f32, 0f32, 0f32, 0f32, 0f32, 1f32, 3f32, 5f32, 6f32, 7f32],
+        ),
+    ]
+}
+
+fn get_presets_path() -> PathBuf {
+    let mut path = config::get_data_dir();
+
+    path.push("equalizer");
+    path
+}
+
+/// One FMOD parametric EQ per band, added on the system so every channel
+/// goes through them.
+pub struct Equalizer {
+    dsps: Vec<rfmod::Dsp>,
+    gains: [f32; NB_BANDS],
+    presets: Vec<Preset>,
+    nb_builtin: usize,
+    current: Option<usize>,
+}
+
+impl Equalizer {
+    pub fn new() -> Equalizer {
+        let presets = get_builtin_presets();
+
+        Equalizer {
+            dsps: Vec::new(),
+            gains: [0f32; NB_BANDS],
+            nb_builtin: presets.len(),
+            presets: presets,
+            current: Some(0usize),
+        }.load_presets()
+    }
+
+    /// Custom presets are stored in `~/.music_player/equalizer`, one per
+    /// line as `name = gain1 gain2 ...`.
+    fn load_presets(mut self) -> Equalizer {
+        let path = get_presets_path();
+        let mut content = String::new();
+
+        match File::open(&path) {
+            Ok(mut f) => {
+                if let Err(e) = f.read_to_string(&mut content) {
+                    println!("Cannot read {} : {}", path.display(), e);
+                }
+            }
+            Err(_) => return self,
+        }
+        for line in content.lines() {
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let values: Vec<f32> = parts
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .filter_map(|v| v.parse().ok())
+                .collect();
+
+            if name.is_empty() || values.len() != NB_BANDS {
+                continue;
+            }
+            let mut gains = [0f32; NB_BANDS];
+
+            gains.copy_from_slice(&values);
+            self.presets.push(Preset::new(name, gains));
+        }
+        self
+    }
+
+    fn save_presets(&self) {
+        let mut out = String::new();
+        let path = get_presets_path();
+
+        for preset in self.presets[self.nb_builtin..].iter() {
+            let gains: Vec<String> = preset.gains.iter().map(|g| format!("{}", g)).collect();
+
+            out.push_str(&format!("{} = {}\n", preset.name, gains.join(" ")));
+        }
+        match File::create(&path) {
+            Ok(mut f) => {
+                if let Err(e) = f.write_all(out.as_bytes()) {
+                    println!("Cannot write {} : {}", path.display(), e);
+                }
+            }
+            Err(e) => println!("Cannot create {} : {}", path.display(), e),
+        }
+    }
+
+    pub fn attach(&mut self, fmod: &rfmod::Sys) {
+        for (band, frequency) in BANDS.iter().enumerate() {
+            match fmod.create_DSP_by_type(rfmod::DspType::ParamEQ) {
+                Ok(dsp) => {
+                    dsp.set_parameter(PARAM_CENTER, *frequency);
+                    dsp.set_parameter(PARAM_BANDWIDTH, 1f32);
+                    dsp.set_parameter(PARAM_GAIN, to_linear(self.gains[band]));
+                    if let Err(e) = fmod.add_DSP(&dsp) {
+                        println!("FmodSys::add_DSP failed : {:?}", e);
+                    }
+                    self.dsps.push(dsp);
+                }
+                Err(e) => {
+                    println!("FmodSys::create_DSP_by_type failed : {:?}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn get_gains(&self) -> [f32; NB_BANDS] {
+        self.gains
+    }
+
+    pub fn set_gain(&mut self, band: usize, gain: f32) {
+        let gain = gain.max(-MAX_GAIN).min(MAX_GAIN);
+
+        self.gains[band] = gain;
+        self.current = None;
+        if band < self.dsps.len() {
+            self.dsps[band].set_parameter(PARAM_GAIN, to_linear(gain));
+        }
+    }
+
+    pub fn set_preset(&mut self, index: usize) {
+        if index < self.presets.len() {
+            let gains = self.presets[index].gains;
+
+            for (band, gain) in gains.iter().enumerate() {
+                self.set_gain(band, *gain);
+            }
+            self.current = Some(index);
+        }
+    }
+
+    pub fn set_preset_by_name(&mut self, name: &str) -> bool {
+        match self.presets.iter().position(|p| p.name == name) {
+            Some(index) => {
+                self.set_preset(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get_preset_name(&self) -> Option<&str> {
+        match self.current {
+            Some(c) => Some(&self.presets[c].name),
+            None => None,
+        }
+    }
+
+    pub fn next_preset(&mut self) {
+        let index = match self.current {
+            Some(c) if c + 1 < self.presets.len() => c + 1,
+            _ => 0usize,
+        };
+
+        self.set_preset(index);
+    }
+
+    pub fn prev_preset(&mut self) {
+        let index = match self.current {
+            Some(c) if c > 0 => c - 1,
+            _ => self.presets.len() - 1,
+        };
+
+        self.set_preset(index);
+    }
+
+    /// Saves the current gains as a new custom preset and returns its name.
+    pub fn save_preset(&mut self) -> String {
+        let mut number = self.presets.len() - self.nb_builtin + 1;
+        let mut name = format!("custom {}", number);
+
+        while self.presets.iter().any(|p| p.name == name) {
+            number += 1;
+            name = format!("custom {}", number);
+        }
+        self.presets.push(Preset::new(&name, self.gains));
+        self.current = Some(self.presets.len() - 1);
+        self.save_presets();
+        name
+    }
+}
+
+fn to_linear(gain: f32) -> f32 {
+    10f32.powf(gain / 20f32).max(0.05f32).min(3f32)
+}
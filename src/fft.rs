@@ -0,0 +1,357 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use rfmod;
+use std::f32::consts::PI;
+
+pub const MIN_SIZE: usize = 64;
+pub const MAX_SIZE: usize = 8192;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FftWindow {
+    Rect,
+    Triangle,
+    Hamming,
+    Hanning,
+    Blackman,
+    BlackmanHarris,
+}
+
+impl FftWindow {
+    pub fn from_str(s: &str) -> Option<FftWindow> {
+        match s {
+            "rect" => Some(FftWindow::Rect),
+            "triangle" => Some(FftWindow::Triangle),
+            "hamming" => Some(FftWindow::Hamming),
+            "hann" | "hanning" => Some(FftWindow::Hanning),
+            "blackman" => Some(FftWindow::Blackman),
+            "blackman-harris" => Some(FftWindow::BlackmanHarris),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            FftWindow::Rect => "rect",
+            FftWindow::Triangle => "triangle",
+            FftWindow::Hamming => "hamming",
+            FftWindow::Hanning => "hann",
+            FftWindow::Blackman => "blackman",
+            FftWindow::BlackmanHarris => "blackman-harris",
+        }
+    }
+
+    pub fn next(&self) -> FftWindow {
+        match *self {
+            FftWindow::Rect => FftWindow::Triangle,
+            FftWindow::Triangle => FftWindow::Hamming,
+            FftWindow::Hamming => FftWindow::Hanning,
+            FftWindow::Hanning => FftWindow::Blackman,
+            FftWindow::Blackman => FftWindow::BlackmanHarris,
+            FftWindow::BlackmanHarris => FftWindow::Rect,
+        }
+    }
+
+    fn to_fmod(&self) -> rfmod::DspFftWindow {
+        match *self {
+            FftWindow::Rect => rfmod::DspFftWindow::Rect,
+            FftWindow::Triangle => rfmod::DspFftWindow::Triangle,
+            FftWindow::Hamming => rfmod::DspFftWindow::Hamming,
+            FftWindow::Hanning => rfmod::DspFftWindow::Hanning,
+            FftWindow::Blackman => rfmod::DspFftWindow::Blackman,
+            FftWindow::BlackmanHarris => rfmod::DspFftWindow::BlackmanHarris,
+        }
+    }
+
+    /// Returns the weight of the sample `it` out of `size`.
+    fn get_value(&self, it: usize, size: usize) -> f32 {
+        let x = 2f32 * PI * it as f32 / (size - 1) as f32;
+
+        match *self {
+            FftWindow::Rect => 1f32,
+            FftWindow::Triangle => 1f32 - (2f32 * it as f32 / (size - 1) as f32 - 1f32).abs(),
+            FftWindow::Hamming => 0.54f32 - 0.46f32 * x.cos(),
+            FftWindow::Hanning => 0.5f32 - 0.5f32 * x.cos(),
+            FftWindow::Blackman => 0.42f32 - 0.5f32 * x.cos() + 0.08f32 * (2f32 * x).cos(),
+            FftWindow::BlackmanHarris => {
+                0.35875f32 - 0.48829f32 * x.cos() + 0.14128f32 * (2f32 * x).cos()
+                    - 0.01168f32 * (3f32 * x).cos()
+            }
+        }
+    }
+}
+
+/// Which signal is analysed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FftChannels {
+    /// Left and right channels, side by side.
+    Split,
+    /// (L + R) / 2
+    Mid,
+    /// (L - R) / 2
+    Side,
+    /// Every channel mixed together.
+    Sum,
+}
+
+impl FftChannels {
+    pub fn from_str(s: &str) -> Option<FftChannels> {
+        match s {
+            "split" => Some(FftChannels::Split),
+            "mid" => Some(FftChannels::Mid),
+            "side" => Some(FftChannels::Side),
+            "sum" => Some(FftChannels::Sum),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            FftChannels::Split => "split",
+            FftChannels::Mid => "mid",
+            FftChannels::Side => "side",
+            FftChannels::Sum => "sum",
+        }
+    }
+
+    pub fn next(&self) -> FftChannels {
+        match *self {
+            FftChannels::Split => FftChannels::Mid,
+            FftChannels::Mid => FftChannels::Side,
+            FftChannels::Side => FftChannels::Sum,
+            FftChannels::Sum => FftChannels::Split,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FftSettings {
+    /// Number of values of the spectrum (of each channel in split mode).
+    pub size: usize,
+    pub window: FftWindow,
+    pub channels: FftChannels,
+}
+
+impl FftSettings {
+    pub fn new() -> FftSettings {
+        FftSettings {
+            size: 256,
+            window: FftWindow::Rect,
+            channels: FftChannels::Split,
+        }
+    }
+
+    /// Doubles the size, going back to the smallest one after the largest.
+    pub fn next_size(&mut self) {
+        self.size = if self.size >= MAX_SIZE {
+            MIN_SIZE
+        } else {
+            self.size * 2
+        };
+    }
+
+    pub fn to_string(&self) -> String {
+        format!(
+            "FFT {} {} {}",
+            self.size,
+            self.window.to_str(),
+            self.channels.to_str()
+        )
+    }
+}
+
+/// Parses a power of two between `MIN_SIZE` and `MAX_SIZE`.
+pub fn parse_size(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(s) if s >= MIN_SIZE && s <= MAX_SIZE && s.is_power_of_two() => Ok(s),
+        _ => Err(format!(
+            "\"{}\" isn't a power of two between {} and {}",
+            value, MIN_SIZE, MAX_SIZE
+        )),
+    }
+}
+
+/// Returns the spectrum of the channel, the second one being empty unless
+/// both channels are shown. FMOD only gives the magnitudes of each channel,
+/// so the mid and side spectra are computed here from the wave data.
+pub fn get_spectrum(chan: &rfmod::Channel, settings: &FftSettings) -> (Vec<f32>, Vec<f32>) {
+    let size = settings.size;
+    let window = Some(settings.window.to_fmod());
+
+    match settings.channels {
+        FftChannels::Split => match chan.get_spectrum(size, Some(0i32), window) {
+            Ok(left) => match chan.get_spectrum(size, Some(1i32), window) {
+                Ok(right) => (left, right),
+                // mono music
+                Err(_) => (left, Vec::new()),
+            },
+            Err(_) => (Vec::new(), Vec::new()),
+        },
+        FftChannels::Sum => match chan.get_spectrum(size, Some(-1i32), window) {
+            Ok(s) => (s, Vec::new()),
+            Err(_) => (Vec::new(), Vec::new()),
+        },
+        FftChannels::Mid | FftChannels::Side => {
+            let left = match chan.get_wave_data(size * 2, 0i32) {
+                Ok(w) => w,
+                Err(_) => return (Vec::new(), Vec::new()),
+            };
+            let right = match chan.get_wave_data(size * 2, 1i32) {
+                Ok(w) => w,
+                Err(_) => left.clone(),
+            };
+            let side = settings.channels == FftChannels::Side;
+            let samples: Vec<f32> = left
+                .iter()
+                .zip(right.iter())
+                .map(|(l, r)| if side { (l - r) / 2f32 } else { (l + r) / 2f32 })
+                .collect();
+
+            (compute(&samples, settings.window), Vec::new())
+        }
+    }
+}
+
+/// Returns the magnitudes (from 0 to 1) of the first half of the FFT of
+/// `samples`, whose length has to be a power of two.
+pub fn compute(samples: &[f32], window: FftWindow) -> Vec<f32> {
+    let size = samples.len();
+
+    if size < 2 || !size.is_power_of_two() {
+        return Vec::new();
+    }
+    let mut re = Vec::with_capacity(size);
+    let mut im = vec![0f32; size];
+    let mut window_sum = 0f32;
+
+    for (it, sample) in samples.iter().enumerate() {
+        let weight = window.get_value(it, size);
+
+        window_sum += weight;
+        re.push(sample * weight);
+    }
+    fft(&mut re, &mut im);
+    re.iter()
+        .zip(im.iter())
+        .take(size / 2)
+        .map(|(r, i)| ((r * r + i * i).sqrt() * 2f32 / window_sum).min(1f32))
+        .collect()
+}
+
+/// In place radix-2 FFT.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let size = re.len();
+    let mut j = 0usize;
+
+    // bit reversal permutation
+    for it in 1..size {
+        let mut bit = size >> 1;
+
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if it < j {
+            re.swap(it, j);
+            im.swap(it, j);
+        }
+    }
+    let mut len = 2usize;
+
+    while len <= size {
+        let angle = -2f32 * PI / len as f32;
+
+        for start in (0..size).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compute, FftWindow};
+    use std::f32::consts::PI;
+
+    /// A sine of amplitude `amplitude` completing `cycles` periods.
+    fn sine(size: usize, cycles: usize, amplitude: f32) -> Vec<f32> {
+        (0..size)
+            .map(|it| amplitude * (2f32 * PI * (cycles * it) as f32 / size as f32).sin())
+            .collect()
+    }
+
+    fn get_peak(spectrum: &[f32]) -> usize {
+        let mut peak = 0usize;
+
+        for (bin, value) in spectrum.iter().enumerate() {
+            if *value > spectrum[peak] {
+                peak = bin;
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn sine_in_its_bin() {
+        let spectrum = compute(&sine(256, 8, 0.5f32), FftWindow::Rect);
+
+        assert_eq!(spectrum.len(), 128);
+        assert_eq!(get_peak(&spectrum), 8);
+        assert!((spectrum[8] - 0.5f32).abs() < 0.01f32);
+        for (bin, value) in spectrum.iter().enumerate() {
+            if bin != 8 {
+                assert!(*value < 0.01f32, "bin {} : {}", bin, value);
+            }
+        }
+    }
+
+    #[test]
+    fn windowed_sine() {
+        for window in [FftWindow::Hanning, FftWindow::BlackmanHarris].iter() {
+            let spectrum = compute(&sine(1024, 100, 1f32), *window);
+
+            assert_eq!(get_peak(&spectrum), 100);
+            assert!((spectrum[100] - 1f32).abs() < 0.05f32);
+            assert!(spectrum[200] < 0.01f32);
+        }
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        assert!(compute(&[0f32; 100], FftWindow::Rect).is_empty());
+        assert!(compute(&[0f32; 1], FftWindow::Rect).is_empty());
+    }
+}
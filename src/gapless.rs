@@ -0,0 +1,294 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use mp4;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+// samples added by the mp3 decoder on top of the encoder delay
+const DECODER_DELAY: u32 = 529;
+// bytes read after the ID3v2 tag, enough for the Xing/LAME frame
+const FIRST_FRAME_SIZE: u64 = 4096;
+
+/// Silence added by the encoder at the beginning (`delay`) and at the end
+/// (`padding`) of a track, in PCM samples.
+#[derive(Clone, Copy, Debug)]
+pub struct EncoderTrim {
+    pub delay: u32,
+    pub padding: u32,
+}
+
+/// Only reads the parts of the file where the gapless information can be:
+/// the "udta" atom of MP4 files, or the ID3v2 tag and the first frame of
+/// MP3 files.
+pub fn read_trim(path: &str) -> Option<EncoderTrim> {
+    let mut file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return None,
+    };
+
+    if let Some(moov) = mp4::read_top_atom(&mut file, b"moov") {
+        return match mp4::find_atom(&moov, &[b"udta"]) {
+            Some(udta) => read_itunsmpb(udta),
+            None => None,
+        };
+    }
+    let data = match read_mp3_start(&mut file) {
+        Some(d) => d,
+        None => return None,
+    };
+
+    match read_itunsmpb(&data) {
+        Some(t) => Some(t),
+        None => read_lame(&data),
+    }
+}
+
+/// Returns the ID3v2 tag (if any) followed by the beginning of the audio.
+fn read_mp3_start(file: &mut File) -> Option<Vec<u8>> {
+    let mut header = [0u8; 10];
+
+    if file.seek(SeekFrom::Start(0)).is_err() || file.read_exact(&mut header).is_err() {
+        return None;
+    }
+    let mut size = FIRST_FRAME_SIZE;
+
+    if &header[0..3] == b"ID3" {
+        size += get_id3_size(&header) as u64;
+    }
+    let mut data = Vec::new();
+
+    if file.seek(SeekFrom::Start(0)).is_err() {
+        return None;
+    }
+    if file.by_ref().take(size).read_to_end(&mut data).is_err() {
+        return None;
+    }
+    Some(data)
+}
+
+/// Size of the ID3v2 tag with its header (and footer), the size being
+/// stored as a syncsafe integer.
+pub fn get_id3_size(data: &[u8]) -> usize {
+    let mut size = read_syncsafe(&data[6..]) + 10;
+
+    if data[5] & 0x10 != 0 {
+        size += 10;
+    }
+    size
+}
+
+/// Reads a 28 bits integer stored in 4 bytes whose highest bit is unused.
+pub fn read_syncsafe(data: &[u8]) -> usize {
+    (data[0] as usize & 0x7f) << 21
+        | (data[1] as usize & 0x7f) << 14
+        | (data[2] as usize & 0x7f) << 7
+        | (data[3] as usize & 0x7f)
+}
+
+fn find(data: &[u8], pattern: &[u8], from: usize) -> Option<usize> {
+    if data.len() < pattern.len() {
+        return None;
+    }
+    let mut it = from;
+
+    while it + pattern.len() <= data.len() {
+        if &data[it..it + pattern.len()] == pattern {
+            return Some(it);
+        }
+        it += 1;
+    }
+    None
+}
+
+/// iTunes stores the gapless information as a string of hexadecimal words:
+/// " 00000000 00000840 000001CA 0000000000A3E76 ...". The second word is the
+/// delay and the third one the padding.
+fn read_itunsmpb(data: &[u8]) -> Option<EncoderTrim> {
+    let start = match find(data, b"iTunSMPB", 0) {
+        Some(s) => s + 8,
+        None => return None,
+    };
+    let end = if start + 256 > data.len() {
+        data.len()
+    } else {
+        start + 256
+    };
+    let mut text = String::new();
+    let mut it = start;
+
+    // skips the atom/frame headers until the value starts
+    while it < end && !(data[it] == b' ' && it + 1 < end && data[it + 1] == b'0') {
+        it += 1;
+    }
+    while it < end && (data[it] == b' ' || (data[it] as char).is_digit(16)) {
+        text.push(data[it] as char);
+        it += 1;
+    }
+    let words: Vec<&str> = text.split_whitespace().collect();
+
+    if words.len() < 4 {
+        return None;
+    }
+    match (
+        u32::from_str_radix(words[1], 16),
+        u32::from_str_radix(words[2], 16),
+    ) {
+        (Ok(delay), Ok(padding)) => Some(EncoderTrim {
+            delay: delay,
+            padding: padding,
+        }),
+        _ => None,
+    }
+}
+
+fn read_lame(data: &[u8]) -> Option<EncoderTrim> {
+    let mut offset = 0usize;
+
+    // skips the ID3v2 tag
+    if data.len() > 10 && &data[0..3] == b"ID3" {
+        offset = get_id3_size(data);
+    }
+    if offset + 4 > data.len() || data[offset] != 0xFF || data[offset + 1] & 0xE0 != 0xE0 {
+        return None;
+    }
+    let limit = if offset + 200 > data.len() {
+        data.len()
+    } else {
+        offset + 200
+    };
+    let xing = match find(&data[..limit], b"Xing", offset) {
+        Some(x) => x,
+        None => match find(&data[..limit], b"Info", offset) {
+            Some(x) => x,
+            None => return None,
+        },
+    };
+    if xing + 8 > data.len() {
+        return None;
+    }
+    let flags = mp4::read_u32(&data[xing + 4..]);
+    let mut lame = xing + 8;
+
+    if flags & 1 != 0 {
+        lame += 4;
+    }
+    if flags & 2 != 0 {
+        lame += 4;
+    }
+    if flags & 4 != 0 {
+        lame += 100;
+    }
+    if flags & 8 != 0 {
+        lame += 4;
+    }
+    if lame + 24 > data.len() {
+        return None;
+    }
+    match &data[lame..lame + 4] {
+        b"LAME" | b"Lavf" | b"Lavc" | b"GOGO" => {}
+        _ => return None,
+    }
+    // encoder version (9), revision (1), lowpass (1), peak (4), radio gain (2),
+    // audiophile gain (2), flags (1), bitrate (1) then 12 bits for each value
+    let delay = (data[lame + 21] as u32) << 4 | (data[lame + 22] as u32) >> 4;
+    let padding = (data[lame + 22] as u32 & 0x0f) << 8 | data[lame + 23] as u32;
+
+    Some(EncoderTrim {
+        delay: delay + DECODER_DELAY,
+        padding: if padding > DECODER_DELAY {
+            padding - DECODER_DELAY
+        } else {
+            0u32
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_itunsmpb, read_lame, DECODER_DELAY};
+
+    /// ID3v2 header of an empty tag, then a MPEG-1 Layer III frame holding a
+    /// Xing header with all its fields and a LAME tag.
+    fn lame_frame(delay: u32, padding: u32) -> Vec<u8> {
+        let mut data = b"ID3\x03\x00\x00\x00\x00\x00\x00".to_vec();
+
+        data.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x64]);
+        // side information
+        data.extend_from_slice(&[0u8; 32]);
+        data.extend_from_slice(b"Xing\x00\x00\x00\x0F");
+        // frames, bytes, TOC and quality
+        data.extend_from_slice(&[0u8; 4 + 4 + 100 + 4]);
+        data.extend_from_slice(b"LAME3.99r");
+        data.extend_from_slice(&[0u8; 12]);
+        data.push((delay >> 4) as u8);
+        data.push(((delay & 0x0f) << 4 | padding >> 8) as u8);
+        data.push(padding as u8);
+        data.extend_from_slice(&[0u8; 100]);
+        data
+    }
+
+    #[test]
+    fn lame_tag() {
+        let trim = read_lame(&lame_frame(576, 1000)).unwrap();
+
+        assert_eq!(trim.delay, 576 + DECODER_DELAY);
+        assert_eq!(trim.padding, 1000 - DECODER_DELAY);
+    }
+
+    #[test]
+    fn lame_small_padding() {
+        let trim = read_lame(&lame_frame(576, 100)).unwrap();
+
+        assert_eq!(trim.padding, 0);
+    }
+
+    #[test]
+    fn lame_without_frame() {
+        let mut data = lame_frame(576, 1000);
+
+        // breaks the frame sync
+        data[10] = 0;
+        assert!(read_lame(&data).is_none());
+        assert!(read_lame(b"not a mp3 file").is_none());
+    }
+
+    #[test]
+    fn itunsmpb() {
+        let mut data = b"\x00\x00\x00\x1c----\x00\x00\x00\x14nameiTunSMPB".to_vec();
+
+        data.extend_from_slice(b"\x00\x00\x00\x5cdata\x00\x00\x00\x01\x00\x00\x00\x00");
+        data.extend_from_slice(b" 00000000 00000840 000001CA 0000000000A3E76 00000000");
+        let trim = read_itunsmpb(&data).unwrap();
+
+        assert_eq!(trim.delay, 0x840);
+        assert_eq!(trim.padding, 0x1CA);
+    }
+
+    #[test]
+    fn itunsmpb_invalid() {
+        assert!(read_itunsmpb(b"no gapless information").is_none());
+        assert!(read_itunsmpb(b"iTunSMPB 00000000 00000840").is_none());
+    }
+}
@@ -0,0 +1,293 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use bookmarks::Bookmark;
+use graphic_element::GraphicElement;
+use graphic_timer;
+use sfml::graphics::{Color, Font, RectangleShape, RenderTarget, RenderWindow, Text};
+use sfml::graphics::{Shape, Transformable};
+use sfml::system::Vector2f;
+
+const LINE_HEIGHT: f32 = 20f32;
+
+/// Lists the bookmarks of the current music. Clicking on one goes to it.
+pub struct GraphicBookmarks<'b> {
+    times: Vec<usize>,
+    texts: Vec<Text<'b>>,
+    empty: Text<'b>,
+    current: Option<usize>,
+    hover_element: Option<usize>,
+    // index of the first displayed bookmark
+    first: usize,
+    clicked_time: Option<usize>,
+    cleaner: RectangleShape<'b>,
+    font: &'b Font,
+    pub need_to_draw: bool,
+    name: String,
+}
+
+impl<'b> GraphicBookmarks<'b> {
+    fn init(mut self, position: &Vector2f) -> GraphicBookmarks<'b> {
+        self.cleaner.set_fill_color(&Color::rgb(0, 0, 0));
+        self.cleaner.set_outline_color(&Color::rgb(255, 255, 255));
+        self.cleaner.set_outline_thickness(1f32);
+        self.empty.set_fill_color(&Color::rgb(120, 120, 120));
+        self.set_position(position);
+        self
+    }
+
+    pub fn set_bookmarks(&mut self, bookmarks: &[Bookmark]) {
+        self.texts.clear();
+        self.times.clear();
+        for bookmark in bookmarks.iter() {
+            let mut text = Text::new(
+                &format!(
+                    "{}  {}",
+                    graphic_timer::format_time(bookmark.time),
+                    bookmark.name
+                ),
+                self.font,
+                16,
+            );
+
+            text.set_fill_color(&Color::rgb(255, 255, 255));
+            self.texts.push(text);
+            self.times.push(bookmark.time);
+        }
+        self.current = None;
+        self.hover_element = None;
+        self.first = 0usize;
+        self.need_to_draw = true;
+    }
+
+    /// Highlights the last bookmark before `position` (in ms).
+    pub fn update_time(&mut self, position: usize) {
+        let current = self.times.iter().rposition(|t| *t <= position);
+
+        if current != self.current {
+            match self.current {
+                Some(c) => self.texts[c].set_fill_color(&Color::rgb(255, 255, 255)),
+                None => {}
+            }
+            match current {
+                Some(c) => {
+                    self.texts[c].set_fill_color(&Color::rgb(255, 125, 25));
+                    // keeps the current bookmark visible
+                    let nb_lines = self.get_nb_lines();
+
+                    if c < self.first {
+                        self.first = c;
+                    } else if c >= self.first + nb_lines {
+                        self.first = c + 1 - nb_lines;
+                    }
+                }
+                None => {}
+            }
+            self.current = current;
+            self.need_to_draw = true;
+        }
+    }
+
+    /// Returns the time of the last clicked bookmark, if any.
+    pub fn get_clicked_time(&mut self) -> Option<usize> {
+        self.clicked_time.take()
+    }
+
+    /// Returns the index of the bookmark under `position`.
+    pub fn get_bookmark_at(&self, position: &Vector2f) -> Option<usize> {
+        let offset = position.y - self.cleaner.position().y - 2f32;
+
+        if offset < 0f32 {
+            return None;
+        }
+        let line = self.first + (offset / LINE_HEIGHT) as usize;
+
+        if line < self.texts.len() {
+            Some(line)
+        } else {
+            None
+        }
+    }
+
+    fn get_nb_lines(&self) -> usize {
+        match (self.cleaner.size().y / LINE_HEIGHT) as usize {
+            0 => 1usize,
+            n => n,
+        }
+    }
+}
+
+impl<'b> GraphicElement<'b> for GraphicBookmarks<'b> {
+    fn new_init(
+        size: &Vector2f,
+        position: &Vector2f,
+        unused: &Color,
+        font: Option<&'b Font>,
+    ) -> GraphicBookmarks<'b> {
+        let font = match font {
+            Some(f) => f,
+            None => panic!("GraphicBookmarks needs Font"),
+        };
+        GraphicBookmarks {
+            times: Vec::new(),
+            texts: Vec::new(),
+            empty: Text::new("No bookmarks", font, 16),
+            current: None,
+            hover_element: None,
+            first: 0usize,
+            clicked_time: None,
+            cleaner: RectangleShape::with_size(Vector2f {
+                x: size.x - 2f32,
+                y: size.y - 2f32,
+            }),
+            font: font,
+            need_to_draw: true,
+            name: String::new(),
+        }.init(position)
+    }
+
+    fn set_position(&mut self, position: &Vector2f) {
+        self.cleaner.set_position(Vector2f {
+            x: position.x + 1f32,
+            y: position.y + 1f32,
+        });
+        let size = self.empty.local_bounds().width;
+        self.empty.set_position(Vector2f {
+            x: (self.cleaner.size().x - size) / 2f32 + self.cleaner.position().x,
+            y: (self.cleaner.size().y - LINE_HEIGHT) / 2f32 + self.cleaner.position().y,
+        });
+        self.need_to_draw = true;
+    }
+
+    fn get_position(&self) -> Vector2f {
+        let tmp = self.cleaner.position();
+
+        Vector2f {
+            x: tmp.x - 1f32,
+            y: tmp.y - 1f32,
+        }
+    }
+
+    fn set_size(&mut self, size: &Vector2f) {
+        let pos = self.get_position();
+
+        self.cleaner.set_size(Vector2f {
+            x: size.x - 2f32,
+            y: size.y - 2f32,
+        });
+        self.set_position(&pos);
+    }
+
+    fn get_size(&self) -> Vector2f {
+        let tmp = self.cleaner.size();
+
+        Vector2f {
+            x: tmp.x + 2f32,
+            y: tmp.y + 2f32,
+        }
+    }
+
+    fn draw(&mut self, win: &mut RenderWindow) {
+        win.draw(&self.cleaner);
+        if self.texts.len() == 0 {
+            win.draw(&self.empty);
+        } else {
+            let nb_lines = self.get_nb_lines();
+            let mut y = self.cleaner.position().y + 2f32;
+            let x = self.cleaner.position().x + 4f32;
+
+            for text in self.texts.iter_mut().skip(self.first).take(nb_lines) {
+                text.set_position(Vector2f { x: x, y: y });
+                win.draw(text);
+                y += LINE_HEIGHT;
+            }
+        }
+        self.need_to_draw = false;
+    }
+
+    fn is_inside(&self, pos: &Vector2f) -> bool {
+        pos.y >= self.cleaner.position().y
+            && pos.y <= self.cleaner.position().y + self.cleaner.size().y
+            && pos.x >= self.cleaner.position().x
+            && pos.x <= self.cleaner.position().x + self.cleaner.size().x
+    }
+
+    fn get_min_size(&self) -> Vector2f {
+        Vector2f { x: 50f32, y: 50f32 }
+    }
+
+    fn get_max_size(&self) -> Option<Vector2f> {
+        None
+    }
+
+    fn cursor_moved(&mut self, position: &Vector2f) {
+        let line = self.get_bookmark_at(position);
+
+        if line != self.hover_element {
+            match self.hover_element {
+                Some(h) if Some(h) != self.current => {
+                    self.texts[h].set_fill_color(&Color::rgb(255, 255, 255))
+                }
+                _ => {}
+            }
+            match line {
+                Some(l) if Some(l) != self.current => {
+                    self.texts[l].set_fill_color(&Color::rgb(255, 175, 100))
+                }
+                _ => {}
+            }
+            self.hover_element = line;
+            self.need_to_draw = true;
+        }
+    }
+
+    fn clicked(&mut self, position: &Vector2f) {
+        self.clicked_time = match self.get_bookmark_at(position) {
+            Some(b) => Some(self.times[b]),
+            None => None,
+        };
+    }
+
+    fn mouse_leave(&mut self) {
+        match self.hover_element {
+            Some(h) => {
+                if Some(h) != self.current {
+                    self.texts[h].set_fill_color(&Color::rgb(255, 255, 255));
+                }
+                self.hover_element = None;
+                self.need_to_draw = true;
+            }
+            None => {}
+        }
+    }
+
+    fn set_element_name(&mut self, name: &String) {
+        self.name = name.clone();
+    }
+
+    fn get_element_name<'a>(&'a self) -> &'a String {
+        &self.name
+    }
+}
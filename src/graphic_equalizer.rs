@@ -0,0 +1,346 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use equalizer::{BANDS, MAX_GAIN, NB_BANDS};
+use graphic_button::GraphicButton;
+use graphic_element::GraphicElement;
+use progress_bar::ProgressBar;
+use sfml::graphics::{Color, Font, RectangleShape, RenderTarget, RenderWindow, Text};
+use sfml::graphics::{Shape, Transformable};
+use sfml::system::Vector2f;
+
+pub enum EqualizerAction {
+    SetGain(usize, f32),
+    PrevPreset,
+    NextPreset,
+    SavePreset,
+    ToggleTrack,
+}
+
+pub struct GraphicEqualizer<'b> {
+    bars: Vec<ProgressBar<'b>>,
+    labels: Vec<Text<'b>>,
+    values: Vec<Text<'b>>,
+    zero_line: RectangleShape<'b>,
+    preset: Text<'b>,
+    prev_button: GraphicButton<'b>,
+    next_button: GraphicButton<'b>,
+    save_button: GraphicButton<'b>,
+    track_button: GraphicButton<'b>,
+    cleaner: RectangleShape<'b>,
+    action: Option<EqualizerAction>,
+    pub need_to_draw: bool,
+    name: String,
+}
+
+impl<'b> GraphicEqualizer<'b> {
+    fn init(mut self, position: &Vector2f, color: &Color, font: &'b Font) -> GraphicEqualizer<'b> {
+        for band in 0..NB_BANDS {
+            let mut bar: ProgressBar<'b> = GraphicElement::new_init(
+                &Vector2f { x: 24f32, y: 100f32 },
+                &Vector2f { x: 0f32, y: 0f32 },
+                color,
+                None,
+            );
+
+            bar.set_vertical(true);
+            bar.set_maximum((MAX_GAIN * 20f32) as usize);
+            bar.set_progress((MAX_GAIN * 10f32) as usize);
+            self.bars.push(bar);
+            self.labels.push(Text::new(
+                &if BANDS[band] >= 1000f32 {
+                    format!("{}k", BANDS[band] as usize / 1000)
+                } else {
+                    format!("{}", BANDS[band] as usize)
+                },
+                font,
+                14,
+            ));
+            self.values.push(Text::new("0.0", font, 12));
+        }
+        self.prev_button.set_label(&"<".to_owned());
+        self.next_button.set_label(&">".to_owned());
+        self.save_button.set_label(&"Save".to_owned());
+        self.track_button.set_label(&"Track".to_owned());
+        self.zero_line.set_fill_color(&Color::rgb(80, 80, 80));
+        self.cleaner.set_fill_color(&Color::rgb(0, 0, 0));
+        self.set_position(position);
+        self
+    }
+
+    pub fn set_gains(&mut self, gains: &[f32; NB_BANDS]) {
+        for (band, gain) in gains.iter().enumerate() {
+            self.bars[band].set_progress(((gain + MAX_GAIN) * 10f32).round() as usize);
+            self.values[band].set_string(&format!("{:.1}", gain));
+        }
+        self.place_texts();
+        self.need_to_draw = true;
+    }
+
+    pub fn set_preset_name(&mut self, name: Option<&str>) {
+        self.preset.set_string(match name {
+            Some(n) => n,
+            None => "(custom)",
+        });
+        self.place_texts();
+        self.need_to_draw = true;
+    }
+
+    /// Tells if the current music has its own equalizer settings.
+    pub fn set_track_override(&mut self, has_override: bool) {
+        self.track_button.set_pushed(has_override);
+        self.need_to_draw = true;
+    }
+
+    pub fn get_action(&mut self) -> Option<EqualizerAction> {
+        self.action.take()
+    }
+
+    fn place_texts(&mut self) {
+        let pos = self.cleaner.position();
+        let size = self.preset.local_bounds().width;
+
+        self.preset.set_position(Vector2f {
+            x: pos.x + (self.cleaner.size().x - size) / 2f32,
+            y: pos.y + 14f32,
+        });
+        for band in 0..NB_BANDS {
+            let bar_pos = self.bars[band].get_position();
+            let bar_size = self.bars[band].get_size();
+            let width = self.labels[band].local_bounds().width;
+
+            self.labels[band].set_position(Vector2f {
+                x: bar_pos.x + (bar_size.x - width) / 2f32,
+                y: bar_pos.y + bar_size.y + 4f32,
+            });
+            let width = self.values[band].local_bounds().width;
+
+            self.values[band].set_position(Vector2f {
+                x: bar_pos.x + (bar_size.x - width) / 2f32,
+                y: bar_pos.y - 18f32,
+            });
+        }
+    }
+}
+
+impl<'b> GraphicElement<'b> for GraphicEqualizer<'b> {
+    fn new_init(
+        size: &Vector2f,
+        position: &Vector2f,
+        color: &Color,
+        font: Option<&'b Font>,
+    ) -> GraphicEqualizer<'b> {
+        let font = match font {
+            Some(f) => f,
+            None => panic!("GraphicEqualizer needs Font"),
+        };
+        let button_size = Vector2f { x: 40f32, y: 30f32 };
+        let origin = Vector2f { x: 0f32, y: 0f32 };
+
+        GraphicEqualizer {
+            bars: Vec::with_capacity(NB_BANDS),
+            labels: Vec::with_capacity(NB_BANDS),
+            values: Vec::with_capacity(NB_BANDS),
+            zero_line: RectangleShape::with_size(Vector2f { x: 0f32, y: 1f32 }),
+            preset: Text::new("flat", font, 20),
+            prev_button: GraphicElement::new_init(&button_size, &origin, color, Some(font)),
+            next_button: GraphicElement::new_init(&button_size, &origin, color, Some(font)),
+            save_button: GraphicElement::new_init(
+                &Vector2f { x: 100f32, y: 30f32 },
+                &origin,
+                color,
+                Some(font),
+            ),
+            track_button: GraphicElement::new_init(
+                &Vector2f { x: 100f32, y: 30f32 },
+                &origin,
+                color,
+                Some(font),
+            ),
+            cleaner: RectangleShape::with_size(Vector2f {
+                x: size.x,
+                y: size.y,
+            }),
+            action: None,
+            need_to_draw: true,
+            name: String::new(),
+        }.init(position, color, font)
+    }
+
+    fn set_position(&mut self, position: &Vector2f) {
+        let size = self.cleaner.size();
+        let bar_height = size.y - 150f32;
+        let step = size.x / NB_BANDS as f32;
+
+        self.cleaner.set_position(Vector2f {
+            x: position.x,
+            y: position.y,
+        });
+        self.prev_button.set_position(&Vector2f {
+            x: position.x + 10f32,
+            y: position.y + 10f32,
+        });
+        self.next_button.set_position(&Vector2f {
+            x: position.x + size.x - 50f32,
+            y: position.y + 10f32,
+        });
+        self.save_button.set_position(&Vector2f {
+            x: position.x + size.x / 2f32 - 105f32,
+            y: position.y + 50f32,
+        });
+        self.track_button.set_position(&Vector2f {
+            x: position.x + size.x / 2f32 + 5f32,
+            y: position.y + 50f32,
+        });
+        for (band, bar) in self.bars.iter_mut().enumerate() {
+            bar.set_size(&Vector2f {
+                x: 24f32,
+                y: bar_height,
+            });
+            bar.set_position(&Vector2f {
+                x: position.x + step * band as f32 + (step - 24f32) / 2f32,
+                y: position.y + 115f32,
+            });
+        }
+        self.zero_line.set_size(Vector2f {
+            x: size.x - 20f32,
+            y: 1f32,
+        });
+        self.zero_line.set_position(Vector2f {
+            x: position.x + 10f32,
+            y: position.y + 115f32 + bar_height / 2f32,
+        });
+        self.place_texts();
+        self.need_to_draw = true;
+    }
+
+    fn get_position(&self) -> Vector2f {
+        self.cleaner.position()
+    }
+
+    fn set_size(&mut self, size: &Vector2f) {
+        let pos = self.cleaner.position();
+
+        self.cleaner.set_size(Vector2f {
+            x: size.x,
+            y: size.y,
+        });
+        self.set_position(&pos);
+    }
+
+    fn get_size(&self) -> Vector2f {
+        self.cleaner.size()
+    }
+
+    fn draw(&mut self, win: &mut RenderWindow) {
+        win.draw(&self.cleaner);
+        win.draw(&self.zero_line);
+        self.prev_button.draw(win);
+        self.next_button.draw(win);
+        self.save_button.draw(win);
+        self.track_button.draw(win);
+        win.draw(&self.preset);
+        for bar in self.bars.iter_mut() {
+            bar.draw(win);
+        }
+        for text in self.labels.iter().chain(self.values.iter()) {
+            win.draw(text);
+        }
+        self.need_to_draw = false;
+    }
+
+    fn is_inside(&self, pos: &Vector2f) -> bool {
+        pos.y >= self.cleaner.position().y
+            && pos.y <= self.cleaner.position().y + self.cleaner.size().y
+            && pos.x >= self.cleaner.position().x
+            && pos.x <= self.cleaner.position().x + self.cleaner.size().x
+    }
+
+    fn clicked(&mut self, position: &Vector2f) {
+        if self.prev_button.is_inside(position) {
+            self.action = Some(EqualizerAction::PrevPreset);
+        } else if self.next_button.is_inside(position) {
+            self.action = Some(EqualizerAction::NextPreset);
+        } else if self.save_button.is_inside(position) {
+            self.action = Some(EqualizerAction::SavePreset);
+        } else if self.track_button.is_inside(position) {
+            self.action = Some(EqualizerAction::ToggleTrack);
+        } else {
+            for (band, bar) in self.bars.iter_mut().enumerate() {
+                if bar.is_inside(position) {
+                    bar.clicked(position);
+                    self.action = Some(EqualizerAction::SetGain(
+                        band,
+                        bar.get_real_value() as f32 / 10f32 - MAX_GAIN,
+                    ));
+                    break;
+                }
+            }
+        }
+        self.need_to_draw = true;
+    }
+
+    fn cursor_moved(&mut self, position: &Vector2f) {
+        for button in vec![
+            &mut self.prev_button,
+            &mut self.next_button,
+            &mut self.save_button,
+            &mut self.track_button,
+        ] {
+            if button.is_inside(position) {
+                button.cursor_moved(position);
+            } else {
+                button.mouse_leave();
+            }
+        }
+        self.need_to_draw = true;
+    }
+
+    fn mouse_leave(&mut self) {
+        self.prev_button.mouse_leave();
+        self.next_button.mouse_leave();
+        self.save_button.mouse_leave();
+        self.track_button.mouse_leave();
+    }
+
+    fn get_min_size(&self) -> Vector2f {
+        Vector2f {
+            x: 300f32,
+            y: 250f32,
+        }
+    }
+
+    fn get_max_size(&self) -> Option<Vector2f> {
+        None
+    }
+
+    fn set_element_name(&mut self, name: &String) {
+        self.name = name.clone();
+    }
+
+    fn get_element_name<'a>(&'a self) -> &'a String {
+        &self.name
+    }
+}
@@ -0,0 +1,268 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use graphic_element::GraphicElement;
+use sfml::graphics::Shape;
+use sfml::graphics::Transformable;
+use sfml::graphics::{Color, Font, PrimitiveType, RectangleShape, RenderTarget, RenderWindow};
+use sfml::graphics::{Text, Vertex, VertexArray};
+use sfml::system::Vector2f;
+
+// height of the correlation meter, under the Lissajous figure
+const METER_HEIGHT: f32 = 30f32;
+// part of the new correlation taken in each frame
+const SMOOTHING: f32 = 0.2f32;
+
+/// Goniometer: each pair of samples is drawn as a point, mono sound giving a
+/// vertical line and out of phase channels a horizontal one. The meter under
+/// it shows the phase correlation, from -1 to 1.
+pub struct GraphicGoniometer<'b> {
+    cleaner: RectangleShape<'b>,
+    axes: Vec<RectangleShape<'b>>,
+    points: VertexArray,
+    meter: RectangleShape<'b>,
+    marker: RectangleShape<'b>,
+    correlation_text: Text<'b>,
+    correlation: f32,
+    pub need_to_draw: bool,
+    name: String,
+}
+
+impl<'b> GraphicGoniometer<'b> {
+    fn init(mut self, position: &Vector2f) -> GraphicGoniometer<'b> {
+        self.cleaner.set_fill_color(&Color::rgb(0, 0, 0));
+        for _ in 0..2 {
+            let mut line = RectangleShape::new();
+
+            line.set_fill_color(&Color::rgb(50, 50, 50));
+            self.axes.push(line);
+        }
+        self.meter.set_fill_color(&Color::rgb(30, 30, 30));
+        self.meter.set_outline_color(&Color::rgb(255, 255, 255));
+        self.meter.set_outline_thickness(1f32);
+        self.marker.set_fill_color(&Color::rgb(255, 255, 255));
+        self.set_position(position);
+        self
+    }
+
+    /// Returns the center and the radius of the figure.
+    fn get_figure(&self) -> (Vector2f, f32) {
+        let position = self.cleaner.position();
+        let size = self.cleaner.size();
+        let height = size.y - METER_HEIGHT;
+
+        (
+            Vector2f {
+                x: position.x + size.x / 2f32,
+                y: position.y + height / 2f32,
+            },
+            size.x.min(height) / 2f32 - 5f32,
+        )
+    }
+
+    pub fn update_wave(&mut self, data_left: &[f32], data_right: &[f32]) {
+        let (center, radius) = self.get_figure();
+        let mut points = VertexArray::new(PrimitiveType::Points, 0);
+        let (mut sum_lr, mut sum_ll, mut sum_rr) = (0f32, 0f32, 0f32);
+
+        for (left, right) in data_left.iter().zip(data_right.iter()) {
+            let (left, right) = (left.max(-1f32).min(1f32), right.max(-1f32).min(1f32));
+            // rotated by 45 degrees: mid goes up and side goes right
+            let side = (right - left) / 2f32.sqrt();
+            let mid = (left + right) / 2f32.sqrt();
+
+            points.append(&Vertex::with_pos_color(
+                Vector2f {
+                    x: center.x + side * radius,
+                    y: center.y - mid * radius,
+                },
+                Color::rgba(100, 220, 80, 160),
+            ));
+            sum_lr += left * right;
+            sum_ll += left * left;
+            sum_rr += right * right;
+        }
+        let correlation = if sum_ll > 0f32 && sum_rr > 0f32 {
+            sum_lr / (sum_ll * sum_rr).sqrt()
+        } else {
+            0f32
+        };
+
+        let correlation = self.correlation + (correlation - self.correlation) * SMOOTHING;
+
+        self.points = points;
+        self.set_correlation(correlation);
+        self.need_to_draw = true;
+    }
+
+    fn set_correlation(&mut self, correlation: f32) {
+        let position = self.meter.position();
+        let size = self.meter.size();
+
+        self.correlation = correlation.max(-1f32).min(1f32);
+        self.marker.set_position(Vector2f {
+            x: position.x + (self.correlation + 1f32) / 2f32 * size.x - 1f32,
+            y: position.y,
+        });
+        self.marker.set_fill_color(&if self.correlation < 0f32 {
+            Color::rgb(255, 60, 60)
+        } else {
+            Color::rgb(100, 220, 80)
+        });
+        self.correlation_text.set_string(&format!("{:+.2}", self.correlation));
+    }
+}
+
+impl<'b> GraphicElement<'b> for GraphicGoniometer<'b> {
+    fn new_init(
+        size: &Vector2f,
+        position: &Vector2f,
+        unused: &Color,
+        font: Option<&'b Font>,
+    ) -> GraphicGoniometer<'b> {
+        GraphicGoniometer {
+            cleaner: RectangleShape::with_size(Vector2f {
+                x: size.x,
+                y: size.y,
+            }),
+            axes: Vec::new(),
+            points: VertexArray::new(PrimitiveType::Points, 0),
+            meter: RectangleShape::new(),
+            marker: RectangleShape::new(),
+            correlation_text: Text::new(
+                "",
+                match font {
+                    Some(f) => f,
+                    None => panic!("GraphicGoniometer needs Font"),
+                },
+                12,
+            ),
+            correlation: 0f32,
+            need_to_draw: true,
+            name: String::new(),
+        }.init(position)
+    }
+
+    fn set_position(&mut self, position: &Vector2f) {
+        let size = self.cleaner.size();
+
+        self.cleaner.set_position(Vector2f {
+            x: position.x,
+            y: position.y,
+        });
+        let (center, radius) = self.get_figure();
+
+        // the left and right channels axes
+        for (it, line) in self.axes.iter_mut().enumerate() {
+            line.set_size(Vector2f {
+                x: radius * 2f32,
+                y: 1f32,
+            });
+            line.set_origin(Vector2f { x: radius, y: 0f32 });
+            line.set_position(center);
+            line.set_rotation(if it == 0 { 45f32 } else { -45f32 });
+        }
+        self.meter.set_size(Vector2f {
+            x: size.x - 60f32,
+            y: 10f32,
+        });
+        self.meter.set_position(Vector2f {
+            x: position.x + 10f32,
+            y: position.y + size.y - METER_HEIGHT / 2f32 - 5f32,
+        });
+        self.marker.set_size(Vector2f { x: 3f32, y: 10f32 });
+        self.correlation_text.set_position(Vector2f {
+            x: position.x + size.x - 42f32,
+            y: position.y + size.y - METER_HEIGHT / 2f32 - 8f32,
+        });
+        let correlation = self.correlation;
+
+        self.set_correlation(correlation);
+        self.points.clear();
+        self.need_to_draw = true;
+    }
+
+    fn get_position(&self) -> Vector2f {
+        self.cleaner.position()
+    }
+
+    fn set_size(&mut self, size: &Vector2f) {
+        let pos = self.get_position();
+
+        self.cleaner.set_size(Vector2f {
+            x: size.x,
+            y: size.y,
+        });
+        self.set_position(&pos);
+    }
+
+    fn get_size(&self) -> Vector2f {
+        self.cleaner.size()
+    }
+
+    fn draw(&mut self, win: &mut RenderWindow) {
+        win.draw(&self.cleaner);
+        for line in self.axes.iter() {
+            win.draw(line);
+        }
+        win.draw(&self.points);
+        win.draw(&self.meter);
+        win.draw(&self.marker);
+        win.draw(&self.correlation_text);
+        self.need_to_draw = false;
+    }
+
+    fn is_inside(&self, pos: &Vector2f) -> bool {
+        pos.y >= self.cleaner.position().y
+            && pos.y <= self.cleaner.position().y + self.cleaner.size().y
+            && pos.x >= self.cleaner.position().x
+            && pos.x <= self.cleaner.position().x + self.cleaner.size().x
+    }
+
+    fn get_min_size(&self) -> Vector2f {
+        Vector2f {
+            x: 100f32,
+            y: 100f32,
+        }
+    }
+
+    fn get_max_size(&self) -> Option<Vector2f> {
+        None
+    }
+
+    fn cursor_moved(&mut self, position: &Vector2f) {}
+
+    fn clicked(&mut self, position: &Vector2f) {}
+
+    fn mouse_leave(&mut self) {}
+
+    fn set_element_name(&mut self, name: &String) {
+        self.name = name.clone();
+    }
+
+    fn get_element_name<'a>(&'a self) -> &'a String {
+        &self.name
+    }
+}
@@ -22,32 +22,159 @@
 
 #![allow(dead_code)]
 
+use bookmarks::{self, Bookmark};
+use bpm::{self, BeatDetector};
+use chapters;
+use config::Config;
+use crossfade::Crossfade;
+use equalizer::Equalizer;
+use fft;
+use graphic_bookmarks::GraphicBookmarks;
 use graphic_button::GraphicButton;
 use graphic_element::GraphicElement;
+use graphic_equalizer::{EqualizerAction, GraphicEqualizer};
+use graphic_goniometer::GraphicGoniometer;
+use graphic_lyrics::GraphicLyrics;
+use graphic_now_playing::GraphicNowPlaying;
+use graphic_oscilloscope::GraphicOscilloscope;
 use graphic_playlist::GraphicPlayList;
 use graphic_sound_position::GraphicSoundPosition;
+use graphic_spectrogram::GraphicSpectrogram;
 use graphic_spectrum::GraphicSpectrum;
-use graphic_timer::GraphicTimer;
+use graphic_status::GraphicStatus;
+use graphic_timer::{self, GraphicTimer};
+use graphic_tooltip::GraphicTooltip;
+use graphic_vu_meter::GraphicVuMeter;
+use library::{Library, Resume};
+use loudness;
+use lyrics::Lyrics;
+use output;
 use playlist::PlayList;
 use progress_bar::ProgressBar;
+use recorder::Recorder;
 use rfmod;
 use sfml::graphics::{Color, Font, RenderTarget, RenderWindow};
 use sfml::system::Vector2f;
 use sfml::window::mouse::Button;
-use sfml::window::{Event, Key};
+use sfml::window::{ContextSettings, Event, Key, Style, VideoMode};
+use sleep_timer::{SleepMode, SleepTimer};
+use std::collections::HashMap;
 use std::default::Default;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tags;
+use trajectory::{Trajectory, TrajectoryKind};
+use track::{self, Track};
+use volume;
+use waveform;
+
+// time left (in ms) before the next music is prepared
+const PRELOAD_TIME: usize = 3000;
+const MIN_SPEED: f32 = 0.5;
+const MAX_SPEED: f32 = 2.0;
+const SPEED_STEP: f32 = 0.05;
+const MAX_PITCH: i32 = 12;
+const BALANCE_STEP: f32 = 0.1;
+// step (in ms) used to move the loop points
+const LOOP_NUDGE: usize = 100;
+// in audiobook mode, musics are resumed a bit before where they were left
+const RESUME_REWIND: usize = 5000;
+// musics left that close to their end are started again from the beginning
+const RESUME_END_MARGIN: usize = 10000;
+const RESUME_SAVE_INTERVAL: u64 = 10;
+// number of samples read for the oscilloscope and the goniometer
+const WAVE_SIZE: usize = 1024;
+// degrees the listener turns for each step of the mouse wheel
+const WHEEL_ROTATION: f32 = 15f32;
+// size of the window outside of the fullscreen mode
+pub const WINDOW_WIDTH: u32 = 800;
+pub const WINDOW_HEIGHT: u32 = 600;
+// height of the music bar when it shows the waveform
+const WAVEFORM_HEIGHT: f32 = 40f32;
+
+/// The visualizations which can be picked with the tabs.
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    Spectrum,
+    Spectrogram,
+    Oscilloscope,
+    Goniometer,
+    Position,
+}
 
 pub struct GraphicHandler<'a> {
     font: Font,
     musics: GraphicPlayList<'a>,
+    lyrics: GraphicLyrics<'a>,
+    bookmarks: GraphicBookmarks<'a>,
+    // the bookmarks panel is shown in place of the lyrics one
+    show_bookmarks: bool,
+    // position and name of the bookmark being typed
+    new_bookmark: Option<(usize, String)>,
     timer: GraphicTimer<'a>,
     music_bar: ProgressBar<'a>,
     volume_bar: ProgressBar<'a>,
+    vu_meter: GraphicVuMeter<'a>,
     playlist: PlayList,
     spectrum: GraphicSpectrum<'a>,
+    spectrogram: GraphicSpectrogram<'a>,
+    oscilloscope: GraphicOscilloscope<'a>,
+    goniometer: GraphicGoniometer<'a>,
     graph_sound: GraphicSoundPosition<'a>,
     spectrum_button: GraphicButton<'a>,
+    spectrogram_button: GraphicButton<'a>,
+    oscilloscope_button: GraphicButton<'a>,
+    goniometer_button: GraphicButton<'a>,
     position_button: GraphicButton<'a>,
+    // what is shown under the tabs when the equalizer isn't
+    view: View,
+    // moves the listener in the 3D view
+    trajectory: Trajectory,
+    // only the view is shown, on the whole display
+    fullscreen: bool,
+    now_playing: GraphicNowPlaying<'a>,
+    equalizer_panel: GraphicEqualizer<'a>,
+    status: GraphicStatus<'a>,
+    tooltip: GraphicTooltip<'a>,
+    // the music bar is being dragged
+    seeking: bool,
+    show_equalizer: bool,
+    equalizer: Equalizer,
+    // the current music has its own equalizer preset
+    equalizer_override: bool,
+    config: Config,
+    track: Option<Track>,
+    // the music scheduled to start right after the current one
+    next: Option<Track>,
+    next_prepared: bool,
+    fade: Option<Crossfade>,
+    library: Library,
+    // the music being analyzed and where the result will be sent
+    analysis: Option<(String, Receiver<Result<(f32, f32), String>>)>,
+    // the music whose waveform is being loaded and where it will be sent
+    waveform: Option<(String, Receiver<Result<Vec<f32>, String>>)>,
+    output_rate: u64,
+    beat: BeatDetector,
+    // tempo of the current music stored in the library
+    track_bpm: Option<f32>,
+    // A and B points (in ms) of the loop
+    loop_start: Option<usize>,
+    loop_end: Option<usize>,
+    // last time the position was stored in audiobook mode
+    resume_saved: Instant,
+    sleep: SleepTimer,
+    // volume factor while the sleep timer fades out
+    sleep_fade: f32,
+    // the next music is from another album than the current one
+    last_of_album: bool,
+    // music for which last_of_album was computed
+    album_checked: Option<String>,
+    // albums of the playlist entries, read from their tags
+    albums: HashMap<String, Option<String>>,
+    // the volume bar keeps its level while muted
+    muted: bool,
+    recorder: Option<Recorder>,
 }
 
 impl<'b> GraphicHandler<'b> {
@@ -56,24 +183,69 @@ impl<'b> GraphicHandler<'b> {
         self.musics.add_musics(&self.playlist.to_vec(), &font);
         self.volume_bar.set_maximum(100usize);
         self.volume_bar.set_progress(100);
-        self.spectrum_button.set_pushed(true);
         self.spectrum_button.set_label(&("Spectrum".to_owned()));
-        self.position_button.set_label(&("3D position".to_owned()));
+        self.spectrogram_button.set_label(&("Waterfall".to_owned()));
+        self.oscilloscope_button.set_label(&("Scope".to_owned()));
+        self.goniometer_button.set_label(&("Stereo".to_owned()));
+        self.position_button.set_label(&("3D".to_owned()));
+        self.select_view(View::Spectrum);
+        self.spectrogram.set_colormap(self.config.spectrogram_colormap);
+        self.spectrum.set_nb_bands(self.config.spectrum_bands);
+        self.spectrum.set_mode(self.config.spectrum_mode);
+        let preset = self.config.equalizer.clone();
+        if !self.equalizer.set_preset_by_name(&preset) {
+            println!("Unknown equalizer preset : {}", preset);
+        }
+        self.update_equalizer_panel();
+        self.update_audiobook_status();
+        self.update_volume_status();
+        self.update_fft_status();
         self
     }
 
-    pub fn new(window: &RenderWindow, playlist: PlayList, font: &'b Font) -> GraphicHandler<'b> {
+    pub fn new(
+        window: &RenderWindow,
+        playlist: PlayList,
+        font: &'b Font,
+        config: Config,
+    ) -> GraphicHandler<'b> {
         GraphicHandler {
             font: font.clone(),
             musics: GraphicElement::new_init(
                 &Vector2f {
                     x: window.size().x as f32 - 511f32,
-                    y: window.size().y as f32 - 32f32,
+                    y: window.size().y as f32 - 232f32,
                 },
                 &Vector2f { x: 513f32, y: 0f32 },
                 &Color::BLACK,
                 Some(&font),
             ),
+            lyrics: GraphicElement::new_init(
+                &Vector2f {
+                    x: window.size().x as f32 - 511f32,
+                    y: 200f32,
+                },
+                &Vector2f {
+                    x: 512f32,
+                    y: window.size().y as f32 - 233f32,
+                },
+                &Color::BLACK,
+                Some(&font),
+            ),
+            bookmarks: GraphicElement::new_init(
+                &Vector2f {
+                    x: window.size().x as f32 - 511f32,
+                    y: 200f32,
+                },
+                &Vector2f {
+                    x: 512f32,
+                    y: window.size().y as f32 - 233f32,
+                },
+                &Color::BLACK,
+                Some(&font),
+            ),
+            show_bookmarks: false,
+            new_bookmark: None,
             timer: GraphicElement::new_init(
                 &Vector2f {
                     x: window.size().x as f32 - 633f32,
@@ -100,7 +272,7 @@ impl<'b> GraphicHandler<'b> {
             ),
             volume_bar: GraphicElement::new_init(
                 &Vector2f {
-                    x: 120f32,
+                    x: 72f32,
                     y: 20f32,
                 },
                 &Vector2f {
@@ -110,97 +282,1353 @@ impl<'b> GraphicHandler<'b> {
                 &Color::rgb(255, 25, 25),
                 None,
             ),
+            vu_meter: GraphicElement::new_init(
+                &Vector2f {
+                    x: 46f32,
+                    y: 24f32,
+                },
+                &Vector2f {
+                    x: 586f32,
+                    y: window.size().y as f32 - 32f32,
+                },
+                &Color::BLACK,
+                None,
+            ),
             playlist: playlist,
             spectrum_button: GraphicElement::new_init(
                 &Vector2f {
-                    x: 256f32,
+                    x: 103f32,
                     y: 25f32,
                 },
                 &Vector2f { x: 0f32, y: 0f32 },
                 &Color::BLACK,
                 Some(&font),
             ),
+            spectrogram_button: GraphicElement::new_init(
+                &Vector2f {
+                    x: 102f32,
+                    y: 25f32,
+                },
+                &Vector2f { x: 103f32, y: 0f32 },
+                &Color::BLACK,
+                Some(&font),
+            ),
+            oscilloscope_button: GraphicElement::new_init(
+                &Vector2f {
+                    x: 102f32,
+                    y: 25f32,
+                },
+                &Vector2f { x: 205f32, y: 0f32 },
+                &Color::BLACK,
+                Some(&font),
+            ),
+            goniometer_button: GraphicElement::new_init(
+                &Vector2f {
+                    x: 102f32,
+                    y: 25f32,
+                },
+                &Vector2f { x: 307f32, y: 0f32 },
+                &Color::BLACK,
+                Some(&font),
+            ),
             position_button: GraphicElement::new_init(
                 &Vector2f {
-                    x: 256f32,
+                    x: 103f32,
                     y: 25f32,
                 },
-                &Vector2f { x: 256f32, y: 0f32 },
+                &Vector2f { x: 409f32, y: 0f32 },
+                &Color::BLACK,
+                Some(&font),
+            ),
+            view: View::Spectrum,
+            trajectory: Trajectory::new(),
+            fullscreen: false,
+            now_playing: GraphicElement::new_init(
+                &Vector2f {
+                    x: window.size().x as f32,
+                    y: window.size().y as f32,
+                },
+                &Vector2f { x: 0f32, y: 0f32 },
                 &Color::BLACK,
                 Some(&font),
             ),
             spectrum: GraphicElement::new_init(
                 &Vector2f {
                     x: 512f32,
-                    y: window.size().y as f32 - 33f32,
+                    y: window.size().y as f32 - 57f32,
                 },
                 &Vector2f { x: 0f32, y: 25f32 },
                 &Color::rgb(50, 100, 30),
+                Some(&font),
+            ),
+            spectrogram: GraphicElement::new_init(
+                &Vector2f {
+                    x: 512f32,
+                    y: window.size().y as f32 - 57f32,
+                },
+                &Vector2f { x: 0f32, y: 25f32 },
+                &Color::BLACK,
+                Some(&font),
+            ),
+            oscilloscope: GraphicElement::new_init(
+                &Vector2f {
+                    x: 512f32,
+                    y: window.size().y as f32 - 57f32,
+                },
+                &Vector2f { x: 0f32, y: 25f32 },
+                &Color::BLACK,
                 None,
             ),
+            goniometer: GraphicElement::new_init(
+                &Vector2f {
+                    x: 512f32,
+                    y: window.size().y as f32 - 57f32,
+                },
+                &Vector2f { x: 0f32, y: 25f32 },
+                &Color::BLACK,
+                Some(&font),
+            ),
             graph_sound: GraphicElement::new_init(
                 &Vector2f {
                     x: 512f32,
-                    y: window.size().y as f32 - 35f32,
+                    y: window.size().y as f32 - 59f32,
                 },
                 &Vector2f { x: 0f32, y: 26f32 },
                 &Color::BLACK,
                 Some(&font),
             ),
+            equalizer_panel: GraphicElement::new_init(
+                &Vector2f {
+                    x: 512f32,
+                    y: window.size().y as f32 - 57f32,
+                },
+                &Vector2f { x: 0f32, y: 25f32 },
+                &Color::rgb(50, 100, 30),
+                Some(&font),
+            ),
+            tooltip: GraphicElement::new_init(
+                &Vector2f { x: 60f32, y: 20f32 },
+                &Vector2f { x: 0f32, y: 0f32 },
+                &Color::BLACK,
+                Some(&font),
+            ),
+            seeking: false,
+            status: GraphicElement::new_init(
+                &Vector2f { x: 512f32, y: 24f32 },
+                &Vector2f {
+                    x: 0f32,
+                    y: window.size().y as f32 - 32f32,
+                },
+                &Color::BLACK,
+                Some(&font),
+            ),
+            show_equalizer: false,
+            equalizer: Equalizer::new(),
+            equalizer_override: false,
+            config: config,
+            track: None,
+            next: None,
+            next_prepared: false,
+            fade: None,
+            library: Library::load(),
+            analysis: None,
+            waveform: None,
+            output_rate: 48000u64,
+            beat: BeatDetector::new(),
+            track_bpm: None,
+            loop_start: None,
+            loop_end: None,
+            resume_saved: Instant::now(),
+            sleep: SleepTimer::new(),
+            sleep_fade: 1f32,
+            last_of_album: false,
+            album_checked: None,
+            albums: HashMap::new(),
+            muted: false,
+            recorder: None,
         }.init(font)
     }
 
-    pub fn set_music(&mut self, fmod: &rfmod::Sys, name: String) -> Result<rfmod::Sound, String> {
-        match fmod.create_sound(&name, Some(rfmod::Mode(rfmod::SOFTWARE | rfmod::_3D)), None) {
-            Ok(s) => {
-                s.set_3D_min_max_distance(5f32, 10000f32);
-                self.musics.set_current(self.playlist.get_pos());
-                self.music_bar.maximum = match s.get_length(rfmod::TIMEUNIT_MS) {
-                    Ok(l) => l as usize,
-                    Err(_) => 0usize,
+    fn create_sound(&self, fmod: &rfmod::Sys, name: &str) -> Result<rfmod::Sound, rfmod::Status> {
+        match fmod.create_sound(name, Some(rfmod::Mode(rfmod::SOFTWARE | rfmod::_3D)), None) {
+            Ok(s) => {
+                s.set_3D_min_max_distance(5f32, 10000f32);
+                if self.playlist.get_nb_musics() > 1 {
+                    s.set_mode(rfmod::Mode(rfmod::LOOP_OFF));
+                } else {
+                    s.set_mode(rfmod::Mode(rfmod::LOOP_NORMAL));
+                }
+                Ok(s)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn show_music(&mut self, name: &str, sound: &rfmod::Sound) {
+        self.apply_track_equalizer(name);
+        self.musics.set_current(self.playlist.get_pos());
+        self.lyrics.set_lyrics(Lyrics::load(name, sound));
+        let length = match sound.get_length(rfmod::TIMEUNIT_MS) {
+            Ok(l) => l as usize,
+            Err(_) => 0usize,
+        };
+        self.music_bar.set_maximum(length);
+        self.update_bookmarks(name);
+        self.musics.set_chapters(&chapters::load(name, sound));
+        self.now_playing.set_music(name, sound);
+        self.music_bar.set_waveform(None);
+        if self.config.waveform {
+            self.load_waveform(name);
+        }
+        self.beat.reset();
+        self.track_bpm = match self.library.get(name) {
+            Some(info) => info.bpm,
+            None => None,
+        };
+    }
+
+    fn get_bookmarks(&self, name: &str) -> Vec<Bookmark> {
+        match self.library.get(name) {
+            Some(info) => info.bookmarks.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    fn update_bookmarks(&mut self, name: &str) {
+        let bookmarks = self.get_bookmarks(name);
+        let marks: Vec<usize> = bookmarks.iter().map(|b| b.time).collect();
+
+        self.bookmarks.set_bookmarks(&bookmarks);
+        self.music_bar.set_marks(&marks);
+    }
+
+    fn get_track_name(&self) -> Option<String> {
+        match self.track {
+            Some(ref t) => Some(t.name.clone()),
+            None => None,
+        }
+    }
+
+    /// Starts typing the name of a bookmark at the current position.
+    fn start_bookmark(&mut self) {
+        let position = self.get_position();
+
+        self.new_bookmark = Some((position, String::new()));
+        self.update_bookmark_entry();
+    }
+
+    fn update_bookmark_entry(&mut self) {
+        let text = match self.new_bookmark {
+            Some((_, ref n)) => Some(format!("Bookmark name : {}_", n)),
+            None => None,
+        };
+
+        self.status.set_entry("bookmark", text);
+    }
+
+    fn add_bookmark(&mut self) {
+        let (position, name) = match self.new_bookmark.take() {
+            Some(b) => b,
+            None => return,
+        };
+        self.update_bookmark_entry();
+        let music = match self.get_track_name() {
+            Some(m) => m,
+            None => return,
+        };
+
+        {
+            let info = self.library.get_mut(&music);
+            let name = if name.trim().is_empty() {
+                format!("Bookmark {}", info.bookmarks.len() + 1)
+            } else {
+                name.trim().to_owned()
+            };
+
+            info.add_bookmark(Bookmark::new(position, &name));
+        }
+        self.library.save();
+        self.update_bookmarks(&music);
+    }
+
+    fn remove_bookmark(&mut self, index: usize) {
+        let music = match self.get_track_name() {
+            Some(m) => m,
+            None => return,
+        };
+
+        {
+            let info = self.library.get_mut(&music);
+
+            if index < info.bookmarks.len() {
+                info.bookmarks.remove(index);
+            }
+        }
+        self.library.save();
+        self.update_bookmarks(&music);
+    }
+
+    fn go_to_bookmark(&mut self, next: bool) {
+        let music = match self.get_track_name() {
+            Some(m) => m,
+            None => return,
+        };
+        let list = self.get_bookmarks(&music);
+        let position = self.get_position();
+        let found = if next {
+            bookmarks::get_next(&list, position)
+        } else {
+            bookmarks::get_prev(&list, position)
+        };
+
+        match found {
+            Some(b) => self.seek(list[b].time),
+            None => {}
+        }
+    }
+
+    fn export_bookmarks(&self) {
+        let music = match self.get_track_name() {
+            Some(m) => m,
+            None => return,
+        };
+        let list = self.get_bookmarks(&music);
+
+        if list.is_empty() {
+            println!("No bookmarks to export for {}", music);
+            return;
+        }
+        for res in vec![
+            bookmarks::export_cue(&music, &list),
+            bookmarks::export_chapters(&music, &list),
+        ] {
+            match res {
+                Ok(path) => println!("Bookmarks exported to {}", path),
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+
+    pub fn set_music(&mut self, fmod: &rfmod::Sys, name: String) -> Result<rfmod::Sound, String> {
+        match self.create_sound(fmod, &name) {
+            Ok(s) => {
+                self.show_music(&name, &s);
+                Ok(s)
+            }
+            Err(err) => {
+                println!(
+                    "FmodSys::create_sound failed on this file : {}\nError : {:?}",
+                    name, err
+                );
+                self.musics.remove_music(self.playlist.get_pos());
+                self.playlist.remove_current();
+                if self.playlist.get_nb_musics() == 0 {
+                    Err("No more music".to_owned())
+                } else {
+                    let tmp_s = self.playlist.get_current();
+
+                    self.set_music(fmod, tmp_s)
+                }
+            }
+        }
+    }
+
+    /// Returns the new music paused, so it can be set up before being heard.
+    fn load_music(&mut self, fmod: &rfmod::Sys, name: String) -> Track {
+        let sound = match self.set_music(fmod, name) {
+            Ok(s) => s,
+            Err(e) => panic!("Error : {:?}", e),
+        };
+        let mut track = match Track::new(self.playlist.get_current(), sound) {
+            Ok(t) => t,
+            Err(e) => panic!("{}", e),
+        };
+
+        self.set_chan_params(&track.chan);
+        self.check_replay_gain(&mut track);
+        track.apply_saved_speed(fmod, &self.library, self.config.time_stretch);
+        self.resume_track(&track);
+        track
+    }
+
+    fn update_audiobook_status(&mut self) {
+        let text = if self.config.audiobook {
+            Some("Audiobook".to_owned())
+        } else {
+            None
+        };
+
+        self.status.set_entry("audiobook", text);
+    }
+
+    /// In audiobook mode, goes back to where the music was left.
+    fn resume_track(&self, track: &Track) {
+        if !self.config.audiobook {
+            return;
+        }
+        let position = match self.library.get(&track.name) {
+            Some(info) => match info.resume {
+                Some(ref r) => r.get_position(&track.name),
+                None => None,
+            },
+            None => None,
+        };
+
+        match position {
+            Some(p) if p > 0 => {
+                let p = if p > RESUME_REWIND { p - RESUME_REWIND } else { 0usize };
+
+                track.chan.set_position(p, rfmod::TIMEUNIT_MS);
+            }
+            _ => {}
+        }
+    }
+
+    /// Stores the position of the current music in audiobook mode. A music
+    /// which ended or is about to will be started from the beginning.
+    fn save_resume(&mut self) {
+        self.resume_saved = Instant::now();
+        if !self.config.audiobook {
+            return;
+        }
+        let (name, position, length) = match self.track {
+            Some(ref t) => (
+                t.name.clone(),
+                match t.chan.get_position(rfmod::TIMEUNIT_MS) {
+                    Ok(p) => Some(p),
+                    Err(_) => None,
+                },
+                match t.sound.get_length(rfmod::TIMEUNIT_MS) {
+                    Ok(l) => l as usize,
+                    Err(_) => 0usize,
+                },
+            ),
+            None => return,
+        };
+        let resume = match position {
+            Some(p) if p + RESUME_END_MARGIN < length => Resume::new(&name, p),
+            _ => None,
+        };
+
+        if self.library.get(&name).and_then(|i| i.resume) != resume {
+            self.library.get_mut(&name).resume = resume;
+            self.library.save();
+        }
+    }
+
+    fn check_resume_save(&mut self) {
+        if self.config.audiobook
+            && self.resume_saved.elapsed() >= Duration::from_secs(RESUME_SAVE_INTERVAL)
+        {
+            self.save_resume();
+        }
+    }
+
+    fn toggle_recording(&mut self, fmod: &rfmod::Sys) {
+        if self.recorder.is_some() {
+            self.stop_recording();
+            return;
+        }
+        match Recorder::start(fmod, self.config.record_format, &self.config.get_record_dir()) {
+            Ok(r) => {
+                self.recorder = Some(r);
+                self.status.set_indicator(Some(Color::rgb(255, 0, 0)));
+                self.update_recording_status();
+            }
+            Err(e) => println!("Cannot record : {}", e),
+        }
+    }
+
+    /// Stores what has to be kept and closes the window.
+    fn quit(&mut self, window: &mut RenderWindow) {
+        self.save_resume();
+        self.stop_recording();
+        window.close()
+    }
+
+    fn stop_recording(&mut self) {
+        match self.recorder.take() {
+            Some(r) => match r.stop() {
+                Ok(path) => println!("Recorded in {}", path),
+                Err(e) => println!("{}", e),
+            },
+            None => return,
+        }
+        self.status.set_indicator(None);
+        self.status.set_entry("record", None);
+    }
+
+    fn update_recording_status(&mut self) {
+        let text = match self.recorder {
+            Some(ref r) => format!(
+                "REC {} {:.1} MB",
+                graphic_timer::format_time(r.get_elapsed()),
+                r.get_size() as f32 / 1048576f32
+            ),
+            None => return,
+        };
+
+        self.status.set_entry("record", Some(text));
+    }
+
+    fn cycle_sleep_timer(&mut self) {
+        let mode = self.sleep.get_mode().next();
+
+        self.sleep.set_mode(mode);
+        self.album_checked = None;
+        self.last_of_album = false;
+        self.sleep_fade = 1f32;
+        // the player may have to stop at the end of the music
+        self.cancel_next();
+        self.fade = None;
+        self.update_volume();
+        if mode == SleepMode::Off {
+            self.timer.set_sleep_text(None);
+        }
+    }
+
+    /// Only the tags are read, not the whole file.
+    fn get_album(&mut self, music: &str) -> Option<String> {
+        self.albums
+            .entry(music.to_owned())
+            .or_insert_with(|| tags::read_album(music))
+            .clone()
+    }
+
+    /// Tells if the next music is from another album. When one of the albums
+    /// is unknown, the musics are considered to be from the same one.
+    fn check_last_of_album(&mut self) -> bool {
+        let name = match self.get_track_name() {
+            Some(n) => n,
+            None => return true,
+        };
+        if self.playlist.get_repeat() {
+            return false;
+        }
+        let next = self.playlist.peek_next();
+
+        match (self.get_album(&name), self.get_album(&next)) {
+            (Some(a), Some(n)) => a != n,
+            _ => false,
+        }
+    }
+
+    fn check_sleep_timer(&mut self) {
+        if self.sleep.get_mode() == SleepMode::Off {
+            return;
+        }
+        let name = self.get_track_name();
+
+        if self.sleep.get_mode() == SleepMode::EndOfAlbum && self.album_checked != name {
+            self.last_of_album = self.check_last_of_album();
+            self.album_checked = name;
+            self.cancel_next();
+        }
+        let position = self.get_position();
+        let length = self.music_bar.maximum;
+        let track_left = if length > position {
+            ((length - position) as f32 / self.get_speed()) as usize
+        } else {
+            0usize
+        };
+        let remaining = self.sleep.get_remaining(track_left, self.last_of_album);
+
+        // at the end of a music, the player is paused when the next one starts
+        let timed = match self.sleep.get_mode() {
+            SleepMode::Minutes(_) => true,
+            _ => false,
+        };
+
+        self.timer.set_sleep_text(self.sleep.get_text(remaining));
+        let fade = match remaining {
+            Some(0) if timed => {
+                self.sleep_fired();
+                return;
+            }
+            Some(r) => self.config.fade_curve.fade_out(SleepTimer::get_fade_progress(r)),
+            None => 1f32,
+        };
+
+        if fade != self.sleep_fade {
+            self.sleep_fade = fade;
+            self.update_volume();
+        }
+    }
+
+    /// Pauses the player and gives back its volume for the next time.
+    fn sleep_fired(&mut self) {
+        let paused = match self.track {
+            Some(ref t) => match t.chan.get_paused() {
+                Ok(p) => p,
+                Err(_) => true,
+            },
+            None => true,
+        };
+
+        if !paused {
+            self.toggle_pause();
+        }
+        self.sleep.set_mode(SleepMode::Off);
+        self.album_checked = None;
+        self.sleep_fade = 1f32;
+        self.timer.set_sleep_text(None);
+        self.update_volume();
+    }
+
+    /// Moves forward (or backward if negative) of `offset` ms.
+    fn skip(&mut self, offset: isize) {
+        let length = self.music_bar.maximum as isize;
+        let position = self.get_position() as isize + offset;
+
+        self.seek(if position < 0 {
+            0usize
+        } else if position > length {
+            length as usize
+        } else {
+            position as usize
+        });
+    }
+
+    fn change_speed(&mut self, fmod: &rfmod::Sys, speed: f32, pitch: i32) {
+        // avoids drifting to something like 1.0499999 after a few steps
+        let speed = (speed.max(MIN_SPEED).min(MAX_SPEED) * 100f32).round() / 100f32;
+        let stretch = self.config.time_stretch;
+        let pitch = track::limit_pitch(speed, pitch.max(-MAX_PITCH).min(MAX_PITCH), stretch);
+        // the current music won't end at the scheduled time anymore
+        self.cancel_next();
+        let name = match self.track {
+            Some(ref mut t) => {
+                t.set_speed(fmod, speed, pitch, stretch);
+                t.name.clone()
+            }
+            None => return,
+        };
+
+        {
+            let info = self.library.get_mut(&name);
+
+            info.speed = if speed != 1f32 { Some(speed) } else { None };
+            info.pitch = if pitch != 0 { Some(pitch) } else { None };
+        }
+        self.library.save();
+        self.update_speed_status();
+    }
+
+    fn update_speed_status(&mut self) {
+        let (speed, pitch) = match self.track {
+            Some(ref t) => (t.speed, t.pitch),
+            None => (1f32, 0i32),
+        };
+
+        self.status.set_entry(
+            "speed",
+            if speed != 1f32 || pitch != 0 {
+                Some(format!(
+                    "Speed x{:.2} / {:+} st [{}]",
+                    speed,
+                    pitch,
+                    if self.config.time_stretch {
+                        "stretch"
+                    } else {
+                        "resample"
+                    }
+                ))
+            } else {
+                None
+            },
+        );
+    }
+
+    fn get_speed(&self) -> f32 {
+        match self.track {
+            Some(ref t) => t.speed,
+            None => 1f32,
+        }
+    }
+
+    fn get_pitch(&self) -> i32 {
+        match self.track {
+            Some(ref t) => t.pitch,
+            None => 0i32,
+        }
+    }
+
+    /// Uses the gain computed by a previous analysis when the music has no
+    /// ReplayGain tags, or starts the analysis if enabled.
+    fn check_replay_gain(&mut self, track: &mut Track) {
+        if !track.replay_gain.is_empty() {
+            return;
+        }
+        if let Some(info) = self.library.get(&track.name) {
+            if info.gain.is_some() {
+                track.replay_gain.track_gain = info.gain;
+                track.replay_gain.track_peak = info.peak;
+                return;
+            }
+        }
+        if self.config.analyze_loudness && self.analysis.is_none() {
+            let (sender, receiver) = mpsc::channel();
+            let name = track.name.clone();
+
+            thread::spawn(move || {
+                let _ = sender.send(loudness::analyze_file(&name));
+            });
+            self.analysis = Some((track.name.clone(), receiver));
+        }
+    }
+
+    fn check_analysis(&mut self) {
+        let res = match self.analysis {
+            Some((_, ref receiver)) => match receiver.try_recv() {
+                Ok(r) => r,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => Err("analysis thread stopped".to_owned()),
+            },
+            None => return,
+        };
+        let name = match self.analysis.take() {
+            Some((n, _)) => n,
+            None => return,
+        };
+
+        match res {
+            Ok((gain, peak)) => {
+                {
+                    let info = self.library.get_mut(&name);
+
+                    info.gain = Some(gain);
+                    info.peak = Some(peak);
+                }
+                self.library.save();
+                for track in self.track.iter_mut().chain(self.next.iter_mut()) {
+                    if track.name == name && track.replay_gain.is_empty() {
+                        track.replay_gain.track_gain = Some(gain);
+                        track.replay_gain.track_peak = Some(peak);
+                    }
+                }
+                self.update_volume();
+            }
+            Err(e) => println!("Loudness analysis failed : {}", e),
+        }
+    }
+
+    /// Shows the tempo of the music. The live estimate is stored once enough
+    /// of the music was heard.
+    fn check_bpm(&mut self) {
+        if self.track_bpm.is_none() && self.beat.get_analysed_time() >= bpm::STORE_TIME {
+            match (self.beat.get_bpm(), self.get_track_name()) {
+                (Some(b), Some(name)) => {
+                    self.library.get_mut(&name).bpm = Some(b);
+                    self.library.save();
+                    self.track_bpm = Some(b);
+                }
+                _ => {}
+            }
+        }
+        let bpm = match self.track_bpm {
+            Some(b) => Some(b),
+            None => self.beat.get_bpm(),
+        };
+
+        self.status.set_entry("bpm", bpm.map(|b| format!("{:.0} BPM", b)));
+    }
+
+    /// Reads the waveform from the cache, or decodes the music, in another
+    /// thread.
+    fn load_waveform(&mut self, name: &str) {
+        let (sender, receiver) = mpsc::channel();
+        let music = name.to_owned();
+
+        thread::spawn(move || {
+            let _ = sender.send(waveform::load(&music));
+        });
+        self.waveform = Some((name.to_owned(), receiver));
+    }
+
+    fn check_waveform(&mut self) {
+        let res = match self.waveform {
+            Some((_, ref receiver)) => match receiver.try_recv() {
+                Ok(r) => r,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => Err("waveform thread stopped".to_owned()),
+            },
+            None => return,
+        };
+        let name = match self.waveform.take() {
+            Some((n, _)) => n,
+            None => return,
+        };
+
+        match res {
+            // the music may have changed in the meantime
+            Ok(peaks) => {
+                if self.config.waveform && self.get_track_name() == Some(name) {
+                    self.music_bar.set_waveform(Some(peaks));
+                }
+            }
+            Err(e) => println!("Cannot get the waveform : {}", e),
+        }
+    }
+
+    fn toggle_waveform(&mut self, window: &RenderWindow) {
+        self.config.waveform = !self.config.waveform;
+        Config::save_option("waveform", &self.config.waveform.to_string());
+        self.waveform = None;
+        self.music_bar.set_waveform(None);
+        if self.config.waveform {
+            match self.get_track_name() {
+                Some(name) => self.load_waveform(&name),
+                None => {}
+            }
+        }
+        self.place_bottom(window);
+    }
+
+    /// The music bar grows when it shows the waveform, pushing the elements
+    /// above it.
+    fn place_bottom(&mut self, window: &RenderWindow) {
+        let offset = self.get_bottom_offset();
+        let height = window.size().y as f32;
+
+        // the bar's size includes its outline
+        self.music_bar.set_size(&Vector2f {
+            x: window.size().x as f32 + 5f32,
+            y: 11f32 + offset,
+        });
+        self.music_bar.set_position(&Vector2f {
+            x: -1f32,
+            y: height - 8f32 - offset,
+        });
+        self.status.set_position(&Vector2f {
+            x: 0f32,
+            y: height - 32f32 - offset,
+        });
+        self.volume_bar.set_position(&Vector2f {
+            x: 512f32,
+            y: height - 30f32 - offset,
+        });
+        self.vu_meter.set_position(&Vector2f {
+            x: 586f32,
+            y: height - 32f32 - offset,
+        });
+        self.timer.set_position(&Vector2f {
+            x: 634f32,
+            y: height - 34f32 - offset,
+        });
+        let panel = Vector2f {
+            x: window.size().x as f32 - 511f32,
+            y: 200f32 - offset,
+        };
+
+        self.lyrics.set_size(&panel);
+        self.bookmarks.set_size(&panel);
+        self.equalizer_panel.set_size(&Vector2f {
+            x: 512f32,
+            y: height - 57f32 - offset,
+        });
+        self.place_views(window);
+    }
+
+    fn get_bottom_offset(&self) -> f32 {
+        if self.config.waveform {
+            WAVEFORM_HEIGHT - 8f32
+        } else {
+            0f32
+        }
+    }
+
+    /// Replaces the current music by `name`. If `fade` isn't 0, both are
+    /// crossfaded during `fade` seconds.
+    fn change_music(&mut self, fmod: &rfmod::Sys, name: String, fade: f32) {
+        self.cancel_next();
+        self.save_resume();
+        let track = self.load_music(fmod, name);
+
+        // dropping the previous crossfade stops its outgoing music
+        self.fade = match self.track.take() {
+            Some(old) => {
+                let paused = match old.chan.get_paused() {
+                    Ok(p) => p,
+                    Err(_) => true,
+                };
+
+                if fade > 0f32 && !paused {
+                    Some(Crossfade::new(old, self.config.fade_curve, fade))
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+        self.track = Some(track);
+        self.set_loop(None, None);
+        self.update_speed_status();
+        self.update_volume();
+        match self.track {
+            Some(ref t) => {
+                t.chan.set_paused(false);
+            }
+            None => {}
+        }
+    }
+
+    /// Starts the next music on the DSP clock tick where the current one
+    /// ends so there is no gap between them.
+    fn schedule_next(&mut self, fmod: &rfmod::Sys) {
+        self.next_prepared = true;
+        let name = self.playlist.peek_next();
+        let sound = match self.create_sound(fmod, &name) {
+            Ok(s) => s,
+            Err(e) => {
+                println!("Cannot prepare next music {} : {:?}", name, e);
+                return;
+            }
+        };
+        let mut next = match Track::new(name, sound) {
+            Ok(t) => t,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        let clock = match self.track {
+            Some(ref t) => t.get_end_clock(fmod, self.output_rate),
+            None => None,
+        };
+
+        match clock {
+            Some(c) => {
+                self.set_chan_params(&next.chan);
+                self.check_replay_gain(&mut next);
+                next.apply_saved_speed(fmod, &self.library, self.config.time_stretch);
+                self.resume_track(&next);
+                next.set_delay(rfmod::DelayType::DSPClockStart, c);
+                match self.track {
+                    Some(ref t) => t.set_delay(rfmod::DelayType::DSPClockEnd, c),
+                    None => {}
+                }
+                self.next = Some(next);
+                self.update_volume();
+                match self.next {
+                    Some(ref n) => {
+                        n.chan.set_paused(false);
+                    }
+                    None => {}
+                }
+            }
+            None => next.stop(),
+        }
+    }
+
+    /// Has to be called whenever the current music won't end where it was
+    /// expected to (pause, seek, playlist change...).
+    fn cancel_next(&mut self) {
+        self.next_prepared = false;
+        match self.next.take() {
+            Some(n) => {
+                n.stop();
+                match self.track {
+                    Some(ref t) => t.set_delay(rfmod::DelayType::DSPClockEnd, 0u64),
+                    None => {}
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Starts the crossfade with the next music when the current one is
+    /// about to end, or prepares the gapless transition.
+    fn check_music_end(&mut self, fmod: &rfmod::Sys, position: usize, length: usize) {
+        if self.next_prepared || self.playlist.get_nb_musics() < 2 {
+            return;
+        }
+        // the player will be paused once the music ends
+        if self.sleep.stops_after_track(self.last_of_album) {
+            return;
+        }
+        let crossfade = self.config.crossfade;
+        // positions are in media time, which goes faster than the real one
+        let speed = self.get_speed();
+
+        if crossfade > 0f32 {
+            let fade_time = (crossfade * 1000f32 * speed) as usize;
+
+            // musics shorter than the crossfade are just played gapless
+            if length > fade_time * 2 {
+                if position + fade_time >= length {
+                    let tmp_s = match self.playlist.get_repeat() {
+                        true => self.playlist.get_current(),
+                        false => self.playlist.get_next(),
+                    };
+
+                    self.change_music(fmod, tmp_s, crossfade);
+                }
+                return;
+            }
+        }
+        if position + (PRELOAD_TIME as f32 * speed) as usize >= length {
+            self.schedule_next(fmod);
+        }
+    }
+
+    fn get_position(&self) -> usize {
+        match self.track {
+            Some(ref t) => match t.chan.get_position(rfmod::TIMEUNIT_MS) {
+                Ok(p) => p,
+                Err(_) => 0usize,
+            },
+            None => 0usize,
+        }
+    }
+
+    /// Both points of the loop, in order, once they are set.
+    fn get_loop(&self) -> Option<(usize, usize)> {
+        match (self.loop_start, self.loop_end) {
+            (Some(a), Some(b)) if a < b => Some((a, b)),
+            (Some(a), Some(b)) if b < a => Some((b, a)),
+            _ => None,
+        }
+    }
+
+    fn set_loop(&mut self, start: Option<usize>, end: Option<usize>) {
+        let length = self.music_bar.maximum;
+
+        self.loop_start = start.map(|a| a.min(length));
+        self.loop_end = end.map(|b| b.min(length));
+        match (self.loop_start, self.loop_end) {
+            (Some(a), Some(b)) => self.music_bar.set_region(Some((a, b))),
+            (Some(a), None) => self.music_bar.set_region(Some((a, a))),
+            (None, Some(b)) => self.music_bar.set_region(Some((b, b))),
+            (None, None) => self.music_bar.set_region(None),
+        }
+        let text = match (self.loop_start, self.loop_end) {
+            (None, None) => None,
+            (a, b) => Some(format!(
+                "A-B {} - {}",
+                match a {
+                    Some(a) => graphic_timer::format_time(a),
+                    None => "?".to_owned(),
+                },
+                match b {
+                    Some(b) => graphic_timer::format_time(b),
+                    None => "?".to_owned(),
+                }
+            )),
+        };
+
+        self.status.set_entry("loop", text);
+        // the music won't reach its end while looping
+        if self.get_loop().is_some() {
+            self.cancel_next();
+        }
+    }
+
+    fn nudge_loop(&mut self, start: bool, forward: bool) {
+        let nudge = |p: Option<usize>| {
+            p.map(|p| {
+                if forward {
+                    p + LOOP_NUDGE
+                } else if p > LOOP_NUDGE {
+                    p - LOOP_NUDGE
+                } else {
+                    0usize
+                }
+            })
+        };
+        let (a, b) = (self.loop_start, self.loop_end);
+
+        if start {
+            self.set_loop(nudge(a), b);
+        } else {
+            self.set_loop(a, nudge(b));
+        }
+    }
+
+    /// Goes back to the A point once the B one is reached.
+    fn check_loop(&mut self, position: usize) -> bool {
+        match self.get_loop() {
+            Some((a, b)) if position >= b => {
+                self.seek(a);
+                true
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    fn seek(&mut self, position: usize) {
+        self.cancel_next();
+        match self.track {
+            Some(ref t) => {
+                t.chan.set_position(position, rfmod::TIMEUNIT_MS);
+            }
+            None => {}
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        self.cancel_next();
+        self.fade = None;
+        self.update_volume();
+        match self.track {
+            Some(ref t) => {
+                t.chan.set_paused(!match t.chan.get_paused() {
+                    Ok(p) => p,
+                    _ => false,
+                });
+            }
+            None => {}
+        }
+    }
+
+    fn update_equalizer_panel(&mut self) {
+        let gains = self.equalizer.get_gains();
+
+        self.equalizer_panel.set_gains(&gains);
+        self.equalizer_panel.set_preset_name(self.equalizer.get_preset_name());
+        self.equalizer_panel.set_track_override(self.equalizer_override);
+    }
+
+    /// Uses the music's own preset if it has one, or goes back to the global
+    /// one after a music which had its own.
+    fn apply_track_equalizer(&mut self, name: &str) {
+        let preset = match self.library.get(name) {
+            Some(info) => info.equalizer.clone(),
+            None => None,
+        };
+
+        match preset {
+            Some(p) => {
+                if !self.equalizer.set_preset_by_name(&p) {
+                    println!("Unknown equalizer preset : {}", p);
+                }
+                self.equalizer_override = true;
+            }
+            None => {
+                if self.equalizer_override {
+                    let global = self.config.equalizer.clone();
+
+                    self.equalizer.set_preset_by_name(&global);
+                    self.equalizer_override = false;
+                }
+            }
+        }
+        self.update_equalizer_panel();
+    }
+
+    /// Remembers the chosen preset, for the current music if it has its own
+    /// settings or as the global one otherwise.
+    fn store_equalizer_preset(&mut self) {
+        let preset = match self.equalizer.get_preset_name() {
+            Some(p) => p.to_owned(),
+            None => return,
+        };
+
+        if self.equalizer_override {
+            let name = match self.track {
+                Some(ref t) => t.name.clone(),
+                None => return,
+            };
+
+            self.library.get_mut(&name).equalizer = Some(preset);
+            self.library.save();
+        } else {
+            Config::save_option("equalizer", &preset);
+            self.config.equalizer = preset;
+        }
+    }
+
+    fn equalizer_clicked(&mut self, action: EqualizerAction) {
+        match action {
+            EqualizerAction::SetGain(band, gain) => self.equalizer.set_gain(band, gain),
+            EqualizerAction::PrevPreset => {
+                self.equalizer.prev_preset();
+                self.store_equalizer_preset();
+            }
+            EqualizerAction::NextPreset => {
+                self.equalizer.next_preset();
+                self.store_equalizer_preset();
+            }
+            EqualizerAction::SavePreset => {
+                self.equalizer.save_preset();
+                self.store_equalizer_preset();
+            }
+            EqualizerAction::ToggleTrack => {
+                let name = match self.track {
+                    Some(ref t) => t.name.clone(),
+                    None => return,
                 };
-                if self.playlist.get_nb_musics() > 1 {
-                    s.set_mode(rfmod::Mode(rfmod::LOOP_OFF));
+
+                if self.equalizer_override {
+                    self.library.get_mut(&name).equalizer = None;
+                    self.library.save();
+                    self.equalizer_override = false;
+                    let global = self.config.equalizer.clone();
+
+                    self.equalizer.set_preset_by_name(&global);
                 } else {
-                    s.set_mode(rfmod::Mode(rfmod::LOOP_NORMAL));
+                    if self.equalizer.get_preset_name().is_none() {
+                        self.equalizer.save_preset();
+                    }
+                    self.equalizer_override = true;
+                    self.store_equalizer_preset();
                 }
-                Ok(s)
             }
-            Err(err) => {
-                println!(
-                    "FmodSys::create_sound failed on this file : {}\nError : {:?}",
-                    name, err
-                );
-                self.musics.remove_music(self.playlist.get_pos());
-                self.playlist.remove_current();
-                if self.playlist.get_nb_musics() == 0 {
-                    Err("No more music".to_owned())
-                } else {
-                    let tmp_s = self.playlist.get_current();
+        }
+        self.update_equalizer_panel();
+    }
 
-                    self.set_music(fmod, tmp_s)
-                }
-            }
+    /// The FFT settings are only shown once they were changed.
+    fn update_fft_status(&mut self) {
+        let text = if self.config.fft != fft::FftSettings::new() {
+            Some(self.config.fft.to_string())
+        } else {
+            None
+        };
+
+        self.status.set_entry("fft", text);
+    }
+
+    /// Shows `view` under the tabs and pushes its button.
+    fn select_view(&mut self, view: View) {
+        self.view = view;
+        self.spectrum_button.set_pushed(view == View::Spectrum);
+        self.spectrogram_button.set_pushed(view == View::Spectrogram);
+        self.oscilloscope_button.set_pushed(view == View::Oscilloscope);
+        self.goniometer_button.set_pushed(view == View::Goniometer);
+        self.position_button.set_pushed(view == View::Position);
+        match view {
+            View::Spectrum => self.spectrum.need_to_draw = true,
+            View::Spectrogram => self.spectrogram.need_to_draw = true,
+            View::Oscilloscope => self.oscilloscope.need_to_draw = true,
+            View::Goniometer => self.goniometer.need_to_draw = true,
+            View::Position => self.graph_sound.need_to_draw = true,
+        }
+    }
+
+    /// Starts `kind` and shows its shape in the 3D view.
+    fn set_trajectory(&mut self, kind: TrajectoryKind) {
+        let from = (self.graph_sound.x, self.graph_sound.y);
+
+        self.trajectory.set_kind(kind, from);
+        self.graph_sound.set_path(&self.trajectory.get_outline());
+        self.update_trajectory_status();
+    }
+
+    fn update_trajectory_status(&mut self) {
+        let text = match self.trajectory.get_kind() {
+            TrajectoryKind::Off => None,
+            kind => Some(format!("3D {} x{}", kind.to_str(), self.trajectory.get_speed())),
+        };
+
+        self.status.set_entry("trajectory", text);
+    }
+
+    /// Switches between the normal layout and a fullscreen window which only
+    /// shows the selected view.
+    fn toggle_fullscreen(&mut self, window: &mut RenderWindow) {
+        self.fullscreen = !self.fullscreen;
+        *window = create_window(self.fullscreen);
+        window.set_mouse_cursor_visible(!self.fullscreen);
+        self.seeking = false;
+        self.tooltip.hide();
+        self.place_bottom(window);
+        if self.fullscreen {
+            self.now_playing.show();
         }
     }
 
+    /// Gives the whole window to the views in fullscreen, or puts them back
+    /// under the tabs.
+    fn place_views(&mut self, window: &RenderWindow) {
+        let (position, size) = if self.fullscreen {
+            (
+                Vector2f { x: 0f32, y: 0f32 },
+                Vector2f {
+                    x: window.size().x as f32,
+                    y: window.size().y as f32,
+                },
+            )
+        } else {
+            (
+                Vector2f { x: 0f32, y: 25f32 },
+                Vector2f {
+                    x: 512f32,
+                    y: window.size().y as f32 - 57f32 - self.get_bottom_offset(),
+                },
+            )
+        };
+
+        self.spectrum.set_size(&size);
+        self.spectrum.set_position(&position);
+        self.spectrogram.set_size(&size);
+        self.spectrogram.set_position(&position);
+        self.oscilloscope.set_size(&size);
+        self.oscilloscope.set_position(&position);
+        self.goniometer.set_size(&size);
+        self.goniometer.set_position(&position);
+        // the 3D view keeps a pixel above and under it
+        self.graph_sound.set_size(&Vector2f {
+            x: size.x,
+            y: size.y - 2f32,
+        });
+        self.graph_sound.set_position(&Vector2f {
+            x: position.x,
+            y: position.y + 1f32,
+        });
+        self.now_playing.set_size(&Vector2f {
+            x: window.size().x as f32,
+            y: window.size().y as f32,
+        });
+        let view = self.view;
+
+        self.select_view(view);
+    }
+
     pub fn set_music_position(&mut self, position: usize) {
         self.music_bar.set_progress(position);
     }
 
+    fn draw_view(&mut self, win: &mut RenderWindow) {
+        match self.view {
+            View::Spectrum => self.spectrum.draw(win),
+            View::Spectrogram => self.spectrogram.draw(win),
+            View::Oscilloscope => self.oscilloscope.draw(win),
+            View::Goniometer => self.goniometer.draw(win),
+            View::Position => self.graph_sound.draw(win),
+        }
+    }
+
     pub fn update(&mut self, win: &mut RenderWindow) {
         win.clear(&Color::BLACK);
+        if self.fullscreen {
+            self.draw_view(win);
+            self.now_playing.draw(win);
+            win.display();
+            return;
+        }
         self.musics.draw(win);
+        if self.show_bookmarks {
+            self.bookmarks.draw(win);
+        } else {
+            self.lyrics.draw(win);
+        }
         self.volume_bar.draw(win);
+        self.vu_meter.draw(win);
         self.timer.draw(win);
         self.spectrum_button.draw(win);
+        self.spectrogram_button.draw(win);
+        self.oscilloscope_button.draw(win);
+        self.goniometer_button.draw(win);
         self.position_button.draw(win);
-        if self.spectrum_button.is_pushed() {
-            self.spectrum.draw(win);
+        if self.show_equalizer {
+            self.equalizer_panel.draw(win);
         } else {
-            self.graph_sound.draw(win);
+            self.draw_view(win);
         }
+        self.status.draw(win);
         self.music_bar.draw(win);
+        self.tooltip.draw(win);
         win.display();
     }
 
@@ -213,15 +1641,139 @@ impl<'b> GraphicHandler<'b> {
             },
             &Default::default(),
         );
-        chan.set_volume(self.volume_bar.get_real_value() as f32 / 100f32);
+        self.set_chan_balance(chan);
+    }
+
+    fn set_chan_balance(&self, chan: &rfmod::Channel) {
+        volume::set_balance(chan, self.config.balance);
+    }
+
+    fn set_balance(&mut self, balance: f32) {
+        // avoids keeping something like 0.0000001 after a few steps
+        self.config.balance = (balance.max(-1f32).min(1f32) * 10f32).round() / 10f32;
+        Config::save_option("balance", &self.config.balance.to_string());
+        match self.track {
+            Some(ref t) => self.set_chan_balance(&t.chan),
+            None => {}
+        }
+        match self.next {
+            Some(ref n) => self.set_chan_balance(&n.chan),
+            None => {}
+        }
+        match self.fade {
+            Some(ref f) => self.set_chan_balance(&f.outgoing.chan),
+            None => {}
+        }
+        self.update_volume_status();
+    }
+
+    fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.update_volume();
+        self.update_volume_status();
+    }
+
+    fn change_volume(&mut self, volume: usize) {
+        // changing the volume by hand gives the sound back
+        self.muted = false;
+        self.volume_bar.set_progress(volume);
+        self.update_volume();
+        self.update_volume_status();
+    }
+
+    fn update_volume_status(&mut self) {
+        let balance = self.config.balance;
+
+        self.status.set_entry(
+            "mute",
+            if self.muted {
+                Some("Muted".to_owned())
+            } else {
+                None
+            },
+        );
+        self.status.set_entry(
+            "balance",
+            if balance < 0f32 {
+                Some(format!("Balance L{:.0}%", -balance * 100f32))
+            } else if balance > 0f32 {
+                Some(format!("Balance R{:.0}%", balance * 100f32))
+            } else {
+                None
+            },
+        );
+    }
+
+    /// Returns the text shown when hovering the volume bar at `value`.
+    fn get_volume_text(&self, value: usize) -> String {
+        let volume = self
+            .config
+            .volume_curve
+            .get_volume(value as f32 / self.volume_bar.maximum as f32);
+
+        match volume::to_db(volume) {
+            Some(db) => format!("{}% ({:.1} dB)", value, db),
+            None => format!("{}% (-inf dB)", value),
+        }
     }
 
-    fn main_loop(
-        &mut self,
-        chan: &rfmod::Channel,
-        old_position: usize,
-        length: u32,
-    ) -> Option<usize> {
+    fn get_gain(&self, track: &Track) -> f32 {
+        track
+            .replay_gain
+            .get_factor(self.config.replay_gain, self.config.replay_gain_preamp)
+    }
+
+    fn update_volume(&self) {
+        let volume = if self.muted {
+            0f32
+        } else {
+            self.config
+                .volume_curve
+                .get_volume(self.volume_bar.get_real_value() as f32 / 100f32)
+                * self.sleep_fade
+        };
+        let fade_in = match self.fade {
+            Some(ref f) => {
+                f.outgoing
+                    .chan
+                    .set_volume(volume * self.get_gain(&f.outgoing) * f.get_out_volume());
+                f.get_in_volume()
+            }
+            None => 1f32,
+        };
+
+        match self.track {
+            Some(ref t) => {
+                t.chan.set_volume(volume * self.get_gain(t) * fade_in);
+            }
+            None => {}
+        }
+        match self.next {
+            Some(ref n) => {
+                n.chan.set_volume(volume * self.get_gain(n));
+            }
+            None => {}
+        }
+    }
+
+    fn update_crossfade(&mut self) {
+        let over = match self.fade {
+            Some(ref f) => f.is_over(),
+            None => return,
+        };
+
+        if over {
+            self.fade = None;
+        }
+        self.update_volume();
+    }
+
+    fn main_loop(&mut self, old_position: usize, length: u32) -> Option<usize> {
+        let chan = match self.track {
+            Some(ref t) => &t.chan,
+            None => return None,
+        };
+
         match chan.is_playing() {
             Ok(b) => {
                 if b == true {
@@ -234,53 +1786,23 @@ impl<'b> GraphicHandler<'b> {
                     };
 
                     if position != old_position {
-                        match chan.get_spectrum(
-                            256usize,
-                            Some(1i32),
-                            Some(rfmod::DspFftWindow::Rect),
-                        ) {
-                            Ok(f) => {
-                                self.spectrum.update_spectrum(
-                                    &match chan.get_spectrum(
-                                        256usize,
-                                        Some(0i32),
-                                        Some(rfmod::DspFftWindow::Rect),
-                                    ) {
-                                        Ok(s) => s,
-                                        Err(_) => {
-                                            let mut tmp = Vec::with_capacity(256);
-
-                                            for _ in 0..256 {
-                                                tmp.push(0f32);
-                                            }
-                                            tmp
-                                        }
-                                    },
-                                    &f,
-                                );
-                            }
-                            Err(_) => {
-                                self.spectrum.update_spectrum(
-                                    &match chan.get_spectrum(
-                                        512usize,
-                                        Some(0i32),
-                                        Some(rfmod::DspFftWindow::Rect),
-                                    ) {
-                                        Ok(s) => s,
-                                        Err(_) => {
-                                            let mut tmp = Vec::with_capacity(256);
-
-                                            for _ in 0..256 {
-                                                tmp.push(0f32);
-                                            }
-                                            tmp
-                                        }
-                                    },
-                                    &Vec::new(),
-                                );
-                            }
-                        };
+                        let (left, right) = fft::get_spectrum(chan, &self.config.fft);
+
+                        self.beat.update(&left, &right, position);
+                        self.spectrum.set_pulse(self.beat.get_pulse());
+                        self.spectrum.update_spectrum(&left, &right);
+                        self.spectrogram.update_spectrum(&left, &right);
+                        let (left, right) = get_wave_data(chan);
+
+                        self.vu_meter.update_levels(&left, &right);
+                        if self.view == View::Oscilloscope || self.view == View::Goniometer {
+                            self.oscilloscope.update_wave(&left, &right);
+                            self.goniometer.update_wave(&left, &right);
+                        }
                         self.timer.update_display(position, length as usize);
+                        self.lyrics.update_time(position);
+                        self.musics.update_chapter(position);
+                        self.bookmarks.update_time(position);
                         Some(position)
                     } else {
                         Some(old_position)
@@ -296,101 +1818,285 @@ impl<'b> GraphicHandler<'b> {
     pub fn start(&mut self, window: &mut RenderWindow, fmod: &rfmod::Sys) {
         let mut old_position = 100usize;
         let mut tmp_s = self.playlist.get_current();
-        let mut sound = match self.set_music(fmod, tmp_s) {
-            Ok(s) => s,
-            Err(e) => panic!("Error : {:?}", e),
-        };
-        let mut chan = match sound.play() {
-            Ok(c) => c,
-            Err(e) => panic!("sound.play : {:?}", e),
-        };
-        self.set_chan_params(&chan);
-        let forward = rfmod::Vector {
-            x: 0f32,
-            y: 0f32,
-            z: 1f32,
+
+        self.output_rate = match fmod.get_software_format() {
+            Ok((rate, ..)) => rate as u64,
+            Err(_) => 48000u64,
         };
+        self.spectrum.set_sample_rate(self.output_rate);
+        self.spectrogram.set_sample_rate(self.output_rate);
+        self.equalizer.attach(fmod);
+        self.status.set_entry("output", output::get_driver_name(fmod));
+        self.change_music(fmod, tmp_s, 0f32);
         let up = rfmod::Vector {
             x: 0f32,
             y: 1f32,
             z: 0f32,
         };
         let mut listener_pos = rfmod::Vector::new();
-        let mut last_pos = rfmod::Vector::new();
 
+        self.place_bottom(window);
         window.clear(&Color::BLACK);
 
         loop {
             while let Some(event) = window.poll_event() {
                 match event {
-                    Event::Closed => window.close(),
+                    Event::Closed => self.quit(window),
+                    // while a bookmark name is typed, keys only edit it
+                    Event::TextEntered { unicode } if self.new_bookmark.is_some() => {
+                        if !unicode.is_control() {
+                            match self.new_bookmark {
+                                Some((_, ref mut n)) => n.push(unicode),
+                                None => {}
+                            }
+                            self.update_bookmark_entry();
+                        }
+                    }
+                    Event::KeyPressed { code, .. } if self.new_bookmark.is_some() => {
+                        if code == Key::BackSpace {
+                            match self.new_bookmark {
+                                Some((_, ref mut n)) => {
+                                    n.pop();
+                                }
+                                None => {}
+                            }
+                            self.update_bookmark_entry();
+                        }
+                    }
+                    Event::KeyReleased { code, .. } if self.new_bookmark.is_some() => match code {
+                        Key::Return => self.add_bookmark(),
+                        Key::Escape => {
+                            self.new_bookmark = None;
+                            self.update_bookmark_entry();
+                        }
+                        _ => {}
+                    },
                     Event::KeyReleased { code, .. } => match code {
-                        Key::Escape => window.close(),
+                        Key::Escape if self.fullscreen => self.toggle_fullscreen(window),
+                        Key::F11 => self.toggle_fullscreen(window),
+                        Key::Escape => self.quit(window),
                         Key::Up => {
                             tmp_s = self.playlist.get_prev();
-                            sound = match self.set_music(fmod, tmp_s) {
-                                Ok(s) => s,
-                                Err(e) => panic!("Error : {:?}", e),
-                            };
-                            chan = match sound.play() {
-                                Ok(c) => c,
-                                Err(e) => panic!("sound.play : {:?}", e),
-                            };
-                            self.set_chan_params(&chan);
+                            let fade = self.config.skip_fade;
+                            self.change_music(fmod, tmp_s, fade);
                         }
                         Key::Down => {
                             tmp_s = self.playlist.get_next();
-                            sound = match self.set_music(fmod, tmp_s) {
-                                Ok(s) => s,
-                                Err(e) => panic!("Error : {:?}", e),
-                            };
-                            chan = match sound.play() {
-                                Ok(c) => c,
-                                Err(e) => panic!("sound.play : {:?}", e),
-                            };
-                            self.set_chan_params(&chan);
+                            let fade = self.config.skip_fade;
+                            self.change_music(fmod, tmp_s, fade);
                         }
                         Key::Space => {
-                            chan.set_paused(!match chan.get_paused() {
-                                Ok(p) => p,
-                                _ => false,
-                            });
+                            self.toggle_pause();
                         }
                         Key::Delete => {
                             self.musics.remove_music(self.playlist.get_pos());
                             self.playlist.remove_current();
                             tmp_s = self.playlist.get_current();
-                            sound = match self.set_music(fmod, tmp_s) {
-                                Ok(s) => s,
-                                Err(e) => panic!("Error : {:?}", e),
-                            };
-                            chan = match sound.play() {
-                                Ok(c) => c,
-                                Err(e) => panic!("sound.play : {:?}", e),
-                            };
-                            self.set_chan_params(&chan);
+                            let fade = self.config.skip_fade;
+                            self.change_music(fmod, tmp_s, fade);
                         }
                         Key::BackSpace => {
+                            self.set_trajectory(TrajectoryKind::Off);
                             self.graph_sound.reset_cross_pos();
                             listener_pos.x = self.graph_sound.x;
                             listener_pos.z = self.graph_sound.y;
                         }
+                        Key::E => {
+                            self.show_equalizer = !self.show_equalizer;
+                            let view = self.view;
+
+                            self.select_view(view);
+                        }
+                        Key::G => {
+                            self.config.replay_gain = self.config.replay_gain.next();
+                            Config::save_option("replaygain", self.config.replay_gain.to_str());
+                            println!("ReplayGain : {}", self.config.replay_gain.to_str());
+                            self.update_volume();
+                        }
                         Key::R => {
                             let repeat = self.playlist.get_repeat();
                             self.playlist.set_repeat(!repeat);
+                            self.cancel_next();
+                        }
+                        Key::Slash => self.change_speed(fmod, 1f32, 0i32),
+                        Key::LBracket => {
+                            let (a, b) = (Some(self.get_position()), self.loop_end);
+                            self.set_loop(a, b);
+                        }
+                        Key::RBracket => {
+                            let (a, b) = (self.loop_start, Some(self.get_position()));
+                            self.set_loop(a, b);
+                        }
+                        Key::BackSlash => self.set_loop(None, None),
+                        Key::B => self.start_bookmark(),
+                        Key::K => {
+                            self.show_bookmarks = !self.show_bookmarks;
+                            self.lyrics.need_to_draw = true;
+                            self.bookmarks.need_to_draw = true;
+                        }
+                        Key::X => self.export_bookmarks(),
+                        Key::Z => self.cycle_sleep_timer(),
+                        Key::W => self.toggle_waveform(window),
+                        Key::P => {
+                            let has_path = self.trajectory.has_path();
+                            let kind = self.trajectory.get_kind().next(has_path);
+
+                            self.set_trajectory(kind);
+                        }
+                        Key::M => self.toggle_mute(),
+                        Key::F2 => match output::next_driver(fmod) {
+                            Some(name) => {
+                                println!("Output : {}", name);
+                                self.status.set_entry("output", Some(name));
+                            }
+                            None => {}
+                        },
+                        Key::V => {
+                            self.config.volume_curve = self.config.volume_curve.next();
+                            Config::save_option("volume_curve", self.config.volume_curve.to_str());
+                            println!("Volume curve : {}", self.config.volume_curve.to_str());
+                            self.update_volume();
+                        }
+                        Key::Numpad5 => self.set_balance(0f32),
+                        Key::F9 => self.toggle_recording(fmod),
+                        Key::F6 => {
+                            self.config.fft.channels = self.config.fft.channels.next();
+                            Config::save_option("fft_channels", self.config.fft.channels.to_str());
+                            self.update_fft_status();
+                        }
+                        Key::F7 => {
+                            self.config.fft.next_size();
+                            Config::save_option("fft_size", &self.config.fft.size.to_string());
+                            self.update_fft_status();
+                        }
+                        Key::F8 => {
+                            self.config.fft.window = self.config.fft.window.next();
+                            Config::save_option("fft_window", self.config.fft.window.to_str());
+                            self.update_fft_status();
+                        }
+                        Key::F4 => {
+                            self.save_resume();
+                            self.config.audiobook = !self.config.audiobook;
+                            Config::save_option("audiobook", &self.config.audiobook.to_string());
+                            self.update_audiobook_status();
+                            self.save_resume();
+                        }
+                        Key::T => {
+                            self.config.time_stretch = !self.config.time_stretch;
+                            let stretch = self.config.time_stretch.to_string();
+
+                            Config::save_option("time_stretch", &stretch);
+                            let (speed, pitch) = (self.get_speed(), self.get_pitch());
+                            self.change_speed(fmod, speed, pitch);
                         }
                         _ => {}
                     },
-                    Event::KeyPressed { code, .. } => match code {
+                    Event::KeyPressed {
+                        code,
+                        ctrl,
+                        alt,
+                        shift,
+                        ..
+                    } => match code {
+                        Key::Num0
+                        | Key::Num1
+                        | Key::Num2
+                        | Key::Num3
+                        | Key::Num4
+                        | Key::Num5
+                        | Key::Num6
+                        | Key::Num7
+                        | Key::Num8
+                        | Key::Num9 => {
+                            let percent = code as usize - Key::Num0 as usize;
+                            let position = self.music_bar.maximum * percent / 10;
+                            self.seek(position);
+                        }
+                        Key::J => self.skip(if shift { -30000 } else { -15000 }),
+                        Key::L => self.skip(if shift { 30000 } else { 15000 }),
+                        Key::Left if ctrl => self.nudge_loop(true, false),
+                        Key::Right if ctrl => self.nudge_loop(true, true),
+                        Key::Left if alt => self.nudge_loop(false, false),
+                        Key::Right if alt => self.nudge_loop(false, true),
+                        Key::Left => self.skip(if shift { -30000 } else { -5000 }),
+                        Key::Right => self.skip(if shift { 30000 } else { 5000 }),
+                        Key::Home | Key::End => {
+                            self.trajectory.change_speed(code == Key::End);
+                            self.update_trajectory_status();
+                        }
+                        Key::PageUp => self.go_to_bookmark(false),
+                        Key::PageDown => self.go_to_bookmark(true),
+                        Key::Comma => {
+                            let (speed, pitch) = (self.get_speed(), self.get_pitch());
+                            self.change_speed(fmod, speed - SPEED_STEP, pitch);
+                        }
+                        Key::Period => {
+                            let (speed, pitch) = (self.get_speed(), self.get_pitch());
+                            self.change_speed(fmod, speed + SPEED_STEP, pitch);
+                        }
+                        Key::SemiColon => {
+                            let (speed, pitch) = (self.get_speed(), self.get_pitch());
+                            self.change_speed(fmod, speed, pitch - 1);
+                        }
+                        Key::Quote => {
+                            let (speed, pitch) = (self.get_speed(), self.get_pitch());
+                            self.change_speed(fmod, speed, pitch + 1);
+                        }
                         Key::Add => {
                             let tmp = self.volume_bar.get_real_value();
-                            self.volume_bar.set_progress(tmp + 1);
-                            chan.set_volume(self.volume_bar.get_real_value() as f32 / 100f32);
+                            self.change_volume(tmp + 1);
                         }
                         Key::Subtract => {
                             let tmp = self.volume_bar.get_real_value();
-                            self.volume_bar.set_progress(tmp - 1);
-                            chan.set_volume(self.volume_bar.get_real_value() as f32 / 100f32);
+                            if tmp > 0 {
+                                self.change_volume(tmp - 1);
+                            }
+                        }
+                        Key::Numpad4 => {
+                            let balance = self.config.balance;
+                            self.set_balance(balance - BALANCE_STEP);
+                        }
+                        Key::Numpad6 => {
+                            let balance = self.config.balance;
+                            self.set_balance(balance + BALANCE_STEP);
+                        }
+                        _ => {}
+                    },
+                    // only the view is shown, the other elements can't be reached
+                    Event::MouseButtonPressed { .. }
+                    | Event::MouseButtonReleased { .. }
+                    | Event::MouseMoved { .. }
+                    | Event::MouseWheelScrolled { .. }
+                        if self.fullscreen => {}
+                    Event::MouseButtonPressed { button, x, y } => match button {
+                        Button::Left => {
+                            let v = Vector2f {
+                                x: x as f32,
+                                y: y as f32,
+                            };
+
+                            if self.music_bar.is_inside(&v) && !is_shift_pressed() {
+                                self.seeking = true;
+                                self.music_bar.clicked(&v);
+                            } else if !self.show_equalizer
+                                && self.view == View::Position
+                                && self.graph_sound.is_inside(&v)
+                            {
+                                self.graph_sound.start_drag(&v);
+                            }
+                        }
+                        Button::Right => {
+                            let v = Vector2f {
+                                x: x as f32,
+                                y: y as f32,
+                            };
+
+                            if !self.show_equalizer
+                                && self.view == View::Position
+                                && self.graph_sound.is_inside(&v)
+                            {
+                                self.graph_sound.start_drawing(&v);
+                            }
                         }
                         _ => {}
                     },
@@ -401,85 +2107,242 @@ impl<'b> GraphicHandler<'b> {
                                 y: y as f32,
                             };
 
-                            if self.music_bar.is_inside(&v) {
+                            if self.graph_sound.is_dragging() {
+                                self.graph_sound.stop_drag();
+                            } else if self.seeking {
+                                // the cursor may have left the bar while dragging
+                                self.seeking = false;
+                                let position = self.music_bar.get_value_at(&v);
+                                self.music_bar.set_progress(position);
+                                self.seek(position);
+                            } else if self.music_bar.is_inside(&v) && is_shift_pressed() {
+                                let a = self.music_bar.get_value_at(&v);
+                                let b = self.loop_end;
+                                self.set_loop(Some(a), b);
+                            } else if self.music_bar.is_inside(&v) {
                                 self.music_bar.clicked(&v);
-                                chan.set_position(
-                                    self.music_bar.get_real_value(),
-                                    rfmod::TIMEUNIT_MS,
-                                );
+                                let position = self.music_bar.get_real_value();
+                                self.seek(position);
                             } else if self.volume_bar.is_inside(&v) {
-                                self.volume_bar.clicked(&v);
-                                chan.set_volume(self.volume_bar.get_real_value() as f32 / 100f32);
+                                let volume = self.volume_bar.get_value_at(&v);
+                                self.change_volume(volume);
+                            } else if self.vu_meter.is_inside(&v) {
+                                self.vu_meter.clicked(&v);
                             } else if self.musics.is_inside(&v) {
                                 let old_c = self.musics.get_current();
                                 self.musics.clicked(&v);
-                                if old_c != self.musics.get_current() {
+                                if let Some(t) = self.musics.get_clicked_chapter() {
+                                    self.seek(t);
+                                } else if old_c != self.musics.get_current() {
                                     self.playlist.set_actual(self.musics.get_current());
 
                                     let tmp_s = self.playlist.get_current();
 
-                                    sound = match self.set_music(fmod, tmp_s) {
-                                        Ok(s) => s,
-                                        Err(e) => panic!("Error : {:?}", e),
-                                    };
-                                    chan = match sound.play() {
-                                        Ok(c) => c,
-                                        Err(e) => panic!("sound.play : {:?}", e),
-                                    };
-                                    self.set_chan_params(&chan);
+                                    let fade = self.config.skip_fade;
+                                    self.change_music(fmod, tmp_s, fade);
+                                }
+                            } else if self.show_bookmarks && self.bookmarks.is_inside(&v) {
+                                self.bookmarks.clicked(&v);
+                                match self.bookmarks.get_clicked_time() {
+                                    Some(t) => self.seek(t),
+                                    None => {}
                                 }
-                            } else if !self.spectrum_button.is_pushed()
+                            } else if !self.show_bookmarks && self.lyrics.is_inside(&v) {
+                                self.lyrics.clicked(&v);
+                                match self.lyrics.get_clicked_time() {
+                                    Some(t) => self.seek(t),
+                                    None => {}
+                                }
+                            } else if self.show_equalizer && self.equalizer_panel.is_inside(&v) {
+                                self.equalizer_panel.clicked(&v);
+                                match self.equalizer_panel.get_action() {
+                                    Some(a) => self.equalizer_clicked(a),
+                                    None => {}
+                                }
+                            } else if !self.show_equalizer
+                                && self.view == View::Position
                                 && self.graph_sound.is_inside(&v)
                             {
+                                // a click takes the listener off its trajectory
+                                self.set_trajectory(TrajectoryKind::Off);
                                 self.graph_sound.clicked(&v);
                                 listener_pos.x = self.graph_sound.x;
                                 listener_pos.z = self.graph_sound.y;
-                            } else if self.spectrum_button.is_inside(&v)
-                                && !self.spectrum_button.is_pushed()
+                            } else if !self.show_equalizer
+                                && self.view == View::Spectrum
+                                && self.spectrum.is_inside(&v)
                             {
-                                self.spectrum_button.clicked(&v);
-                                self.position_button.clicked(&v);
-                                self.spectrum.need_to_draw = true;
-                            } else if self.position_button.is_inside(&v)
-                                && !self.position_button.is_pushed()
+                                let mode = self.spectrum.get_mode().next();
+
+                                self.spectrum.set_mode(mode);
+                            } else if !self.show_equalizer
+                                && self.view == View::Spectrogram
+                                && self.spectrogram.is_inside(&v)
                             {
-                                self.position_button.clicked(&v);
-                                self.spectrum_button.clicked(&v);
-                                self.graph_sound.need_to_draw = true;
+                                self.spectrogram.clicked(&v);
+                            } else if self.spectrum_button.is_inside(&v) {
+                                self.select_view(View::Spectrum);
+                            } else if self.spectrogram_button.is_inside(&v) {
+                                self.select_view(View::Spectrogram);
+                            } else if self.oscilloscope_button.is_inside(&v) {
+                                self.select_view(View::Oscilloscope);
+                            } else if self.goniometer_button.is_inside(&v) {
+                                self.select_view(View::Goniometer);
+                            } else if self.position_button.is_inside(&v) {
+                                self.select_view(View::Position);
+                            }
+                        }
+                        Button::Right => {
+                            let v = Vector2f {
+                                x: x as f32,
+                                y: y as f32,
+                            };
+
+                            if self.graph_sound.is_drawing() {
+                                let path = self.graph_sound.stop_drawing();
+
+                                if path.len() > 2 {
+                                    self.trajectory.set_path(path);
+                                    self.update_trajectory_status();
+                                }
+                                let outline = self.trajectory.get_outline();
+
+                                self.graph_sound.set_path(&outline);
+                            } else if self.music_bar.is_inside(&v) && is_shift_pressed() {
+                                let a = self.loop_start;
+                                let b = self.music_bar.get_value_at(&v);
+                                self.set_loop(a, Some(b));
+                            } else if self.show_bookmarks && self.bookmarks.is_inside(&v) {
+                                match self.bookmarks.get_bookmark_at(&v) {
+                                    Some(b) => self.remove_bookmark(b),
+                                    None => {}
+                                }
                             }
                         }
                         _ => {}
                     },
+                    Event::MouseWheelScrolled { delta, x, y, .. } => {
+                        let v = Vector2f {
+                            x: x as f32,
+                            y: y as f32,
+                        };
+
+                        if !self.show_equalizer
+                            && self.view == View::Position
+                            && self.graph_sound.is_inside(&v)
+                        {
+                            let angle = self.graph_sound.angle + delta * WHEEL_ROTATION;
+
+                            self.graph_sound.set_angle(angle);
+                        }
+                    }
                     Event::MouseMoved { x, y } => {
                         let v = Vector2f {
                             x: x as f32,
                             y: y as f32,
                         };
 
+                        if self.graph_sound.is_drawing() {
+                            self.graph_sound.add_path_point(&v);
+                        }
+                        if self.graph_sound.is_dragging() {
+                            self.graph_sound.drag_to(&v);
+                        }
+                        if self.seeking {
+                            // scrubbing
+                            let position = self.music_bar.get_value_at(&v);
+                            self.music_bar.set_progress(position);
+                            self.seek(position);
+                        }
+                        if self.seeking || self.music_bar.is_inside(&v) {
+                            let time = self.music_bar.get_value_at(&v);
+                            let anchor = Vector2f {
+                                x: v.x,
+                                y: self.music_bar.get_position().y,
+                            };
+                            self.tooltip.show(
+                                &graphic_timer::format_time(time),
+                                &anchor,
+                                window.size().x as f32,
+                            );
+                        } else if self.volume_bar.is_inside(&v) {
+                            let value = self.volume_bar.get_value_at(&v);
+                            let text = self.get_volume_text(value);
+                            let anchor = Vector2f {
+                                x: v.x,
+                                y: self.volume_bar.get_position().y,
+                            };
+                            self.tooltip.show(&text, &anchor, window.size().x as f32);
+                        } else if self.vu_meter.is_inside(&v) {
+                            let text = self.vu_meter.get_text();
+                            let anchor = Vector2f {
+                                x: v.x,
+                                y: self.vu_meter.get_position().y,
+                            };
+                            self.tooltip.show(&text, &anchor, window.size().x as f32);
+                        } else {
+                            self.tooltip.hide();
+                        }
                         if self.musics.is_inside(&v) {
                             self.musics.cursor_moved(&v);
                         } else {
                             self.musics.mouse_leave();
                         }
+                        if self.lyrics.is_inside(&v) {
+                            self.lyrics.cursor_moved(&v);
+                        } else {
+                            self.lyrics.mouse_leave();
+                        }
+                        if self.bookmarks.is_inside(&v) {
+                            self.bookmarks.cursor_moved(&v);
+                        } else {
+                            self.bookmarks.mouse_leave();
+                        }
                         if self.spectrum_button.is_inside(&v) {
                             self.spectrum_button.cursor_moved(&v);
                         } else {
                             self.spectrum_button.mouse_leave();
                         }
+                        if self.spectrogram_button.is_inside(&v) {
+                            self.spectrogram_button.cursor_moved(&v);
+                        } else {
+                            self.spectrogram_button.mouse_leave();
+                        }
+                        if self.oscilloscope_button.is_inside(&v) {
+                            self.oscilloscope_button.cursor_moved(&v);
+                        } else {
+                            self.oscilloscope_button.mouse_leave();
+                        }
+                        if self.goniometer_button.is_inside(&v) {
+                            self.goniometer_button.cursor_moved(&v);
+                        } else {
+                            self.goniometer_button.mouse_leave();
+                        }
                         if self.position_button.is_inside(&v) {
                             self.position_button.cursor_moved(&v);
                         } else {
                             self.position_button.mouse_leave();
                         }
+                        if self.show_equalizer && self.equalizer_panel.is_inside(&v) {
+                            self.equalizer_panel.cursor_moved(&v);
+                        } else {
+                            self.equalizer_panel.mouse_leave();
+                        }
                     }
                     _ => {}
                 }
             }
 
+            self.check_sleep_timer();
             let length = self.music_bar.maximum as u32;
-            old_position = match self.main_loop(&chan, old_position, length) {
+            old_position = match self.main_loop(old_position, length) {
                 Some(p) => {
-                    self.set_music_position(p);
+                    if !self.seeking {
+                        self.set_music_position(p);
+                    }
+                    if !self.check_loop(p) {
+                        self.check_music_end(fmod, p, length as usize);
+                    }
                     p
                 }
                 None => {
@@ -487,32 +2350,100 @@ impl<'b> GraphicHandler<'b> {
                         true => self.playlist.get_current(),
                         false => self.playlist.get_next(),
                     };
-                    sound = match self.set_music(fmod, tmp_s) {
-                        Ok(s) => s,
-                        Err(e) => panic!("Error : {:?}", e),
-                    };
-                    chan = match sound.play() {
-                        Ok(c) => c,
-                        Err(e) => panic!("sound.play : {:?}", e),
-                    };
-                    self.set_chan_params(&chan);
+                    // the music is over, it will be started from the beginning
+                    self.save_resume();
+                    let sleep = self.sleep.stops_after_track(self.last_of_album);
+                    match self.next.take() {
+                        // already playing since the previous one ended
+                        Some(n) => {
+                            self.show_music(&tmp_s, &n.sound);
+                            self.track = Some(n);
+                            self.next_prepared = false;
+                            self.set_loop(None, None);
+                            self.update_speed_status();
+                        }
+                        None => self.change_music(fmod, tmp_s, 0f32),
+                    }
+                    if sleep {
+                        self.sleep_fired();
+                    }
                     100usize
                 }
             };
-            fmod.set_3D_listener_attributes(
-                0,
-                &listener_pos,
-                &rfmod::Vector {
-                    x: (listener_pos.x - last_pos.x) * 30f32,
-                    y: (listener_pos.y - last_pos.y) * 30f32,
-                    z: (listener_pos.z - last_pos.z) * 30f32,
-                },
-                &forward,
-                &up,
-            );
-            last_pos = listener_pos;
+            self.update_crossfade();
+            self.check_analysis();
+            self.check_waveform();
+            self.check_bpm();
+            self.check_resume_save();
+            self.update_recording_status();
+            // clicks move the listener at once, only trajectories give it a
+            // velocity and so a Doppler effect
+            let velocity = match self.trajectory.update() {
+                Some(((x, y), (velocity_x, velocity_y))) => {
+                    self.graph_sound.set_listener(x, y);
+                    listener_pos.x = x;
+                    listener_pos.z = y;
+                    rfmod::Vector {
+                        x: velocity_x,
+                        y: 0f32,
+                        z: velocity_y,
+                    }
+                }
+                None => rfmod::Vector::new(),
+            };
+            let (forward_x, forward_z) = self.graph_sound.get_forward();
+            let forward = rfmod::Vector {
+                x: forward_x,
+                y: 0f32,
+                z: forward_z,
+            };
+
+            listener_pos.y = self.graph_sound.elevation;
+            fmod.set_3D_listener_attributes(0, &listener_pos, &velocity, &forward, &up);
             fmod.update();
             self.update(window);
         }
     }
 }
+
+/// Returns the last samples of both channels, the left one being used twice
+/// for mono musics.
+fn get_wave_data(chan: &rfmod::Channel) -> (Vec<f32>, Vec<f32>) {
+    let left = match chan.get_wave_data(WAVE_SIZE, 0i32) {
+        Ok(w) => w,
+        Err(_) => vec![0f32; WAVE_SIZE],
+    };
+    let right = match chan.get_wave_data(WAVE_SIZE, 1i32) {
+        Ok(w) => w,
+        Err(_) => left.clone(),
+    };
+
+    (left, right)
+}
+
+/// Creates the window of the player, taking the whole display in fullscreen.
+pub fn create_window(fullscreen: bool) -> RenderWindow {
+    let mut window = if fullscreen {
+        RenderWindow::new(
+            VideoMode::desktop_mode(),
+            "Music Player",
+            Style::FULLSCREEN,
+            &ContextSettings::default(),
+        )
+    } else {
+        RenderWindow::new(
+            VideoMode::new(WINDOW_WIDTH, WINDOW_HEIGHT, 32),
+            "Music Player",
+            Style::CLOSE,
+            &ContextSettings::default(),
+        )
+    };
+
+    window.set_vertical_sync_enabled(true);
+    window.set_framerate_limit(30u32);
+    window
+}
+
+fn is_shift_pressed() -> bool {
+    Key::LShift.is_pressed() || Key::RShift.is_pressed()
+}
@@ -0,0 +1,232 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use graphic_element::GraphicElement;
+use rfmod;
+use sfml::graphics::{Color, Font, RectangleShape, RenderTarget, RenderWindow, Text};
+use sfml::graphics::{Shape, Transformable};
+use sfml::system::Vector2f;
+use std::path::Path;
+use std::time::Instant;
+use tags;
+
+// durations (in seconds) of the fade in, of the full display and of the fade out
+const FADE_IN: f32 = 0.5f32;
+const SHOWN: f32 = 5f32;
+const FADE_OUT: f32 = 1.5f32;
+// space between the box and the sides of the element
+const MARGIN: f32 = 40f32;
+
+/// The title and the artist of the current music, shown for a few seconds
+/// in the bottom left corner of the fullscreen visualizer.
+pub struct GraphicNowPlaying<'b> {
+    title: Text<'b>,
+    artist: Text<'b>,
+    cleaner: RectangleShape<'b>,
+    position: Vector2f,
+    size: Vector2f,
+    // when the overlay was shown for the last time
+    start: Option<Instant>,
+    pub need_to_draw: bool,
+    name: String,
+}
+
+impl<'b> GraphicNowPlaying<'b> {
+    fn init(mut self, position: &Vector2f) -> GraphicNowPlaying<'b> {
+        self.set_position(position);
+        self
+    }
+
+    /// Uses the tags of `sound` or, if it has none, the file name of `music`.
+    pub fn set_music(&mut self, music: &str, sound: &rfmod::Sound) {
+        let title = match get_title(sound) {
+            Some(t) => t,
+            None => match Path::new(music).file_stem() {
+                Some(s) => s.to_string_lossy().into_owned(),
+                None => music.to_owned(),
+            },
+        };
+
+        self.title.set_string(&title);
+        self.artist.set_string(&get_artist(sound).unwrap_or(String::new()));
+        self.place_texts();
+        self.show();
+    }
+
+    /// Starts the fade in again.
+    pub fn show(&mut self) {
+        self.start = Some(Instant::now());
+        self.need_to_draw = true;
+    }
+
+    /// Opacity, from 0 to 1, of the overlay at this time.
+    fn get_alpha(&self) -> f32 {
+        let elapsed = match self.start {
+            Some(s) => {
+                let e = s.elapsed();
+
+                e.as_secs() as f32 + e.subsec_nanos() as f32 / 1_000_000_000f32
+            }
+            None => return 0f32,
+        };
+
+        if elapsed < FADE_IN {
+            elapsed / FADE_IN
+        } else if elapsed < FADE_IN + SHOWN {
+            1f32
+        } else {
+            (1f32 - (elapsed - FADE_IN - SHOWN) / FADE_OUT).max(0f32)
+        }
+    }
+
+    fn place_texts(&mut self) {
+        let title = self.title.local_bounds();
+        let artist = self.artist.local_bounds();
+        let height = if artist.width > 0f32 { 90f32 } else { 60f32 };
+
+        self.cleaner.set_size(Vector2f {
+            x: title.width.max(artist.width) + 40f32,
+            y: height,
+        });
+        self.cleaner.set_position(Vector2f {
+            x: self.position.x + MARGIN,
+            y: self.position.y + self.size.y - MARGIN - height,
+        });
+        self.title.set_position(Vector2f {
+            x: self.position.x + MARGIN + 20f32,
+            y: self.position.y + self.size.y - MARGIN - height + 8f32,
+        });
+        self.artist.set_position(Vector2f {
+            x: self.position.x + MARGIN + 20f32,
+            y: self.position.y + self.size.y - MARGIN - 36f32,
+        });
+    }
+}
+
+impl<'b> GraphicElement<'b> for GraphicNowPlaying<'b> {
+    fn new_init(
+        size: &Vector2f,
+        position: &Vector2f,
+        unused: &Color,
+        font: Option<&'b Font>,
+    ) -> GraphicNowPlaying<'b> {
+        let font = match font {
+            Some(f) => f,
+            None => panic!("GraphicNowPlaying needs Font"),
+        };
+
+        GraphicNowPlaying {
+            title: Text::new("", font, 40),
+            artist: Text::new("", font, 24),
+            cleaner: RectangleShape::new(),
+            position: position.clone(),
+            size: size.clone(),
+            start: None,
+            need_to_draw: true,
+            name: String::new(),
+        }.init(position)
+    }
+
+    fn set_position(&mut self, position: &Vector2f) {
+        self.position = position.clone();
+        self.place_texts();
+        self.need_to_draw = true;
+    }
+
+    fn get_position(&self) -> Vector2f {
+        self.position
+    }
+
+    fn set_size(&mut self, size: &Vector2f) {
+        self.size = size.clone();
+        self.place_texts();
+        self.need_to_draw = true;
+    }
+
+    fn get_size(&self) -> Vector2f {
+        self.size
+    }
+
+    fn draw(&mut self, win: &mut RenderWindow) {
+        let alpha = self.get_alpha();
+
+        if alpha > 0f32 {
+            self.cleaner.set_fill_color(&Color::rgba(0, 0, 0, (alpha * 160f32) as u8));
+            self.title.set_fill_color(&Color::rgba(255, 255, 255, (alpha * 255f32) as u8));
+            self.artist.set_fill_color(&Color::rgba(180, 180, 180, (alpha * 255f32) as u8));
+            win.draw(&self.cleaner);
+            win.draw(&self.title);
+            win.draw(&self.artist);
+        }
+        self.need_to_draw = false;
+    }
+
+    fn is_inside(&self, pos: &Vector2f) -> bool {
+        false
+    }
+
+    fn get_min_size(&self) -> Vector2f {
+        Vector2f { x: 20f32, y: 20f32 }
+    }
+
+    fn get_max_size(&self) -> Option<Vector2f> {
+        None
+    }
+
+    fn set_element_name(&mut self, name: &String) {
+        self.name = name.clone();
+    }
+
+    fn get_element_name<'a>(&'a self) -> &'a String {
+        &self.name
+    }
+
+    fn cursor_moved(&mut self, position: &Vector2f) {}
+
+    fn clicked(&mut self, position: &Vector2f) {}
+
+    fn mouse_leave(&mut self) {}
+}
+
+fn get_title(sound: &rfmod::Sound) -> Option<String> {
+    match tags::get_tag_string(sound, "TITLE") {
+        Some(t) => Some(t),
+        None => match tags::get_id3_text(sound, "TIT2") {
+            Some(t) => Some(t),
+            None => tags::get_tag_string(sound, "title"),
+        },
+    }
+}
+
+fn get_artist(sound: &rfmod::Sound) -> Option<String> {
+    match tags::get_tag_string(sound, "ARTIST") {
+        Some(a) => Some(a),
+        None => match tags::get_id3_text(sound, "TPE1") {
+            Some(a) => Some(a),
+            None => tags::get_tag_string(sound, "artist"),
+        },
+    }
+}
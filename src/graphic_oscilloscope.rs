@@ -0,0 +1,218 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use graphic_element::GraphicElement;
+use sfml::graphics::Shape;
+use sfml::graphics::Transformable;
+use sfml::graphics::{Color, Font, PrimitiveType, RectangleShape, RenderTarget, RenderWindow};
+use sfml::graphics::{Vertex, VertexArray};
+use sfml::system::Vector2f;
+
+/// Dual-trace oscilloscope: the left channel is drawn on the top half and
+/// the right one on the bottom half. The traces start where the left channel
+/// crosses zero upwards so a steady wave doesn't move.
+pub struct GraphicOscilloscope<'b> {
+    cleaner: RectangleShape<'b>,
+    axes: Vec<RectangleShape<'b>>,
+    left: VertexArray,
+    right: VertexArray,
+    pub need_to_draw: bool,
+    name: String,
+}
+
+impl<'b> GraphicOscilloscope<'b> {
+    fn init(mut self, position: &Vector2f) -> GraphicOscilloscope<'b> {
+        self.cleaner.set_fill_color(&Color::rgb(0, 0, 0));
+        for _ in 0..3 {
+            let mut line = RectangleShape::new();
+
+            line.set_fill_color(&Color::rgb(50, 50, 50));
+            self.axes.push(line);
+        }
+        self.set_position(position);
+        self
+    }
+
+    /// Returns the index where `data` crosses zero upwards, if it does in
+    /// its first half.
+    fn get_trigger(data: &[f32]) -> usize {
+        for it in 1..data.len() / 2 {
+            if data[it - 1] < 0f32 && data[it] >= 0f32 {
+                return it;
+            }
+        }
+        0usize
+    }
+
+    fn set_trace(&self, trace: &mut VertexArray, data: &[f32], center: f32, color: &Color) {
+        let position = self.cleaner.position();
+        let size = self.cleaner.size();
+        let nb_points = data.len().max(2);
+        // each trace has half of the height
+        let scale = size.y / 4f32;
+
+        trace.clear();
+        for (it, sample) in data.iter().enumerate() {
+            let sample = sample.max(-1f32).min(1f32);
+
+            trace.append(&Vertex::with_pos_color(
+                Vector2f {
+                    x: position.x + it as f32 * size.x / (nb_points - 1) as f32,
+                    y: center - sample * scale,
+                },
+                *color,
+            ));
+        }
+    }
+
+    /// Shows half of the samples, from the trigger point.
+    pub fn update_wave(&mut self, data_left: &[f32], data_right: &[f32]) {
+        let len = data_left.len().min(data_right.len());
+
+        if len < 4 {
+            return;
+        }
+        let start = GraphicOscilloscope::get_trigger(&data_left[..len]);
+        let end = start + len / 2;
+        let position = self.cleaner.position();
+        let size = self.cleaner.size();
+        let mut left = VertexArray::new(PrimitiveType::LineStrip, 0);
+        let mut right = VertexArray::new(PrimitiveType::LineStrip, 0);
+
+        self.set_trace(
+            &mut left,
+            &data_left[start..end],
+            position.y + size.y / 4f32,
+            &Color::rgb(100, 220, 80),
+        );
+        self.set_trace(
+            &mut right,
+            &data_right[start..end],
+            position.y + size.y * 3f32 / 4f32,
+            &Color::rgb(255, 150, 50),
+        );
+        self.left = left;
+        self.right = right;
+        self.need_to_draw = true;
+    }
+}
+
+impl<'b> GraphicElement<'b> for GraphicOscilloscope<'b> {
+    fn new_init(
+        size: &Vector2f,
+        position: &Vector2f,
+        unused: &Color,
+        font: Option<&'b Font>,
+    ) -> GraphicOscilloscope<'b> {
+        GraphicOscilloscope {
+            cleaner: RectangleShape::with_size(Vector2f {
+                x: size.x,
+                y: size.y,
+            }),
+            axes: Vec::new(),
+            left: VertexArray::new(PrimitiveType::LineStrip, 0),
+            right: VertexArray::new(PrimitiveType::LineStrip, 0),
+            need_to_draw: true,
+            name: String::new(),
+        }.init(position)
+    }
+
+    fn set_position(&mut self, position: &Vector2f) {
+        let size = self.cleaner.size();
+
+        self.cleaner.set_position(Vector2f {
+            x: position.x,
+            y: position.y,
+        });
+        // the center of both traces and the line between them
+        for (it, line) in self.axes.iter_mut().enumerate() {
+            line.set_size(Vector2f { x: size.x, y: 1f32 });
+            line.set_position(Vector2f {
+                x: position.x,
+                y: position.y + size.y * (it + 1) as f32 / 4f32,
+            });
+        }
+        self.left.clear();
+        self.right.clear();
+        self.need_to_draw = true;
+    }
+
+    fn get_position(&self) -> Vector2f {
+        self.cleaner.position()
+    }
+
+    fn set_size(&mut self, size: &Vector2f) {
+        let pos = self.get_position();
+
+        self.cleaner.set_size(Vector2f {
+            x: size.x,
+            y: size.y,
+        });
+        self.set_position(&pos);
+    }
+
+    fn get_size(&self) -> Vector2f {
+        self.cleaner.size()
+    }
+
+    fn draw(&mut self, win: &mut RenderWindow) {
+        win.draw(&self.cleaner);
+        for line in self.axes.iter() {
+            win.draw(line);
+        }
+        win.draw(&self.left);
+        win.draw(&self.right);
+        self.need_to_draw = false;
+    }
+
+    fn is_inside(&self, pos: &Vector2f) -> bool {
+        pos.y >= self.cleaner.position().y
+            && pos.y <= self.cleaner.position().y + self.cleaner.size().y
+            && pos.x >= self.cleaner.position().x
+            && pos.x <= self.cleaner.position().x + self.cleaner.size().x
+    }
+
+    fn get_min_size(&self) -> Vector2f {
+        Vector2f { x: 20f32, y: 20f32 }
+    }
+
+    fn get_max_size(&self) -> Option<Vector2f> {
+        None
+    }
+
+    fn cursor_moved(&mut self, position: &Vector2f) {}
+
+    fn clicked(&mut self, position: &Vector2f) {}
+
+    fn mouse_leave(&mut self) {}
+
+    fn set_element_name(&mut self, name: &String) {
+        self.name = name.clone();
+    }
+
+    fn get_element_name<'a>(&'a self) -> &'a String {
+        &self.name
+    }
+}
@@ -23,14 +23,27 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
+use chapters::Chapter;
 use graphic_element::GraphicElement;
 use sfml::graphics::{Color, Font, RectangleShape, RenderTarget, RenderWindow, Text};
 use sfml::graphics::{Shape, Transformable};
 use sfml::system::Vector2f;
 
+/// What is displayed on a line of the playlist. The chapters of the current
+/// music are listed right after it.
+#[derive(Clone, Copy, PartialEq)]
+enum Row {
+    Music(usize),
+    Chapter(usize),
+}
+
 pub struct GraphicPlayList<'a> {
     musics: Vec<String>,
     texts: Vec<Text<'a>>,
+    chapters: Vec<Text<'a>>,
+    chapter_times: Vec<usize>,
+    current_chapter: Option<usize>,
+    clicked_chapter: Option<usize>,
     to_draw: usize,
     current: usize,
     hover_element: Option<usize>,
@@ -38,7 +51,7 @@ pub struct GraphicPlayList<'a> {
     cleaner: RectangleShape<'a>,
     need_to_draw: bool,
     has_mouse: bool,
-    font: Font,
+    font: &'a Font,
     name: String,
 }
 
@@ -88,14 +101,15 @@ impl<'b> GraphicPlayList<'b> {
 
     pub fn set_to_add(&mut self, to_add: isize) {
         let tmp_add = to_add * 22isize;
-        let max = (self.texts.len() as isize + 1isize) * 22isize;
+        let max = (self.get_nb_rows() as isize + 1isize) * 22isize;
 
         if self.add_to_view != to_add && tmp_add >= 0isize
             && tmp_add + self.to_draw as isize * 22isize < max
-            && self.texts.len() as isize * 22isize >= (self.cleaner.size().y as isize - 1)
+            && self.get_nb_rows() as isize * 22isize >= (self.cleaner.size().y as isize - 1)
         {
             let mut pos = self.cleaner.position().y as isize - tmp_add as isize;
-            for tmp in self.texts.iter_mut() {
+            for row in 0..self.get_nb_rows() {
+                let tmp = self.get_row_text(row);
                 let x = tmp.position().x;
                 tmp.set_position(Vector2f {
                     x: x as f32,
@@ -114,6 +128,8 @@ impl<'b> GraphicPlayList<'b> {
 
     fn set_current_intern(&mut self, current: usize, by_click: bool) {
         if self.texts.len() > 0 && current != self.current {
+            // the chapters belong to the previous music
+            self.set_chapters(&[]);
             if self.current < self.texts.len() {
                 self.texts[self.current].set_fill_color(&Color::rgb(255, 255, 255));
             }
@@ -123,7 +139,7 @@ impl<'b> GraphicPlayList<'b> {
             let tmp_to_draw = self.to_draw;
 
             if by_click == false
-                && self.texts.len() as isize * 22isize >= (self.cleaner.size().y as isize - 1)
+                && self.get_nb_rows() as isize * 22isize >= (self.cleaner.size().y as isize - 1)
             {
                 if self.current as isize + 2isize >= self.to_draw as isize + self.add_to_view {
                     self.set_to_add(current as isize + 2isize - tmp_to_draw as isize);
@@ -143,6 +159,7 @@ impl<'b> GraphicPlayList<'b> {
     }
 
     pub fn remove_music(&mut self, pos: usize) {
+        self.set_chapters(&[]);
         self.texts.remove(pos);
         let tmp = Vector2f {
             x: self.cleaner.position().x,
@@ -156,6 +173,106 @@ impl<'b> GraphicPlayList<'b> {
     }
 
     pub fn repeat(&mut self, pos: usize) {}
+
+    /// Lists the chapters of the current music under it.
+    pub fn set_chapters(&mut self, chapters: &[Chapter]) {
+        self.reset_hover();
+        self.chapters.clear();
+        self.chapter_times.clear();
+        for chapter in chapters.iter() {
+            let mut text = Text::new(&chapter.title, self.font, 16);
+
+            text.set_fill_color(&Color::rgb(180, 180, 180));
+            self.chapters.push(text);
+            self.chapter_times.push(chapter.time);
+        }
+        self.current_chapter = None;
+        let tmp = self.cleaner.position();
+        self.set_position(&tmp);
+        // set_position puts the first row on top, the view has to be moved again
+        let add_to_view = self.add_to_view;
+        self.add_to_view = 0isize;
+        self.set_to_add(add_to_view);
+    }
+
+    /// Highlights the chapter being played at `position` (in ms).
+    pub fn update_chapter(&mut self, position: usize) {
+        let current = self.chapter_times.iter().rposition(|t| *t <= position);
+
+        if current != self.current_chapter {
+            if let Some(c) = self.current_chapter {
+                self.chapters[c].set_fill_color(&Color::rgb(180, 180, 180));
+            }
+            if let Some(c) = current {
+                self.chapters[c].set_fill_color(&Color::rgb(255, 125, 25));
+            }
+            self.current_chapter = current;
+            self.need_to_draw = true;
+        }
+    }
+
+    /// Returns the start time of the last clicked chapter, if any.
+    pub fn get_clicked_chapter(&mut self) -> Option<usize> {
+        self.clicked_chapter.take()
+    }
+
+    fn get_nb_rows(&self) -> usize {
+        self.texts.len() + self.chapters.len()
+    }
+
+    fn get_row(&self, row: usize) -> Option<Row> {
+        if row <= self.current || self.chapters.is_empty() {
+            if row < self.texts.len() {
+                Some(Row::Music(row))
+            } else {
+                None
+            }
+        } else if row <= self.current + self.chapters.len() {
+            Some(Row::Chapter(row - self.current - 1))
+        } else if row < self.get_nb_rows() {
+            Some(Row::Music(row - self.chapters.len()))
+        } else {
+            None
+        }
+    }
+
+    fn get_row_text(&mut self, row: usize) -> &mut Text<'b> {
+        match self.get_row(row) {
+            Some(Row::Chapter(c)) => &mut self.chapters[c],
+            Some(Row::Music(m)) => &mut self.texts[m],
+            None => panic!("GraphicPlayList: no row {}", row),
+        }
+    }
+
+    /// Tells if the row is the current music or the current chapter.
+    fn is_current_row(&self, row: usize) -> bool {
+        match self.get_row(row) {
+            Some(Row::Music(m)) => m == self.current,
+            Some(Row::Chapter(c)) => Some(c) == self.current_chapter,
+            None => false,
+        }
+    }
+
+    fn set_hover_color(&mut self, row: usize, hover: bool) {
+        let color = match (self.get_row(row), hover) {
+            (_, true) => Color::rgb(255, 175, 100),
+            (Some(Row::Chapter(_)), false) => Color::rgb(180, 180, 180),
+            (_, false) => Color::rgb(255, 255, 255),
+        };
+
+        self.get_row_text(row).set_fill_color(&color);
+    }
+
+    fn reset_hover(&mut self) {
+        match self.hover_element.take() {
+            Some(s) => {
+                if s < self.get_nb_rows() && !self.is_current_row(s) {
+                    self.set_hover_color(s, false);
+                }
+            }
+            None => {}
+        }
+    }
 }
 
 impl<'b> GraphicElement<'b> for GraphicPlayList<'b> {
@@ -163,11 +280,15 @@ impl<'b> GraphicElement<'b> for GraphicPlayList<'b> {
         size: &Vector2f,
         position: &Vector2f,
         color: &Color,
-        font: Option<&Font>,
+        font: Option<&'b Font>,
     ) -> GraphicPlayList<'b> {
         GraphicPlayList {
             musics: Vec::new(),
             texts: Vec::new(),
+            chapters: Vec::new(),
+            chapter_times: Vec::new(),
+            current_chapter: None,
+            clicked_chapter: None,
             to_draw: 0usize,
             current: 1usize,
             cleaner: RectangleShape::with_size(Vector2f {
@@ -179,7 +300,7 @@ impl<'b> GraphicElement<'b> for GraphicPlayList<'b> {
             need_to_draw: true,
             has_mouse: false,
             font: match font {
-                Some(f) => f.clone(),
+                Some(f) => f,
                 None => panic!("GraphicPlayList needs Font"),
             },
             name: String::new(),
@@ -196,9 +317,17 @@ impl<'b> GraphicElement<'b> for GraphicPlayList<'b> {
             y: position.y,
         });
         if self.texts.len() > 0 {
-            for tmp in self.texts.iter_mut() {
-                tmp.set_position(Vector2f {
-                    x: self.cleaner.position().x + 4f32,
+            let x = self.cleaner.position().x + 4f32;
+
+            for row in 0..self.get_nb_rows() {
+                // chapters are shifted to the right of their music
+                let indent = match self.get_row(row) {
+                    Some(Row::Chapter(_)) => 20f32,
+                    _ => 0f32,
+                };
+
+                self.get_row_text(row).set_position(Vector2f {
+                    x: x + indent,
                     y: pos,
                 });
                 if pos < limit {
@@ -255,26 +384,15 @@ impl<'b> GraphicElement<'b> for GraphicPlayList<'b> {
 
         self.need_to_draw = true;
         self.has_mouse = true;
-        if tmp >= self.texts.len() {
-            self.hover_element = None;
+        if tmp >= self.get_nb_rows() {
+            self.reset_hover();
             return;
         }
-        match self.hover_element {
-            Some(s) => {
-                if self.current == tmp {
-                    self.texts[s].set_fill_color(&Color::rgb(255, 255, 255));
-                    self.hover_element = None;
-                } else if s != tmp {
-                    self.texts[s].set_fill_color(&Color::rgb(255, 255, 255));
-                    self.hover_element = Some(tmp);
-                    self.texts[tmp].set_fill_color(&Color::rgb(255, 175, 100));
-                }
-            }
-            None => {
-                if self.current != tmp {
-                    self.hover_element = Some(tmp);
-                    self.texts[tmp].set_fill_color(&Color::rgb(255, 175, 100));
-                }
+        if self.hover_element != Some(tmp) {
+            self.reset_hover();
+            if !self.is_current_row(tmp) {
+                self.hover_element = Some(tmp);
+                self.set_hover_color(tmp, true);
             }
         }
     }
@@ -285,29 +403,24 @@ impl<'b> GraphicElement<'b> for GraphicPlayList<'b> {
                 as usize;
 
             self.need_to_draw = true;
-            if tmp < self.texts.len() {
-                self.hover_element = match self.hover_element {
-                    Some(s) => {
-                        self.texts[s].set_fill_color(&Color::rgb(255, 255, 255));
-                        None
-                    }
-                    None => None,
-                };
-                self.set_current_intern(tmp, true);
+            match self.get_row(tmp) {
+                Some(Row::Music(m)) => {
+                    self.reset_hover();
+                    self.set_current_intern(m, true);
+                }
+                Some(Row::Chapter(c)) => {
+                    self.reset_hover();
+                    self.clicked_chapter = Some(self.chapter_times[c]);
+                }
+                None => {}
             }
         }
     }
 
     fn mouse_leave(&mut self) {
         if self.has_mouse {
-            match self.hover_element {
-                Some(s) => {
-                    self.texts[s].set_fill_color(&Color::rgb(255, 255, 255));
-                    self.hover_element = None;
-                    self.need_to_draw = true;
-                }
-                None => {}
-            }
+            self.reset_hover();
+            self.need_to_draw = true;
             self.has_mouse = false;
         }
     }
@@ -324,12 +437,12 @@ impl<'b> GraphicElement<'b> for GraphicPlayList<'b> {
 
         win.draw(&self.cleaner);
         if self.texts.len() > 0 {
-            for tmp in self.texts.iter_mut() {
+            for row in 0..self.get_nb_rows() {
                 if it == self.to_draw as isize + self.add_to_view {
                     break;
                 }
                 if it >= self.add_to_view as isize {
-                    win.draw(tmp);
+                    win.draw(&*self.get_row_text(row));
                 }
                 it += 1;
             }
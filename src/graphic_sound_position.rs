@@ -28,8 +28,23 @@ use sfml::graphics::Shape;
 use sfml::graphics::Text;
 use sfml::graphics::Transformable;
 use sfml::graphics::{CircleShape, Color, Font, RectangleShape, RenderTarget, RenderWindow};
+use sfml::graphics::{PrimitiveType, Vertex, VertexArray};
 use sfml::system::Vector2f;
 
+const ARROW_LENGTH: f32 = 40f32;
+// the elevation slider is in the top right corner, out of the circle
+const SLIDER_LENGTH: f32 = 100f32;
+const SLIDER_MARGIN: f32 = 12f32;
+// how far (in pixels) from the arrow's tip or from the slider it can be grabbed
+const GRAB_DISTANCE: f32 = 10f32;
+
+/// What the mouse is moving.
+#[derive(Clone, Copy, PartialEq)]
+enum Drag {
+    Elevation,
+    Direction,
+}
+
 pub struct GraphicSoundPosition<'b> {
     circle: CircleShape<'b>,
     center: CircleShape<'b>,
@@ -43,6 +58,22 @@ pub struct GraphicSoundPosition<'b> {
     pub x: f32,
     pub y: f32,
     pub limit: f32,
+    // trajectory of the listener, in the same units as x and y
+    path: Vec<(f32, f32)>,
+    path_shape: VertexArray,
+    // the path is being drawn with the right button
+    drawing: bool,
+    /// Height of the listener, from -limit to limit.
+    pub elevation: f32,
+    /// Direction the listener faces, in degrees, clockwise from the front.
+    pub angle: f32,
+    arrow: RectangleShape<'b>,
+    arrow_head: CircleShape<'b>,
+    slider: RectangleShape<'b>,
+    slider_knob: RectangleShape<'b>,
+    text_elevation: Text<'b>,
+    text_angle: Text<'b>,
+    drag: Option<Drag>,
 }
 
 impl<'b> GraphicSoundPosition<'b> {
@@ -55,6 +86,19 @@ impl<'b> GraphicSoundPosition<'b> {
         self.cross2.set_rotation(315f32);
         self.cross2.set_fill_color(&Color::rgb(255, 50, 50));
         self.cleaner.set_fill_color(&Color::rgb(0, 0, 0));
+        self.arrow.set_origin(Vector2f {
+            x: 1f32,
+            y: ARROW_LENGTH,
+        });
+        self.arrow.set_fill_color(&Color::rgb(255, 200, 50));
+        // the triangle's tip is at the end of the arrow
+        self.arrow_head.set_origin(Vector2f {
+            x: 6f32,
+            y: ARROW_LENGTH + 6f32,
+        });
+        self.arrow_head.set_fill_color(&Color::rgb(255, 200, 50));
+        self.slider.set_fill_color(&Color::rgb(80, 80, 80));
+        self.slider_knob.set_fill_color(&Color::rgb(255, 255, 255));
         let size = self.cleaner.size();
 
         self.set_cross_pos(&Vector2f {
@@ -74,25 +118,222 @@ impl<'b> GraphicSoundPosition<'b> {
             x: position.x - 5f32,
             y: position.y - 5f32,
         });
+        self.arrow.set_position(*position);
+        self.arrow.set_rotation(self.angle);
+        self.arrow_head.set_position(*position);
+        self.arrow_head.set_rotation(self.angle);
         self.need_to_draw = true;
     }
 
-    fn convert_cross_pos(&mut self) {
-        let tmp_x = self.x;
-        let tmp_y = self.y;
-        let tmp_size = self.cleaner.size();
-        let tmp_pos = self.cleaner.position();
-        let tmp_limit = self.limit;
+    fn place_slider(&mut self) {
+        let (top, x) = self.get_slider_pos();
+        let ratio = (self.limit - self.elevation) / (self.limit * 2f32);
 
-        self.set_cross_pos(&Vector2f {
-            x: (tmp_x + tmp_limit) * tmp_size.x / (tmp_limit * 2f32) + tmp_pos.x,
-            y: (tmp_y + tmp_limit) * tmp_size.y / (tmp_limit * 2f32) + tmp_pos.y,
+        self.slider.set_size(Vector2f {
+            x: 4f32,
+            y: SLIDER_LENGTH,
+        });
+        self.slider.set_position(Vector2f { x: x - 2f32, y: top });
+        self.slider_knob.set_size(Vector2f { x: 16f32, y: 4f32 });
+        self.slider_knob.set_position(Vector2f {
+            x: x - 8f32,
+            y: top + ratio * SLIDER_LENGTH - 2f32,
         });
     }
 
+    /// Returns the top and the horizontal center of the elevation slider.
+    fn get_slider_pos(&self) -> (f32, f32) {
+        let pos = self.cleaner.position();
+        let size = self.cleaner.size();
+
+        (pos.y + SLIDER_MARGIN, pos.x + size.x - SLIDER_MARGIN - 8f32)
+    }
+
+    /// Starts moving the elevation slider or the direction arrow if
+    /// `position` is on one of them.
+    pub fn start_drag(&mut self, position: &Vector2f) {
+        let (top, x) = self.get_slider_pos();
+        let (x_pos, y_pos) = (self.x, self.y);
+        let center = self.to_screen(x_pos, y_pos);
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let (tip_x, tip_y) = (center.x + sin * ARROW_LENGTH, center.y - cos * ARROW_LENGTH);
+
+        self.drag = if (position.x - x).abs() <= GRAB_DISTANCE
+            && position.y >= top - GRAB_DISTANCE
+            && position.y <= top + SLIDER_LENGTH + GRAB_DISTANCE
+        {
+            Some(Drag::Elevation)
+        } else if (position.x - tip_x).hypot(position.y - tip_y) <= GRAB_DISTANCE {
+            Some(Drag::Direction)
+        } else {
+            None
+        };
+        self.drag_to(position);
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    pub fn drag_to(&mut self, position: &Vector2f) {
+        match self.drag {
+            Some(Drag::Elevation) => {
+                let (top, _) = self.get_slider_pos();
+                let ratio = ((position.y - top) / SLIDER_LENGTH).max(0f32).min(1f32);
+                let elevation = self.limit - ratio * self.limit * 2f32;
+
+                self.set_elevation(elevation);
+            }
+            Some(Drag::Direction) => {
+                let (x_pos, y_pos) = (self.x, self.y);
+                let center = self.to_screen(x_pos, y_pos);
+                let angle = (position.x - center.x).atan2(center.y - position.y);
+
+                self.set_angle(angle.to_degrees());
+            }
+            None => {}
+        }
+    }
+
+    pub fn stop_drag(&mut self) {
+        self.drag = None;
+    }
+
+    pub fn set_elevation(&mut self, elevation: f32) {
+        self.elevation = elevation.max(-self.limit).min(self.limit);
+        self.place_slider();
+        self.update_texts();
+        self.need_to_draw = true;
+    }
+
+    /// Turns the listener to `angle` degrees, clockwise from the front.
+    pub fn set_angle(&mut self, angle: f32) {
+        self.angle = (angle % 360f32 + 360f32) % 360f32;
+        self.arrow.set_rotation(self.angle);
+        self.arrow_head.set_rotation(self.angle);
+        self.update_texts();
+        self.need_to_draw = true;
+    }
+
+    /// Returns the direction the listener faces, as `x` and `y` of a unit
+    /// vector.
+    pub fn get_forward(&self) -> (f32, f32) {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+
+        (sin, cos)
+    }
+
+    fn convert_cross_pos(&mut self) {
+        let (x, y) = (self.x, self.y);
+        let position = self.to_screen(x, y);
+
+        self.set_cross_pos(&position);
+        self.place_path();
+    }
+
+    /// Returns where the point (`x`, `y`) is drawn, `y` going up.
+    fn to_screen(&self, x: f32, y: f32) -> Vector2f {
+        let pos = self.cleaner.position();
+        let size = self.cleaner.size();
+        let radius = self.circle.radius();
+
+        Vector2f {
+            x: pos.x + size.x / 2f32 + x * radius / self.limit,
+            y: pos.y + size.y / 2f32 - y * radius / self.limit,
+        }
+    }
+
+    /// Returns the point under `position` if it is inside the circle.
+    fn to_world(&self, position: &Vector2f) -> Option<(f32, f32)> {
+        let tmp_x = self.cleaner.position().x + self.cleaner.size().x / 2f32 - position.x;
+        let tmp_y = self.cleaner.position().y + self.cleaner.size().y / 2f32 - position.y;
+        let radius = self.circle.radius();
+
+        if (tmp_x * tmp_x + tmp_y * tmp_y).sqrt() <= radius {
+            Some((tmp_x * self.limit / -radius, tmp_y * self.limit / radius))
+        } else {
+            None
+        }
+    }
+
+    fn update_texts(&mut self) {
+        self.text_x.set_string(&format!("x: {:.1}", self.x));
+        self.text_y.set_string(&format!("y: {:.1}", self.y));
+        self.text_elevation.set_string(&format!("height: {:.1}", self.elevation));
+        self.text_angle.set_string(&format!("angle: {:.0}", self.angle));
+    }
+
+    /// Moves the listener to (`x`, `y`) without a click.
+    pub fn set_listener(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        self.convert_cross_pos();
+        self.update_texts();
+    }
+
+    /// Shows the trajectory followed by the listener, an empty one hides it.
+    pub fn set_path(&mut self, path: &[(f32, f32)]) {
+        self.path = path.to_vec();
+        self.place_path();
+    }
+
+    fn place_path(&mut self) {
+        let points: Vec<Vector2f> = self.path.iter().map(|p| self.to_screen(p.0, p.1)).collect();
+
+        self.path_shape.clear();
+        for point in points.iter() {
+            self.path_shape.append(&Vertex::with_pos_color(*point, Color::rgb(90, 90, 160)));
+        }
+        self.need_to_draw = true;
+    }
+
+    /// Starts a new path if `position` is inside the circle.
+    pub fn start_drawing(&mut self, position: &Vector2f) {
+        if let Some(point) = self.to_world(position) {
+            self.drawing = true;
+            self.set_path(&[point]);
+        }
+    }
+
+    pub fn is_drawing(&self) -> bool {
+        self.drawing
+    }
+
+    /// Adds the point under `position`, kept inside the circle, to the path.
+    pub fn add_path_point(&mut self, position: &Vector2f) {
+        let pos = self.cleaner.position();
+        let size = self.cleaner.size();
+        let (dx, dy) = (position.x - pos.x - size.x / 2f32, position.y - pos.y - size.y / 2f32);
+        let distance = (dx * dx + dy * dy).sqrt();
+        let radius = self.circle.radius();
+        let ratio = if distance > radius {
+            radius / distance
+        } else {
+            1f32
+        };
+        let point = (dx * ratio * self.limit / radius, -dy * ratio * self.limit / radius);
+
+        match self.path.last() {
+            // points too close to each other aren't kept
+            Some(last) if (last.0 - point.0).abs() + (last.1 - point.1).abs() < 0.5f32 => return,
+            _ => {}
+        }
+        self.path.push(point);
+        self.place_path();
+    }
+
+    /// Returns the drawn path.
+    pub fn stop_drawing(&mut self) -> Vec<(f32, f32)> {
+        self.drawing = false;
+        self.path.clone()
+    }
+
+    /// Puts the listener back in the center, facing the front.
     pub fn reset_cross_pos(&mut self) {
         self.x = 0f32;
         self.y = 0f32;
+        self.angle = 0f32;
+        self.set_elevation(0f32);
         let pos = self.cleaner.position();
         let size = self.cleaner.size();
 
@@ -100,8 +341,7 @@ impl<'b> GraphicSoundPosition<'b> {
             x: pos.x + size.x / 2f32,
             y: pos.y + size.y / 2f32,
         });
-        self.text_x.set_string(&format!("x: {}", self.x));
-        self.text_y.set_string(&format!("y: {}", self.y));
+        self.update_texts();
     }
 }
 
@@ -139,6 +379,21 @@ impl<'b> GraphicElement<'b> for GraphicSoundPosition<'b> {
             x: 0f32,
             y: 0f32,
             limit: 30f32,
+            path: Vec::new(),
+            path_shape: VertexArray::new(PrimitiveType::LineStrip, 0),
+            drawing: false,
+            elevation: 0f32,
+            angle: 0f32,
+            arrow: RectangleShape::with_size(Vector2f {
+                x: 2f32,
+                y: ARROW_LENGTH,
+            }),
+            arrow_head: CircleShape::new(6f32, 3u32),
+            slider: RectangleShape::new(),
+            slider_knob: RectangleShape::new(),
+            text_elevation: Text::new("height: 0.0", &additionnal.unwrap(), 20),
+            text_angle: Text::new("angle: 0", &additionnal.unwrap(), 20),
+            drag: None,
         }.init(position)
     }
 
@@ -150,29 +405,24 @@ impl<'b> GraphicElement<'b> for GraphicSoundPosition<'b> {
     }
 
     fn clicked(&mut self, position: &Vector2f) {
-        let tmp_x = self.cleaner.position().x + self.cleaner.size().x / 2f32 - position.x;
-        let tmp_y = self.cleaner.position().y + self.cleaner.size().y / 2f32 - position.y;
-        let res = tmp_x * tmp_x + tmp_y * tmp_y;
-
-        if res.sqrt() <= self.circle.radius() {
-            let radius = self.circle.radius() as f32;
-            self.x = tmp_x * self.limit / -radius;
-            self.y = tmp_y * self.limit / radius;
-            self.set_cross_pos(&Vector2f {
-                x: position.x,
-                y: position.y,
-            });
-            self.text_x.set_string(&format!("x: {}", self.x));
-            self.text_y.set_string(&format!("y: {}", self.y));
+        if let Some((x, y)) = self.to_world(position) {
+            self.set_listener(x, y);
         }
     }
 
     fn draw(&mut self, win: &mut RenderWindow) {
         win.draw(&self.cleaner);
         win.draw(&self.circle);
+        win.draw(&self.path_shape);
         win.draw(&self.text_x);
         win.draw(&self.text_y);
+        win.draw(&self.text_elevation);
+        win.draw(&self.text_angle);
+        win.draw(&self.slider);
+        win.draw(&self.slider_knob);
         win.draw(&self.center);
+        win.draw(&self.arrow);
+        win.draw(&self.arrow_head);
         win.draw(&self.cross1);
         win.draw(&self.cross2);
         self.need_to_draw = false;
@@ -203,6 +453,15 @@ impl<'b> GraphicElement<'b> for GraphicSoundPosition<'b> {
             x: position.x + 1f32,
             y: position.y + 21f32,
         });
+        self.text_elevation.set_position(Vector2f {
+            x: position.x + 1f32,
+            y: position.y + 42f32,
+        });
+        self.text_angle.set_position(Vector2f {
+            x: position.x + 1f32,
+            y: position.y + 63f32,
+        });
+        self.place_slider();
         self.convert_cross_pos();
         self.need_to_draw = true;
     }
@@ -0,0 +1,352 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use graphic_element::GraphicElement;
+use sfml::graphics::Texture;
+use sfml::graphics::Transformable;
+use sfml::graphics::{Color, Font, IntRect, RenderTarget, RenderWindow, Sprite, Text};
+use sfml::system::Vector2f;
+
+const MIN_FREQUENCY: f32 = 20f32;
+// level (in dB) shown with the first color of the colormap
+const MIN_DB: f32 = -80f32;
+const LABEL_FREQUENCIES: [f32; 6] = [50f32, 200f32, 1000f32, 2000f32, 5000f32, 10000f32];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Colormap {
+    Heat,
+    Gray,
+    Green,
+}
+
+impl Colormap {
+    pub fn from_str(s: &str) -> Option<Colormap> {
+        match s {
+            "heat" => Some(Colormap::Heat),
+            "gray" => Some(Colormap::Gray),
+            "green" => Some(Colormap::Green),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> Colormap {
+        match *self {
+            Colormap::Heat => Colormap::Gray,
+            Colormap::Gray => Colormap::Green,
+            Colormap::Green => Colormap::Heat,
+        }
+    }
+
+    fn get_stops(&self) -> &'static [(u8, u8, u8)] {
+        match *self {
+            Colormap::Heat => &[
+                (0, 0, 0),
+                (30, 0, 100),
+                (150, 0, 120),
+                (230, 60, 20),
+                (255, 200, 0),
+                (255, 255, 255),
+            ],
+            Colormap::Gray => &[(0, 0, 0), (255, 255, 255)],
+            Colormap::Green => &[(0, 0, 0), (50, 100, 30), (150, 255, 100)],
+        }
+    }
+
+    /// Returns the color of `value` (from 0 to 1).
+    pub fn get_color(&self, value: f32) -> Color {
+        let stops = self.get_stops();
+        let pos = value.max(0f32).min(1f32) * (stops.len() - 1) as f32;
+        let index = (pos as usize).min(stops.len() - 2);
+        let ratio = pos - index as f32;
+        let (r1, g1, b1) = stops[index];
+        let (r2, g2, b2) = stops[index + 1];
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * ratio) as u8;
+
+        Color::rgb(mix(r1, r2), mix(g1, g2), mix(b1, b2))
+    }
+}
+
+/// Scrolling spectrogram: each spectrum becomes a column of pixels, the
+/// frequencies going up on a log scale. Clicking on it changes the colormap.
+pub struct GraphicSpectrogram<'b> {
+    texture: Texture,
+    // column of the texture where the next spectrum will be written
+    column: u32,
+    // level (from 0 to 1) of each pixel, to redraw them with another colormap
+    levels: Vec<f32>,
+    labels: Vec<Text<'b>>,
+    position: Vector2f,
+    colormap: Colormap,
+    sample_rate: f32,
+    to_update: bool,
+    pub need_to_draw: bool,
+    name: String,
+    font: Option<&'b Font>,
+}
+
+impl<'b> GraphicSpectrogram<'b> {
+    fn init(mut self, position: &Vector2f) -> GraphicSpectrogram<'b> {
+        if let Some(font) = self.font {
+            for frequency in LABEL_FREQUENCIES.iter() {
+                let label = if *frequency >= 1000f32 {
+                    format!("{}k", frequency / 1000f32)
+                } else {
+                    format!("{}", frequency)
+                };
+                let mut text = Text::new(&label, font, 10);
+
+                text.set_fill_color(&Color::rgb(200, 200, 200));
+                self.labels.push(text);
+            }
+        }
+        self.set_position(position);
+        self
+    }
+
+    fn get_nyquist(&self) -> f32 {
+        self.sample_rate / 2f32
+    }
+
+    /// Returns the frequency shown on `row`, the lowest ones being at the
+    /// bottom.
+    fn get_row_frequency(&self, row: u32, height: u32) -> f32 {
+        let ratio = (self.get_nyquist() / MIN_FREQUENCY).max(1f32);
+
+        MIN_FREQUENCY * ratio.powf((height - 1 - row) as f32 / height as f32)
+    }
+
+    fn place_labels(&mut self) {
+        let height = self.texture.size().y as f32;
+        let ratio = (self.get_nyquist() / MIN_FREQUENCY).max(1f32);
+
+        for (text, frequency) in self.labels.iter_mut().zip(LABEL_FREQUENCIES.iter()) {
+            let pos = (frequency / MIN_FREQUENCY).ln() / ratio.ln();
+
+            text.set_position(Vector2f {
+                x: self.position.x + 2f32,
+                y: self.position.y + height * (1f32 - pos) - 6f32,
+            });
+        }
+    }
+
+    pub fn get_colormap(&self) -> Colormap {
+        self.colormap
+    }
+
+    pub fn set_colormap(&mut self, colormap: Colormap) {
+        self.colormap = colormap;
+        self.redraw();
+    }
+
+    /// Sends every pixel to the texture again, from their levels.
+    fn redraw(&mut self) {
+        let colormap = self.colormap;
+        let mut pixels = Vec::with_capacity(self.levels.len() * 4);
+        let size = self.texture.size();
+
+        for level in self.levels.iter() {
+            push_color(&mut pixels, &colormap.get_color(*level));
+        }
+        self.texture.update_from_pixels(&pixels, size.x, size.y, 0, 0);
+        self.need_to_draw = true;
+    }
+
+    /// The spectrum goes from 0 to half of the output `rate`.
+    pub fn set_sample_rate(&mut self, rate: u64) {
+        self.sample_rate = rate as f32;
+        self.place_labels();
+    }
+
+    pub fn update_spectrum(&mut self, data_left: &Vec<f32>, data_right: &Vec<f32>) {
+        if !self.to_update {
+            self.to_update = true;
+            return;
+        }
+        self.to_update = false;
+        let nb_bins = data_left.len().max(data_right.len());
+        let size = self.texture.size();
+
+        if nb_bins == 0 || size.x == 0 || size.y == 0 {
+            return;
+        }
+        let bin_width = self.get_nyquist() / nb_bins as f32;
+        // RGBA pixels of the new column, the only part sent to the texture
+        let mut pixels = Vec::with_capacity(size.y as usize * 4);
+
+        for row in 0..size.y {
+            let frequency = self.get_row_frequency(row, size.y);
+            let bin = ((frequency / bin_width) as usize).min(nb_bins - 1);
+            let left = data_left.get(bin).cloned().unwrap_or(0f32);
+            let right = data_right.get(bin).cloned().unwrap_or(0f32);
+            let value = left.max(right);
+            let level = if value > 0f32 {
+                ((20f32 * value.log10() - MIN_DB) / -MIN_DB).max(0f32).min(1f32)
+            } else {
+                0f32
+            };
+
+            push_color(&mut pixels, &self.colormap.get_color(level));
+            self.levels[(row * size.x + self.column) as usize] = level;
+        }
+        self.texture.update_from_pixels(&pixels, 1, size.y, self.column, 0);
+        self.column = (self.column + 1) % size.x;
+        self.need_to_draw = true;
+    }
+
+    fn create_texture(&mut self, width: u32, height: u32) {
+        self.texture = match Texture::new(width, height) {
+            Some(t) => t,
+            None => panic!("Cannot create the spectrogram texture"),
+        };
+        self.levels = vec![0f32; (width * height) as usize];
+        self.column = 0;
+        self.place_labels();
+        self.redraw();
+    }
+}
+
+fn push_color(pixels: &mut Vec<u8>, color: &Color) {
+    pixels.push(color.r);
+    pixels.push(color.g);
+    pixels.push(color.b);
+    pixels.push(color.a);
+}
+
+impl<'b> GraphicElement<'b> for GraphicSpectrogram<'b> {
+    fn new_init(
+        size: &Vector2f,
+        position: &Vector2f,
+        unused: &Color,
+        font: Option<&'b Font>,
+    ) -> GraphicSpectrogram<'b> {
+        let mut spectrogram = GraphicSpectrogram {
+            texture: match Texture::new(1, 1) {
+                Some(t) => t,
+                None => panic!("Cannot create the spectrogram texture"),
+            },
+            column: 0,
+            levels: Vec::new(),
+            labels: Vec::new(),
+            position: position.clone(),
+            colormap: Colormap::Heat,
+            sample_rate: 48000f32,
+            to_update: true,
+            need_to_draw: true,
+            name: String::new(),
+            font: font,
+        };
+
+        spectrogram.create_texture(size.x as u32, size.y as u32);
+        spectrogram.init(position)
+    }
+
+    fn set_position(&mut self, position: &Vector2f) {
+        self.position = position.clone();
+        self.place_labels();
+        self.need_to_draw = true;
+    }
+
+    fn get_position(&self) -> Vector2f {
+        self.position
+    }
+
+    fn set_size(&mut self, size: &Vector2f) {
+        self.create_texture(size.x as u32, size.y as u32);
+    }
+
+    fn get_size(&self) -> Vector2f {
+        let size = self.texture.size();
+
+        Vector2f {
+            x: size.x as f32,
+            y: size.y as f32,
+        }
+    }
+
+    /// The oldest column is on the left, so the texture is drawn in two parts
+    /// around the column which was written last.
+    fn draw(&mut self, win: &mut RenderWindow) {
+        let size = self.texture.size();
+        let column = self.column as i32;
+        let width = size.x as i32;
+        let mut old = Sprite::with_texture_and_rect(
+            &self.texture,
+            &IntRect::new(column, 0, width - column, size.y as i32),
+        );
+        let mut new = Sprite::with_texture_and_rect(
+            &self.texture,
+            &IntRect::new(0, 0, column, size.y as i32),
+        );
+
+        old.set_position(self.position);
+        new.set_position(Vector2f {
+            x: self.position.x + (width - column) as f32,
+            y: self.position.y,
+        });
+        win.draw(&old);
+        if column > 0 {
+            win.draw(&new);
+        }
+        for label in self.labels.iter() {
+            win.draw(label);
+        }
+        self.need_to_draw = false;
+    }
+
+    fn is_inside(&self, pos: &Vector2f) -> bool {
+        let size = self.get_size();
+
+        pos.y >= self.position.y
+            && pos.y <= self.position.y + size.y
+            && pos.x >= self.position.x
+            && pos.x <= self.position.x + size.x
+    }
+
+    fn get_min_size(&self) -> Vector2f {
+        Vector2f { x: 20f32, y: 20f32 }
+    }
+
+    fn get_max_size(&self) -> Option<Vector2f> {
+        None
+    }
+
+    fn cursor_moved(&mut self, position: &Vector2f) {}
+
+    fn clicked(&mut self, position: &Vector2f) {
+        let colormap = self.colormap.next();
+
+        self.set_colormap(colormap);
+    }
+
+    fn mouse_leave(&mut self) {}
+
+    fn set_element_name(&mut self, name: &String) {
+        self.name = name.clone();
+    }
+
+    fn get_element_name<'a>(&'a self) -> &'a String {
+        &self.name
+    }
+}
@@ -26,67 +26,380 @@
 use graphic_element::GraphicElement;
 use sfml::graphics::Shape;
 use sfml::graphics::Transformable;
-use sfml::graphics::{Color, Font, RectangleShape, RenderTarget, RenderWindow};
+use sfml::graphics::{Color, Font, RectangleShape, RenderTarget, RenderWindow, Text};
 use sfml::system::Vector2f;
+
+// frequency range of the bands, in Hz
+const MIN_FREQUENCY: f32 = 20f32;
+const MAX_FREQUENCY: f32 = 20000f32;
+// level (in dB) at the bottom of the bars
+const MIN_DB: f32 = -60f32;
+// part of the distance to the new level done in a frame when a bar rises
+const ATTACK: f32 = 0.6f32;
+// what a bar or a peak cap falls in a frame (1 being the full height)
+const DECAY: f32 = 0.03f32;
+const PEAK_FALL: f32 = 0.01f32;
+// frames during which a peak cap stays at its level
+const PEAK_HOLD: usize = 20;
+const GRID_FREQUENCIES: [f32; 9] = [
+    50f32, 100f32, 200f32, 500f32, 1000f32, 2000f32, 5000f32, 10000f32, 20000f32,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpectrumMode {
+    /// One pixel per FFT bin, left channel on the left and right one on the
+    /// right.
+    Linear,
+    /// Log-spaced bands on a dB scale, with peak hold.
+    Bars,
+}
+
+impl SpectrumMode {
+    pub fn from_str(s: &str) -> Option<SpectrumMode> {
+        match s {
+            "linear" => Some(SpectrumMode::Linear),
+            "bars" => Some(SpectrumMode::Bars),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> SpectrumMode {
+        match *self {
+            SpectrumMode::Linear => SpectrumMode::Bars,
+            SpectrumMode::Bars => SpectrumMode::Linear,
+        }
+    }
+}
+
 pub struct GraphicSpectrum<'a> {
     spectrum: Vec<RectangleShape<'a>>,
     cleaner: RectangleShape<'a>,
     to_update: bool,
     pub need_to_draw: bool,
     name: String,
+    mode: SpectrumMode,
+    sample_rate: f32,
+    // level (from 0 to 1) of each band and of its peak cap
+    levels: Vec<f32>,
+    peaks: Vec<f32>,
+    // frames left before each peak cap falls
+    peak_holds: Vec<usize>,
+    bars: Vec<RectangleShape<'a>>,
+    caps: Vec<RectangleShape<'a>>,
+    grid: Vec<RectangleShape<'a>>,
+    labels: Vec<Text<'a>>,
+    font: Option<&'a Font>,
+    color: Color,
+    column_width: f32,
 }
 
 impl<'a> GraphicSpectrum<'a> {
-    fn init(mut self, position: &Vector2f, color: &Color) -> GraphicSpectrum<'a> {
-        let mut it = 0;
+    fn init(mut self, position: &Vector2f) -> GraphicSpectrum<'a> {
+        let width = self.cleaner.size().x as usize;
+
+        self.set_nb_columns(width);
+        for frequency in GRID_FREQUENCIES.iter() {
+            let mut line = RectangleShape::new();
 
-        while it < 512 {
-            self.spectrum.push(RectangleShape::with_size(Vector2f {
-                x: 1f32,
-                y: self.cleaner.size().y,
-            }));
-            self.spectrum[it].set_fill_color(color);
-            it += 1;
+            line.set_fill_color(&Color::rgb(40, 40, 40));
+            self.grid.push(line);
+            if let Some(font) = self.font {
+                let label = if *frequency >= 1000f32 {
+                    format!("{}k", frequency / 1000f32)
+                } else {
+                    format!("{}", frequency)
+                };
+                let mut text = Text::new(&label, font, 10);
+
+                text.set_fill_color(&Color::rgb(120, 120, 120));
+                self.labels.push(text);
+            }
         }
+        // one line every 20 dB
+        for _ in 1..(-MIN_DB / 20f32) as usize {
+            let mut line = RectangleShape::new();
+
+            line.set_fill_color(&Color::rgb(40, 40, 40));
+            self.grid.push(line);
+        }
+        self.set_nb_bands(32);
         self.set_position(position);
         self.cleaner.set_fill_color(&Color::rgb(0, 0, 0));
         self
     }
 
+    pub fn get_mode(&self) -> SpectrumMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: SpectrumMode) {
+        self.mode = mode;
+        self.need_to_draw = true;
+    }
+
+    pub fn set_nb_bands(&mut self, nb_bands: usize) {
+        let nb_bands = nb_bands.max(1).min(256);
+        let color = self.color;
+
+        self.levels = vec![0f32; nb_bands];
+        self.peaks = vec![0f32; nb_bands];
+        self.peak_holds = vec![0usize; nb_bands];
+        self.bars.clear();
+        self.caps.clear();
+        for _ in 0..nb_bands {
+            let mut bar = RectangleShape::new();
+            let mut cap = RectangleShape::new();
+
+            bar.set_fill_color(&color);
+            cap.set_fill_color(&Color::rgb(220, 220, 220));
+            self.bars.push(bar);
+            self.caps.push(cap);
+        }
+        let pos = self.get_position();
+
+        self.set_position(&pos);
+    }
+
+    /// Brightens the columns and the bars on a beat, `pulse` going from 0
+    /// to 1.
+    pub fn set_pulse(&mut self, pulse: f32) {
+        let ratio = pulse.max(0f32).min(1f32) * 0.6f32;
+        let base = self.color;
+        let mix = |c: u8| (c as f32 + (255f32 - c as f32) * ratio) as u8;
+        let color = Color::rgb(mix(base.r), mix(base.g), mix(base.b));
+
+        for column in self.spectrum.iter_mut() {
+            column.set_fill_color(&color);
+        }
+        for bar in self.bars.iter_mut() {
+            bar.set_fill_color(&color);
+        }
+    }
+
+    /// The spectrum goes from 0 to half of the output `rate`.
+    pub fn set_sample_rate(&mut self, rate: u64) {
+        self.sample_rate = rate as f32;
+    }
+
+    fn get_max_frequency(&self) -> f32 {
+        MAX_FREQUENCY.min(self.sample_rate / 2f32)
+    }
+
+    /// Position, from 0 to 1, of `frequency` on the log axis.
+    fn get_frequency_pos(&self, frequency: f32) -> f32 {
+        (frequency / MIN_FREQUENCY).ln() / (self.get_max_frequency() / MIN_FREQUENCY).ln()
+    }
+
+    fn get_bar_width(&self) -> f32 {
+        let nb_bands = self.bars.len() as f32;
+
+        ((self.cleaner.size().x - nb_bands) / nb_bands).max(1f32)
+    }
+
+    fn place_grid(&mut self) {
+        let position = self.cleaner.position();
+        let size = self.cleaner.size();
+        let nb_frequencies = GRID_FREQUENCIES.len();
+
+        for (it, frequency) in GRID_FREQUENCIES.iter().enumerate() {
+            let pos = self.get_frequency_pos(*frequency);
+            let x = position.x + pos * size.x;
+            // frequencies over the Nyquist one aren't in the spectrum
+            let height = if pos <= 1f32 { size.y } else { 0f32 };
+
+            self.grid[it].set_size(Vector2f { x: 1f32, y: height });
+            self.grid[it].set_position(Vector2f {
+                x: x,
+                y: position.y,
+            });
+            if it < self.labels.len() {
+                let width = self.labels[it].local_bounds().width;
+
+                self.labels[it].set_position(Vector2f {
+                    x: (x + 2f32).min(position.x + size.x - width - 2f32),
+                    y: position.y + 2f32,
+                });
+            }
+        }
+        for it in nb_frequencies..self.grid.len() {
+            let db = (it - nb_frequencies + 1) as f32 * 20f32;
+
+            self.grid[it].set_size(Vector2f { x: size.x, y: 1f32 });
+            self.grid[it].set_position(Vector2f {
+                x: position.x,
+                y: position.y + db / -MIN_DB * size.y,
+            });
+        }
+    }
+
+    fn place_bars(&mut self) {
+        let position = self.cleaner.position();
+        let height = self.cleaner.size().y;
+        let width = self.get_bar_width();
+
+        for it in 0..self.bars.len() {
+            let x = position.x + it as f32 * (width + 1f32);
+            let bar_height = self.levels[it] * height;
+            let cap_y = position.y + height - self.peaks[it] * height;
+
+            self.bars[it].set_size(Vector2f {
+                x: width,
+                y: bar_height,
+            });
+            self.bars[it].set_position(Vector2f {
+                x: x,
+                y: position.y + height - bar_height,
+            });
+            self.caps[it].set_size(Vector2f { x: width, y: 2f32 });
+            self.caps[it].set_position(Vector2f {
+                x: x,
+                y: (cap_y - 2f32).max(position.y),
+            });
+        }
+    }
+
+    /// Groups the bins of both channels into the bands and makes the bars
+    /// and their peak caps move towards the new levels.
+    fn update_bars(&mut self, data_left: &Vec<f32>, data_right: &Vec<f32>) {
+        let nb_bins = data_left.len().max(data_right.len());
+
+        if nb_bins == 0 {
+            return;
+        }
+        let bin_width = self.sample_rate / 2f32 / nb_bins as f32;
+        let ratio = self.get_max_frequency() / MIN_FREQUENCY;
+        let nb_bands = self.bars.len();
+
+        for band in 0..nb_bands {
+            let low = MIN_FREQUENCY * ratio.powf(band as f32 / nb_bands as f32);
+            let high = MIN_FREQUENCY * ratio.powf((band + 1) as f32 / nb_bands as f32);
+            let first = ((low / bin_width) as usize).min(nb_bins - 1);
+            // a band narrower than a bin still gets the bin it's in
+            let last = ((high / bin_width) as usize).max(first + 1).min(nb_bins);
+            let mut value = 0f32;
+
+            for bin in first..last {
+                let left = data_left.get(bin).cloned().unwrap_or(0f32);
+                let right = data_right.get(bin).cloned().unwrap_or(0f32);
+
+                value = value.max(left).max(right);
+            }
+            let target = if value > 0f32 {
+                ((20f32 * value.log10() - MIN_DB) / -MIN_DB).max(0f32).min(1f32)
+            } else {
+                0f32
+            };
+            let level = self.levels[band];
+
+            self.levels[band] = if target > level {
+                level + (target - level) * ATTACK
+            } else {
+                (level - DECAY).max(target)
+            };
+            if self.levels[band] >= self.peaks[band] {
+                self.peaks[band] = self.levels[band];
+                self.peak_holds[band] = PEAK_HOLD;
+            } else if self.peak_holds[band] > 0 {
+                self.peak_holds[band] -= 1;
+            } else {
+                self.peaks[band] = (self.peaks[band] - PEAK_FALL).max(self.levels[band]);
+            }
+        }
+        self.place_bars();
+    }
+
+    /// Creates the columns of the linear spectrum, sharing the width.
+    fn set_nb_columns(&mut self, nb_columns: usize) {
+        let nb_columns = nb_columns.max(1);
+
+        self.column_width = self.cleaner.size().x / nb_columns as f32;
+        self.spectrum.clear();
+        for _ in 0..nb_columns {
+            let mut column = RectangleShape::with_size(Vector2f {
+                x: self.column_width,
+                y: 0f32,
+            });
+
+            column.set_fill_color(&self.color);
+            self.spectrum.push(column);
+        }
+        self.place_columns();
+    }
+
+    fn place_columns(&mut self) {
+        let position = self.cleaner.position();
+        let height = self.cleaner.size().y;
+        let width = self.column_width;
+
+        for (it, column) in self.spectrum.iter_mut().enumerate() {
+            column.set_position(Vector2f {
+                x: position.x + it as f32 * width,
+                y: position.y + height,
+            });
+        }
+    }
+
+    fn set_column(&mut self, it: usize, value: f32) {
+        let mut tmp = value * -15f32;
+
+        if tmp < -1f32 {
+            tmp = -1f32;
+        }
+        let height = self.cleaner.size().y;
+
+        self.spectrum[it].set_size(Vector2f {
+            x: self.column_width,
+            y: height * tmp,
+        });
+    }
+
+    /// Groups the bins of `data` into `nb_columns` and returns the loudest
+    /// one of the column `it`.
+    fn get_column_value(data: &[f32], it: usize, nb_columns: usize) -> f32 {
+        let first = it * data.len() / nb_columns;
+        let last = ((it + 1) * data.len() / nb_columns).max(first + 1);
+
+        data[first..last].iter().fold(0f32, |a, b| a.max(*b))
+    }
+
+    /// In the linear mode, the left channel goes from the left side to the
+    /// middle and the right one from the right side to the middle. A
+    /// single spectrum takes the whole width.
     pub fn update_spectrum(&mut self, data_left: &Vec<f32>, data_right: &Vec<f32>) {
         if !self.to_update {
             self.to_update = true;
             return;
         }
-        let mut it = 0;
-        let height = self.cleaner.size().y;
-
         self.need_to_draw = true;
         self.to_update = false;
-        for t_data in data_left.iter() {
-            let mut tmp = *t_data * -15f32;
+        if self.mode == SpectrumMode::Bars {
+            self.update_bars(data_left, data_right);
+            return;
+        }
+        if data_left.is_empty() {
+            return;
+        }
+        let nb_sides = if data_right.is_empty() { 1 } else { 2 };
+        let side_width = self.cleaner.size().x as usize / nb_sides;
+        let nb_columns = data_left.len().min(side_width).max(1);
 
-            if tmp < -1f32 {
-                tmp = -1f32;
-            }
-            self.spectrum[it].set_size(Vector2f {
-                x: 1f32,
-                y: height * tmp,
-            });
-            it += 1;
+        if self.spectrum.len() != nb_columns * nb_sides {
+            self.set_nb_columns(nb_columns * nb_sides);
+        }
+        for it in 0..nb_columns {
+            let value = GraphicSpectrum::get_column_value(data_left, it, nb_columns);
+
+            self.set_column(it, value);
         }
-        it = 511;
-        for t_data in data_right.iter() {
-            let mut tmp = *t_data * -15f32;
+        if nb_sides == 2 {
+            let last = self.spectrum.len() - 1;
+            let nb_right = data_right.len().min(nb_columns);
 
-            if tmp < -1f32 {
-                tmp = -1f32;
+            for it in 0..nb_right {
+                let value = GraphicSpectrum::get_column_value(data_right, it, nb_right);
+
+                self.set_column(last - it, value);
             }
-            self.spectrum[it].set_size(Vector2f {
-                x: 1f32,
-                y: height * tmp,
-            });
-            it -= 1;
         }
     }
 }
@@ -96,34 +409,40 @@ impl<'b> GraphicElement<'b> for GraphicSpectrum<'b> {
         size: &Vector2f,
         position: &Vector2f,
         color: &Color,
-        additionnal: Option<&Font>,
+        additionnal: Option<&'b Font>,
     ) -> GraphicSpectrum<'b> {
         GraphicSpectrum {
             spectrum: Vec::new(),
             cleaner: RectangleShape::with_size(Vector2f {
-                x: 512f32,
+                x: size.x,
                 y: size.y,
             }),
             to_update: true,
             need_to_draw: true,
             name: String::new(),
-        }.init(position, color)
+            mode: SpectrumMode::Linear,
+            sample_rate: 48000f32,
+            levels: Vec::new(),
+            peaks: Vec::new(),
+            peak_holds: Vec::new(),
+            bars: Vec::new(),
+            caps: Vec::new(),
+            grid: Vec::new(),
+            labels: Vec::new(),
+            font: additionnal,
+            color: color.clone(),
+            column_width: 1f32,
+        }.init(position)
     }
 
     fn set_position(&mut self, position: &Vector2f) {
-        let mut it = 0usize;
-
-        for tmp in self.spectrum.iter_mut() {
-            tmp.set_position(Vector2f {
-                x: it as f32 + position.x,
-                y: self.cleaner.size().y + position.y,
-            });
-            it += 1;
-        }
         self.cleaner.set_position(Vector2f {
             x: position.x,
             y: position.y,
         });
+        self.place_columns();
+        self.place_grid();
+        self.place_bars();
         self.need_to_draw = true;
     }
 
@@ -132,11 +451,17 @@ impl<'b> GraphicElement<'b> for GraphicSpectrum<'b> {
     }
 
     fn set_size(&mut self, size: &Vector2f) {
+        let nb_columns = self.spectrum.len();
+
         self.cleaner.set_size(Vector2f {
-            x: 512f32,
+            x: size.x,
             y: size.y,
         });
-        self.need_to_draw = true;
+        // the columns share the new width
+        self.set_nb_columns(nb_columns);
+        let pos = self.get_position();
+
+        self.set_position(&pos);
     }
 
     fn get_size(&self) -> Vector2f {
@@ -145,8 +470,28 @@ impl<'b> GraphicElement<'b> for GraphicSpectrum<'b> {
 
     fn draw(&mut self, win: &mut RenderWindow) {
         win.draw(&self.cleaner);
-        for tmp in self.spectrum.iter_mut() {
-            win.draw(tmp);
+        match self.mode {
+            SpectrumMode::Linear => {
+                for tmp in self.spectrum.iter_mut() {
+                    win.draw(tmp);
+                }
+            }
+            SpectrumMode::Bars => {
+                for line in self.grid.iter() {
+                    win.draw(line);
+                }
+                for (line, label) in self.grid.iter().zip(self.labels.iter()) {
+                    if line.size().y > 0f32 {
+                        win.draw(label);
+                    }
+                }
+                for bar in self.bars.iter() {
+                    win.draw(bar);
+                }
+                for cap in self.caps.iter() {
+                    win.draw(cap);
+                }
+            }
         }
         self.need_to_draw = false;
     }
@@ -163,10 +508,7 @@ impl<'b> GraphicElement<'b> for GraphicSpectrum<'b> {
     }
 
     fn get_max_size(&self) -> Option<Vector2f> {
-        Some(Vector2f {
-            x: 512f32,
-            y: 100000000f32,
-        })
+        None
     }
 
     fn set_element_name(&mut self, name: &String) {
@@ -0,0 +1,208 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use graphic_element::GraphicElement;
+use sfml::graphics::{Color, Font, RectangleShape, RenderTarget, RenderWindow, Text};
+use sfml::graphics::{CircleShape, Shape, Transformable};
+use sfml::system::Vector2f;
+
+/// A line of text made of several entries, each one identified by a key so
+/// it can be updated or removed independently.
+pub struct GraphicStatus<'b> {
+    entries: Vec<(String, String)>,
+    text: Text<'b>,
+    // dot drawn before the text
+    indicator: Option<CircleShape<'b>>,
+    cleaner: RectangleShape<'b>,
+    pub need_to_draw: bool,
+    name: String,
+}
+
+impl<'b> GraphicStatus<'b> {
+    fn init(mut self, position: &Vector2f) -> GraphicStatus<'b> {
+        self.cleaner.set_fill_color(&Color::rgb(0, 0, 0));
+        self.cleaner.set_outline_color(&Color::rgb(255, 255, 255));
+        self.cleaner.set_outline_thickness(1f32);
+        self.set_position(position);
+        self
+    }
+
+    /// Sets the entry `key` to `value`, `None` removes it.
+    pub fn set_entry(&mut self, key: &str, value: Option<String>) {
+        let pos = self.entries.iter().position(|e| e.0 == key);
+
+        match (pos, value) {
+            (Some(p), Some(v)) => {
+                if self.entries[p].1 == v {
+                    return;
+                }
+                self.entries[p].1 = v;
+            }
+            (Some(p), None) => {
+                self.entries.remove(p);
+            }
+            (None, Some(v)) => self.entries.push((key.to_owned(), v)),
+            (None, None) => return,
+        }
+        let content: Vec<&str> = self.entries.iter().map(|e| e.1.as_str()).collect();
+
+        self.text.set_string(&content.join("   "));
+        self.need_to_draw = true;
+    }
+
+    /// Shows a dot of the given color before the entries, `None` hides it.
+    pub fn set_indicator(&mut self, color: Option<Color>) {
+        self.indicator = match color {
+            Some(c) => {
+                let mut dot = CircleShape::new(5f32, 16);
+
+                dot.set_fill_color(&c);
+                Some(dot)
+            }
+            None => None,
+        };
+        let pos = self.get_position();
+
+        self.set_position(&pos);
+    }
+}
+
+impl<'b> GraphicElement<'b> for GraphicStatus<'b> {
+    fn new_init(
+        size: &Vector2f,
+        position: &Vector2f,
+        unused: &Color,
+        font: Option<&'b Font>,
+    ) -> GraphicStatus<'b> {
+        GraphicStatus {
+            entries: Vec::new(),
+            text: Text::new(
+                "",
+                match font {
+                    Some(f) => f,
+                    None => panic!("GraphicStatus needs Font"),
+                },
+                16,
+            ),
+            indicator: None,
+            cleaner: RectangleShape::with_size(Vector2f {
+                x: size.x - 2f32,
+                y: size.y - 2f32,
+            }),
+            need_to_draw: true,
+            name: String::new(),
+        }.init(position)
+    }
+
+    fn set_position(&mut self, position: &Vector2f) {
+        self.cleaner.set_position(Vector2f {
+            x: position.x + 1f32,
+            y: position.y + 1f32,
+        });
+        let y = position.y + self.cleaner.size().y / 2f32 + 1f32;
+        let offset = match self.indicator {
+            Some(ref mut dot) => {
+                dot.set_position(Vector2f {
+                    x: position.x + 6f32,
+                    y: y - 5f32,
+                });
+                16f32
+            }
+            None => 0f32,
+        };
+
+        self.text.set_position(Vector2f {
+            x: position.x + 5f32 + offset,
+            y: position.y + (self.cleaner.size().y - 16f32) / 2f32 - 1f32,
+        });
+        self.need_to_draw = true;
+    }
+
+    fn get_position(&self) -> Vector2f {
+        let tmp = self.cleaner.position();
+
+        Vector2f {
+            x: tmp.x - 1f32,
+            y: tmp.y - 1f32,
+        }
+    }
+
+    fn set_size(&mut self, size: &Vector2f) {
+        let pos = self.get_position();
+
+        self.cleaner.set_size(Vector2f {
+            x: size.x - 2f32,
+            y: size.y - 2f32,
+        });
+        self.set_position(&pos);
+    }
+
+    fn get_size(&self) -> Vector2f {
+        let tmp = self.cleaner.size();
+
+        Vector2f {
+            x: tmp.x + 2f32,
+            y: tmp.y + 2f32,
+        }
+    }
+
+    fn draw(&mut self, win: &mut RenderWindow) {
+        win.draw(&self.cleaner);
+        if let Some(ref dot) = self.indicator {
+            win.draw(dot);
+        }
+        win.draw(&self.text);
+        self.need_to_draw = false;
+    }
+
+    fn is_inside(&self, pos: &Vector2f) -> bool {
+        pos.y >= self.cleaner.position().y
+            && pos.y <= self.cleaner.position().y + self.cleaner.size().y
+            && pos.x >= self.cleaner.position().x
+            && pos.x <= self.cleaner.position().x + self.cleaner.size().x
+    }
+
+    fn get_min_size(&self) -> Vector2f {
+        Vector2f { x: 50f32, y: 20f32 }
+    }
+
+    fn get_max_size(&self) -> Option<Vector2f> {
+        None
+    }
+
+    fn cursor_moved(&mut self, position: &Vector2f) {}
+
+    fn clicked(&mut self, position: &Vector2f) {}
+
+    fn mouse_leave(&mut self) {}
+
+    fn set_element_name(&mut self, name: &String) {
+        self.name = name.clone();
+    }
+
+    fn get_element_name<'a>(&'a self) -> &'a String {
+        &self.name
+    }
+}
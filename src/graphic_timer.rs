@@ -29,8 +29,20 @@ use sfml::graphics::Transformable;
 use sfml::graphics::{Color, Font, RectangleShape, RenderTarget, RenderWindow, Text};
 use sfml::system::Vector2f;
 
+/// Formats a time in ms as "mm:ss.d".
+pub fn format_time(time: usize) -> String {
+    format!(
+        "{:02}:{:02}.{}",
+        time / 1000 / 60,
+        time / 1000 % 60,
+        time % 1000 / 100
+    )
+}
+
 pub struct GraphicTimer<'a> {
     timer: Text<'a>,
+    // time left before the sleep timer stops the player
+    sleep: Text<'a>,
     cleaner: RectangleShape<'a>,
     need_to_draw: bool,
     name: String,
@@ -42,9 +54,41 @@ impl<'b> GraphicTimer<'b> {
         self.cleaner.set_fill_color(&Color::rgb(0, 0, 0));
         self.cleaner.set_outline_color(&Color::rgb(255, 255, 255));
         self.cleaner.set_outline_thickness(1f32);
+        self.sleep.set_fill_color(&Color::rgb(150, 150, 255));
         self
     }
 
+    /// Shows `text` on the right of the timer, `None` hides it.
+    pub fn set_sleep_text(&mut self, text: Option<String>) {
+        let text = text.unwrap_or(String::new());
+
+        if text != self.sleep.string() {
+            self.sleep.set_string(&text);
+            self.place_texts();
+            self.need_to_draw = true;
+        }
+    }
+
+    fn place_texts(&mut self) {
+        let pos = self.cleaner.position();
+        let size = self.cleaner.size();
+        let sleep_width = if self.sleep.string().is_empty() {
+            0f32
+        } else {
+            self.sleep.local_bounds().width + 6f32
+        };
+        let width = self.timer.local_bounds().width;
+
+        self.sleep.set_position(Vector2f {
+            x: pos.x + size.x - sleep_width,
+            y: pos.y + (size.y - 12f32) / 2f32 - 2f32,
+        });
+        self.timer.set_position(Vector2f {
+            x: (size.x - sleep_width - 1f32 - width) / 2f32 + pos.x,
+            y: (size.y - 20f32) / 2f32 + pos.y - 2f32,
+        });
+    }
+
     pub fn update_display(&mut self, position: usize, length: usize) {
         let st = format!(
             "{:02}:{:02} / {:02}:{:02}",
@@ -57,12 +101,7 @@ impl<'b> GraphicTimer<'b> {
         if st != self.timer.string() {
             self.need_to_draw = true;
             self.timer.set_string(&st);
-            let size = self.timer.local_bounds().width;
-            let y = self.timer.position().y;
-            self.timer.set_position(Vector2f {
-                x: (self.cleaner.size().x - 1f32 - size as f32) / 2f32 + self.cleaner.position().x,
-                y: y,
-            });
+            self.place_texts();
         }
     }
 }
@@ -76,6 +115,7 @@ impl<'b> GraphicElement<'b> for GraphicTimer<'b> {
     ) -> GraphicTimer<'b> {
         GraphicTimer {
             timer: Text::new("", &font.unwrap(), 20),
+            sleep: Text::new("", &font.unwrap(), 12),
             cleaner: RectangleShape::with_size(Vector2f {
                 x: size.x as f32 - 2f32,
                 y: size.y as f32 - 2f32,
@@ -86,16 +126,11 @@ impl<'b> GraphicElement<'b> for GraphicTimer<'b> {
     }
 
     fn set_position(&mut self, position: &Vector2f) {
-        let size = self.timer.local_bounds().width;
-
         self.cleaner.set_position(Vector2f {
             x: position.x + 1f32,
             y: position.y + 1f32,
         });
-        self.timer.set_position(Vector2f {
-            x: (self.cleaner.size().x - 1f32 - size as f32) / 2f32 + self.cleaner.position().x,
-            y: (self.cleaner.size().y - 20f32) / 2f32 + self.cleaner.position().y - 2f32,
-        });
+        self.place_texts();
         self.need_to_draw = true;
     }
 
@@ -131,6 +166,7 @@ impl<'b> GraphicElement<'b> for GraphicTimer<'b> {
         //if self.need_to_draw {
         win.draw(&self.cleaner);
         win.draw(&self.timer);
+        win.draw(&self.sleep);
         self.need_to_draw = false;
         //}
     }
@@ -0,0 +1,185 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use graphic_element::GraphicElement;
+use sfml::graphics::{Color, Font, RectangleShape, RenderTarget, RenderWindow, Text};
+use sfml::graphics::{Shape, Transformable};
+use sfml::system::Vector2f;
+
+/// A small box with some text, drawn over the other elements.
+pub struct GraphicTooltip<'b> {
+    text: Text<'b>,
+    cleaner: RectangleShape<'b>,
+    visible: bool,
+    pub need_to_draw: bool,
+    name: String,
+}
+
+impl<'b> GraphicTooltip<'b> {
+    fn init(mut self, position: &Vector2f) -> GraphicTooltip<'b> {
+        self.cleaner.set_fill_color(&Color::rgb(40, 40, 40));
+        self.cleaner.set_outline_color(&Color::rgb(255, 255, 255));
+        self.cleaner.set_outline_thickness(1f32);
+        self.set_position(position);
+        self
+    }
+
+    /// Shows `text` above `anchor`, kept inside a window `width` pixels wide.
+    pub fn show(&mut self, text: &str, anchor: &Vector2f, width: f32) {
+        if text != self.text.string() {
+            self.text.set_string(text);
+            let bounds = self.text.local_bounds();
+
+            self.cleaner.set_size(Vector2f {
+                x: bounds.width + 10f32,
+                y: 20f32,
+            });
+        }
+        let size = self.get_size();
+        let x = (anchor.x - size.x / 2f32).max(0f32).min(width - size.x);
+
+        self.set_position(&Vector2f {
+            x: x,
+            y: anchor.y - size.y - 4f32,
+        });
+        self.visible = true;
+    }
+
+    pub fn hide(&mut self) {
+        if self.visible {
+            self.visible = false;
+            self.need_to_draw = true;
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+}
+
+impl<'b> GraphicElement<'b> for GraphicTooltip<'b> {
+    fn new_init(
+        size: &Vector2f,
+        position: &Vector2f,
+        unused: &Color,
+        font: Option<&'b Font>,
+    ) -> GraphicTooltip<'b> {
+        GraphicTooltip {
+            text: Text::new(
+                "",
+                match font {
+                    Some(f) => f,
+                    None => panic!("GraphicTooltip needs Font"),
+                },
+                14,
+            ),
+            cleaner: RectangleShape::with_size(Vector2f {
+                x: size.x - 2f32,
+                y: size.y - 2f32,
+            }),
+            visible: false,
+            need_to_draw: true,
+            name: String::new(),
+        }.init(position)
+    }
+
+    fn set_position(&mut self, position: &Vector2f) {
+        self.cleaner.set_position(Vector2f {
+            x: position.x + 1f32,
+            y: position.y + 1f32,
+        });
+        self.text.set_position(Vector2f {
+            x: position.x + 6f32,
+            y: position.y + 2f32,
+        });
+        self.need_to_draw = true;
+    }
+
+    fn get_position(&self) -> Vector2f {
+        let tmp = self.cleaner.position();
+
+        Vector2f {
+            x: tmp.x - 1f32,
+            y: tmp.y - 1f32,
+        }
+    }
+
+    fn set_size(&mut self, size: &Vector2f) {
+        let pos = self.get_position();
+
+        self.cleaner.set_size(Vector2f {
+            x: size.x - 2f32,
+            y: size.y - 2f32,
+        });
+        self.set_position(&pos);
+    }
+
+    fn get_size(&self) -> Vector2f {
+        let tmp = self.cleaner.size();
+
+        Vector2f {
+            x: tmp.x + 2f32,
+            y: tmp.y + 2f32,
+        }
+    }
+
+    fn draw(&mut self, win: &mut RenderWindow) {
+        if self.visible {
+            win.draw(&self.cleaner);
+            win.draw(&self.text);
+        }
+        self.need_to_draw = false;
+    }
+
+    fn is_inside(&self, pos: &Vector2f) -> bool {
+        self.visible
+            && pos.y >= self.cleaner.position().y
+            && pos.y <= self.cleaner.position().y + self.cleaner.size().y
+            && pos.x >= self.cleaner.position().x
+            && pos.x <= self.cleaner.position().x + self.cleaner.size().x
+    }
+
+    fn get_min_size(&self) -> Vector2f {
+        Vector2f { x: 20f32, y: 20f32 }
+    }
+
+    fn get_max_size(&self) -> Option<Vector2f> {
+        None
+    }
+
+    fn cursor_moved(&mut self, position: &Vector2f) {}
+
+    fn clicked(&mut self, position: &Vector2f) {}
+
+    fn mouse_leave(&mut self) {}
+
+    fn set_element_name(&mut self, name: &String) {
+        self.name = name.clone();
+    }
+
+    fn get_element_name<'a>(&'a self) -> &'a String {
+        &self.name
+    }
+}
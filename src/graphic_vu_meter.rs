@@ -0,0 +1,319 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use graphic_element::GraphicElement;
+use sfml::graphics::Shape;
+use sfml::graphics::Transformable;
+use sfml::graphics::{Color, Font, RectangleShape, RenderTarget, RenderWindow};
+use sfml::system::Vector2f;
+
+// level (in dBFS) at the left of the meters
+const MIN_DB: f32 = -60f32;
+// what the RMS bar falls in a frame, in dB
+const DECAY: f32 = 1.5f32;
+// frames during which the peak stays at its level, then what it falls
+const PEAK_HOLD: usize = 45;
+const PEAK_FALL: f32 = 0.5f32;
+const LED_WIDTH: f32 = 8f32;
+
+struct Meter<'b> {
+    background: RectangleShape<'b>,
+    rms_bar: RectangleShape<'b>,
+    peak_mark: RectangleShape<'b>,
+    // levels in dBFS
+    rms: f32,
+    peak: f32,
+    peak_hold: usize,
+}
+
+impl<'b> Meter<'b> {
+    fn new() -> Meter<'b> {
+        let mut meter = Meter {
+            background: RectangleShape::new(),
+            rms_bar: RectangleShape::new(),
+            peak_mark: RectangleShape::new(),
+            rms: MIN_DB,
+            peak: MIN_DB,
+            peak_hold: 0,
+        };
+
+        meter.background.set_fill_color(&Color::rgb(30, 30, 30));
+        meter.peak_mark.set_fill_color(&Color::rgb(255, 255, 255));
+        meter
+    }
+
+    fn place(&mut self, position: &Vector2f, size: &Vector2f) {
+        self.background.set_position(*position);
+        self.background.set_size(*size);
+        self.rms_bar.set_position(*position);
+        self.peak_mark.set_position(*position);
+        self.peak_mark.set_size(Vector2f { x: 2f32, y: size.y });
+        self.update_shapes();
+    }
+
+    fn get_ratio(db: f32) -> f32 {
+        ((db - MIN_DB) / -MIN_DB).max(0f32).min(1f32)
+    }
+
+    fn update_shapes(&mut self) {
+        let position = self.background.position();
+        let size = self.background.size();
+        let rms = Meter::get_ratio(self.rms);
+
+        self.rms_bar.set_size(Vector2f {
+            x: rms * size.x,
+            y: size.y,
+        });
+        self.rms_bar.set_fill_color(&if self.rms > -6f32 {
+            Color::rgb(255, 60, 60)
+        } else if self.rms > -18f32 {
+            Color::rgb(230, 200, 50)
+        } else {
+            Color::rgb(100, 220, 80)
+        });
+        self.peak_mark.set_position(Vector2f {
+            x: position.x + (Meter::get_ratio(self.peak) * size.x - 2f32).max(0f32),
+            y: position.y,
+        });
+    }
+
+    /// Returns whether a sample reached 0 dBFS.
+    fn update(&mut self, data: &[f32]) -> bool {
+        let mut sum = 0f32;
+        let mut peak = 0f32;
+
+        for sample in data.iter() {
+            sum += sample * sample;
+            peak = peak.max(sample.abs());
+        }
+        let rms = if data.is_empty() {
+            MIN_DB
+        } else {
+            to_db((sum / data.len() as f32).sqrt())
+        };
+        let peak_db = to_db(peak);
+
+        self.rms = rms.max(self.rms - DECAY);
+        if peak_db >= self.peak {
+            self.peak = peak_db;
+            self.peak_hold = PEAK_HOLD;
+        } else if self.peak_hold > 0 {
+            self.peak_hold -= 1;
+        } else {
+            self.peak = (self.peak - PEAK_FALL).max(peak_db);
+        }
+        self.update_shapes();
+        peak >= 1f32
+    }
+}
+
+fn to_db(value: f32) -> f32 {
+    if value > 0f32 {
+        (20f32 * value.log10()).max(MIN_DB)
+    } else {
+        MIN_DB
+    }
+}
+
+/// Stereo RMS meters with a peak hold mark, and a clip LED which stays on
+/// once a sample reached 0 dBFS until the meters are clicked.
+pub struct GraphicVuMeter<'b> {
+    meters: Vec<Meter<'b>>,
+    led: RectangleShape<'b>,
+    clipped: bool,
+    cleaner: RectangleShape<'b>,
+    pub need_to_draw: bool,
+    name: String,
+}
+
+impl<'b> GraphicVuMeter<'b> {
+    fn init(mut self, position: &Vector2f) -> GraphicVuMeter<'b> {
+        self.cleaner.set_fill_color(&Color::rgb(0, 0, 0));
+        self.cleaner.set_outline_color(&Color::rgb(255, 255, 255));
+        self.cleaner.set_outline_thickness(1f32);
+        self.set_clipped(false);
+        self.set_position(position);
+        self
+    }
+
+    fn set_clipped(&mut self, clipped: bool) {
+        self.clipped = clipped;
+        self.led.set_fill_color(&if clipped {
+            Color::rgb(255, 0, 0)
+        } else {
+            Color::rgb(70, 0, 0)
+        });
+        self.need_to_draw = true;
+    }
+
+    /// Updates the meters with the last samples of both channels.
+    pub fn update_levels(&mut self, data_left: &[f32], data_right: &[f32]) {
+        let left = self.meters[0].update(data_left);
+        let right = self.meters[1].update(data_right);
+
+        if (left || right) && !self.clipped {
+            self.set_clipped(true);
+        }
+        self.need_to_draw = true;
+    }
+
+    /// Returns the levels as text, for the tooltip.
+    pub fn get_text(&self) -> String {
+        let mut text = format!(
+            "RMS {:.1} / {:.1} dB   peak {:.1} / {:.1} dB",
+            self.meters[0].rms, self.meters[1].rms, self.meters[0].peak, self.meters[1].peak
+        );
+
+        if self.clipped {
+            text.push_str("   clipped");
+        }
+        text
+    }
+}
+
+impl<'b> GraphicElement<'b> for GraphicVuMeter<'b> {
+    fn new_init(
+        size: &Vector2f,
+        position: &Vector2f,
+        unused: &Color,
+        font: Option<&'b Font>,
+    ) -> GraphicVuMeter<'b> {
+        GraphicVuMeter {
+            meters: vec![Meter::new(), Meter::new()],
+            led: RectangleShape::new(),
+            clipped: false,
+            cleaner: RectangleShape::with_size(Vector2f {
+                x: size.x - 2f32,
+                y: size.y - 2f32,
+            }),
+            need_to_draw: true,
+            name: String::new(),
+        }.init(position)
+    }
+
+    fn set_position(&mut self, position: &Vector2f) {
+        self.cleaner.set_position(Vector2f {
+            x: position.x + 1f32,
+            y: position.y + 1f32,
+        });
+        let inner = self.cleaner.position();
+        let size = self.cleaner.size();
+        let height = (size.y - 6f32) / 2f32;
+        let width = size.x - LED_WIDTH - 6f32;
+
+        for (it, meter) in self.meters.iter_mut().enumerate() {
+            meter.place(
+                &Vector2f {
+                    x: inner.x + 2f32,
+                    y: inner.y + 2f32 + it as f32 * (height + 2f32),
+                },
+                &Vector2f {
+                    x: width,
+                    y: height,
+                },
+            );
+        }
+        self.led.set_size(Vector2f {
+            x: LED_WIDTH,
+            y: size.y - 4f32,
+        });
+        self.led.set_position(Vector2f {
+            x: inner.x + size.x - LED_WIDTH - 2f32,
+            y: inner.y + 2f32,
+        });
+        self.need_to_draw = true;
+    }
+
+    fn get_position(&self) -> Vector2f {
+        let tmp = self.cleaner.position();
+
+        Vector2f {
+            x: tmp.x - 1f32,
+            y: tmp.y - 1f32,
+        }
+    }
+
+    fn set_size(&mut self, size: &Vector2f) {
+        let pos = self.get_position();
+
+        self.cleaner.set_size(Vector2f {
+            x: size.x - 2f32,
+            y: size.y - 2f32,
+        });
+        self.set_position(&pos);
+    }
+
+    fn get_size(&self) -> Vector2f {
+        let tmp = self.cleaner.size();
+
+        Vector2f {
+            x: tmp.x + 2f32,
+            y: tmp.y + 2f32,
+        }
+    }
+
+    fn draw(&mut self, win: &mut RenderWindow) {
+        win.draw(&self.cleaner);
+        for meter in self.meters.iter() {
+            win.draw(&meter.background);
+            win.draw(&meter.rms_bar);
+            win.draw(&meter.peak_mark);
+        }
+        win.draw(&self.led);
+        self.need_to_draw = false;
+    }
+
+    fn is_inside(&self, pos: &Vector2f) -> bool {
+        pos.y >= self.cleaner.position().y
+            && pos.y <= self.cleaner.position().y + self.cleaner.size().y
+            && pos.x >= self.cleaner.position().x
+            && pos.x <= self.cleaner.position().x + self.cleaner.size().x
+    }
+
+    fn get_min_size(&self) -> Vector2f {
+        Vector2f { x: 30f32, y: 12f32 }
+    }
+
+    fn get_max_size(&self) -> Option<Vector2f> {
+        None
+    }
+
+    fn cursor_moved(&mut self, position: &Vector2f) {}
+
+    /// Turns the clip LED off.
+    fn clicked(&mut self, position: &Vector2f) {
+        self.set_clipped(false);
+    }
+
+    fn mouse_leave(&mut self) {}
+
+    fn set_element_name(&mut self, name: &String) {
+        self.name = name.clone();
+    }
+
+    fn get_element_name<'a>(&'a self) -> &'a String {
+        &self.name
+    }
+}
@@ -0,0 +1,289 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use bookmarks::Bookmark;
+use config;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+/// Where the music was left. The file's size and modification time are kept
+/// so the position isn't used if the file is replaced.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Resume {
+    pub position: usize,
+    pub size: u64,
+    pub mtime: u64,
+}
+
+impl Resume {
+    pub fn new(music: &str, position: usize) -> Option<Resume> {
+        match get_file_id(music) {
+            Some((size, mtime)) => Some(Resume {
+                position: position,
+                size: size,
+                mtime: mtime,
+            }),
+            None => None,
+        }
+    }
+
+    /// Returns the position if the file didn't change since it was stored.
+    pub fn get_position(&self, music: &str) -> Option<usize> {
+        match get_file_id(music) {
+            Some((size, mtime)) if size == self.size && mtime == self.mtime => {
+                Some(self.position)
+            }
+            _ => None,
+        }
+    }
+
+    fn from_str(value: &str) -> Result<Resume, String> {
+        let values: Vec<u64> = value
+            .split_whitespace()
+            .filter_map(|v| v.parse().ok())
+            .collect();
+
+        if values.len() != 3 {
+            return Err(format!("\"{}\" isn't a valid resume position", value));
+        }
+        Ok(Resume {
+            position: values[0] as usize,
+            size: values[1],
+            mtime: values[2],
+        })
+    }
+}
+
+/// Returns the size and the modification time (in seconds) of the file.
+pub fn get_file_id(music: &str) -> Option<(u64, u64)> {
+    match fs::metadata(music) {
+        Ok(m) => {
+            let mtime = match m.modified() {
+                Ok(t) => match t.duration_since(UNIX_EPOCH) {
+                    Ok(d) => d.as_secs(),
+                    Err(_) => 0u64,
+                },
+                Err(_) => 0u64,
+            };
+
+            Some((m.len(), mtime))
+        }
+        Err(_) => None,
+    }
+}
+
+/// What the player remembers about a music.
+#[derive(Clone, Debug, Default)]
+pub struct TrackInfo {
+    /// ReplayGain (in dB) computed by the loudness analysis.
+    pub gain: Option<f32>,
+    pub peak: Option<f32>,
+    /// Equalizer preset used instead of the global one.
+    pub equalizer: Option<String>,
+    pub speed: Option<f32>,
+    /// Pitch shift in semitones.
+    pub pitch: Option<i32>,
+    /// Sorted by time.
+    pub bookmarks: Vec<Bookmark>,
+    /// Last position in audiobook mode.
+    pub resume: Option<Resume>,
+    /// Tempo found by the beat detection.
+    pub bpm: Option<f32>,
+}
+
+impl TrackInfo {
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "gain" => self.gain = Some(try!(parse_f32(value))),
+            "peak" => self.peak = Some(try!(parse_f32(value))),
+            "equalizer" => self.equalizer = Some(value.to_owned()),
+            "bookmark" => {
+                let bookmark = try!(Bookmark::from_str(value));
+                self.add_bookmark(bookmark);
+            }
+            "resume" => self.resume = Some(try!(Resume::from_str(value))),
+            "bpm" => self.bpm = Some(try!(parse_f32(value))),
+            "speed" => self.speed = Some(try!(parse_f32(value))),
+            "pitch" => {
+                self.pitch = match value.parse() {
+                    Ok(v) => Some(v),
+                    Err(_) => return Err(format!("\"{}\" isn't a number", value)),
+                }
+            }
+            _ => return Err(format!("unknown key \"{}\"", key)),
+        }
+        Ok(())
+    }
+
+    fn write(&self, out: &mut String) {
+        if let Some(g) = self.gain {
+            out.push_str(&format!("gain = {}\n", g));
+        }
+        if let Some(p) = self.peak {
+            out.push_str(&format!("peak = {}\n", p));
+        }
+        if let Some(ref e) = self.equalizer {
+            out.push_str(&format!("equalizer = {}\n", e));
+        }
+        if let Some(s) = self.speed {
+            out.push_str(&format!("speed = {}\n", s));
+        }
+        if let Some(p) = self.pitch {
+            out.push_str(&format!("pitch = {}\n", p));
+        }
+        if let Some(b) = self.bpm {
+            out.push_str(&format!("bpm = {:.1}\n", b));
+        }
+        if let Some(r) = self.resume {
+            out.push_str(&format!("resume = {} {} {}\n", r.position, r.size, r.mtime));
+        }
+        for bookmark in self.bookmarks.iter() {
+            out.push_str(&format!("bookmark = {}\n", bookmark.to_string()));
+        }
+    }
+
+    /// Inserts the bookmark at its place and returns its index.
+    pub fn add_bookmark(&mut self, bookmark: Bookmark) -> usize {
+        let pos = match self.bookmarks.iter().position(|b| b.time > bookmark.time) {
+            Some(p) => p,
+            None => self.bookmarks.len(),
+        };
+
+        self.bookmarks.insert(pos, bookmark);
+        pos
+    }
+}
+
+fn parse_f32(value: &str) -> Result<f32, String> {
+    match value.parse() {
+        Ok(v) => Ok(v),
+        Err(_) => Err(format!("\"{}\" isn't a number", value)),
+    }
+}
+
+/// Stored in `~/.music_player/library`. Each music has its own section
+/// starting with its path between brackets, followed by `key = value` lines.
+pub struct Library {
+    tracks: BTreeMap<String, TrackInfo>,
+    path: PathBuf,
+}
+
+impl Library {
+    pub fn load() -> Library {
+        let mut path = config::get_data_dir();
+
+        path.push("library");
+        let mut library = Library {
+            tracks: BTreeMap::new(),
+            path: path,
+        };
+        let mut content = String::new();
+
+        match File::open(&library.path) {
+            Ok(mut f) => {
+                if let Err(e) = f.read_to_string(&mut content) {
+                    println!("Cannot read {} : {}", library.path.display(), e);
+                }
+            }
+            Err(_) => return library,
+        }
+        let mut current: Option<String> = None;
+
+        for (pos, line) in content.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                let name = line[1..line.len() - 1].to_owned();
+
+                library.tracks.entry(name.clone()).or_insert(TrackInfo::default());
+                current = Some(name);
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            let res = match current {
+                Some(ref c) => match library.tracks.get_mut(c) {
+                    Some(t) => t.set(key, value),
+                    None => Ok(()),
+                },
+                None => Err("no music section".to_owned()),
+            };
+
+            if let Err(e) = res {
+                println!("{}:{} : {}", library.path.display(), pos + 1, e);
+            }
+        }
+        library
+    }
+
+    pub fn save(&self) {
+        let mut out = String::new();
+
+        for (name, info) in self.tracks.iter() {
+            let mut content = String::new();
+
+            info.write(&mut content);
+            if !content.is_empty() {
+                out.push_str(&format!("[{}]\n{}\n", name, content));
+            }
+        }
+        match File::create(&self.path) {
+            Ok(mut f) => {
+                if let Err(e) = f.write_all(out.as_bytes()) {
+                    println!("Cannot write {} : {}", self.path.display(), e);
+                }
+            }
+            Err(e) => println!("Cannot create {} : {}", self.path.display(), e),
+        }
+    }
+
+    pub fn get(&self, music: &str) -> Option<&TrackInfo> {
+        self.tracks.get(&get_key(music))
+    }
+
+    pub fn get_mut(&mut self, music: &str) -> &mut TrackInfo {
+        self.tracks.entry(get_key(music)).or_insert(TrackInfo::default())
+    }
+
+    /// Returns the path of every music the library knows.
+    pub fn get_musics(&self) -> Vec<String> {
+        self.tracks.keys().cloned().collect()
+    }
+}
+
+/// Musics are stored with their absolute path so they are found again
+/// whatever the folder the player is launched from.
+fn get_key(music: &str) -> String {
+    match fs::canonicalize(music) {
+        Ok(p) => p.to_string_lossy().into_owned(),
+        Err(_) => music.to_owned(),
+    }
+}
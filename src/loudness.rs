@@ -0,0 +1,176 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use decoder::Decoder;
+use std::f64::consts::PI;
+
+// ReplayGain 2.0 reference level, in LUFS
+const REFERENCE_LOUDNESS: f64 = -18f64;
+const ABSOLUTE_GATE: f64 = -70f64;
+const RELATIVE_GATE: f64 = -10f64;
+
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.z[0];
+
+        self.z[0] = self.b[1] * input - self.a[1] * output + self.z[1];
+        self.z[1] = self.b[2] * input - self.a[2] * output;
+        output
+    }
+}
+
+/// Returns the two filters of the EBU R128 K-weighting (a high shelf and a
+/// high pass) for the given sample rate.
+fn k_weighting(rate: f64) -> [Biquad; 2] {
+    let f0 = 1681.974450955533f64;
+    let gain = 3.999843853973347f64;
+    let q = 0.7071752369554196f64;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain / 20f64);
+    let vb = vh.powf(0.4996667741545416f64);
+    let a0 = 1f64 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2f64 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [1f64, 2f64 * (k * k - 1f64) / a0, (1f64 - k / q + k * k) / a0],
+        z: [0f64; 2],
+    };
+    let f0 = 38.13547087602444f64;
+    let q = 0.5003270373238773f64;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1f64 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1f64, -2f64, 1f64],
+        a: [1f64, 2f64 * (k * k - 1f64) / a0, (1f64 - k / q + k * k) / a0],
+        z: [0f64; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+/// Integrated loudness measurement as described by EBU R128 / ITU BS.1770.
+pub struct LoudnessMeter {
+    channels: usize,
+    filters: Vec<[Biquad; 2]>,
+    // energies of the 100ms parts, gating blocks are 4 of them
+    sub_blocks: Vec<f64>,
+    sub_block_size: usize,
+    energy: f64,
+    count: usize,
+    peak: f32,
+}
+
+impl LoudnessMeter {
+    pub fn new(rate: u32, channels: usize) -> LoudnessMeter {
+        LoudnessMeter {
+            channels: channels,
+            filters: (0..channels).map(|_| k_weighting(rate as f64)).collect(),
+            sub_blocks: Vec::new(),
+            sub_block_size: rate as usize / 10,
+            energy: 0f64,
+            count: 0usize,
+            peak: 0f32,
+        }
+    }
+
+    /// `samples` are interleaved.
+    pub fn add_samples(&mut self, samples: &[f32]) {
+        for frame in samples.chunks(self.channels) {
+            for (channel, sample) in frame.iter().enumerate() {
+                let filters = &mut self.filters[channel];
+                let value = filters[1].process(filters[0].process(*sample as f64));
+
+                self.energy += value * value;
+                if sample.abs() > self.peak {
+                    self.peak = sample.abs();
+                }
+            }
+            self.count += 1;
+            if self.count >= self.sub_block_size {
+                self.sub_blocks.push(self.energy / self.count as f64);
+                self.energy = 0f64;
+                self.count = 0;
+            }
+        }
+    }
+
+    pub fn get_peak(&self) -> f32 {
+        self.peak
+    }
+
+    /// Returns the integrated loudness in LUFS.
+    pub fn get_loudness(&self) -> Option<f64> {
+        if self.sub_blocks.len() < 4 {
+            return None;
+        }
+        // 400ms blocks overlapping by 75%
+        let blocks: Vec<f64> = self.sub_blocks
+            .windows(4)
+            .map(|w| w.iter().sum::<f64>() / 4f64)
+            .filter(|e| to_lufs(*e) > ABSOLUTE_GATE)
+            .collect();
+
+        if blocks.len() == 0 {
+            return None;
+        }
+        let gate = to_lufs(blocks.iter().sum::<f64>() / blocks.len() as f64) + RELATIVE_GATE;
+        let gated: Vec<&f64> = blocks.iter().filter(|e| to_lufs(**e) > gate).collect();
+
+        if gated.len() == 0 {
+            None
+        } else {
+            Some(to_lufs(
+                gated.iter().map(|e| **e).sum::<f64>() / gated.len() as f64,
+            ))
+        }
+    }
+}
+
+fn to_lufs(energy: f64) -> f64 {
+    -0.691f64 + 10f64 * energy.log10()
+}
+
+/// Decodes the whole file and returns its ReplayGain (in dB) and peak.
+pub fn analyze_file(path: &str) -> Result<(f32, f32), String> {
+    let mut decoder = try!(Decoder::new(path));
+    let mut meter = LoudnessMeter::new(decoder.rate, decoder.channels);
+
+    while let Some(samples) = decoder.read() {
+        meter.add_samples(&samples);
+    }
+    match meter.get_loudness() {
+        Some(l) => Ok(((REFERENCE_LOUDNESS - l) as f32, meter.get_peak())),
+        None => Err(format!("{} is too short or silent", path)),
+    }
+}
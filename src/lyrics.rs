@@ -0,0 +1,306 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use rfmod;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tags;
+
+pub struct LyricLine {
+    pub time: usize,
+    pub text: String,
+}
+
+pub struct Lyrics {
+    pub lines: Vec<LyricLine>,
+    pub synced: bool,
+}
+
+impl Lyrics {
+    /// Looks for a `.lrc` file next to the music first, then for SYLT and
+    /// USLT tags.
+    pub fn load(music: &str, sound: &rfmod::Sound) -> Option<Lyrics> {
+        match Lyrics::from_file(music) {
+            Some(l) => Some(l),
+            None => Lyrics::from_sound(sound),
+        }
+    }
+
+    pub fn from_file(music: &str) -> Option<Lyrics> {
+        let path = Path::new(music);
+        let candidates = vec![
+            path.with_extension("lrc"),
+            Path::new(&format!("{}.lrc", music)).to_path_buf(),
+        ];
+
+        for candidate in candidates.iter() {
+            let mut content = String::new();
+
+            match File::open(candidate) {
+                Ok(mut f) => {
+                    if f.read_to_string(&mut content).is_err() {
+                        continue;
+                    }
+                }
+                Err(_) => continue,
+            }
+            let lyrics = Lyrics::from_lrc(&content);
+
+            if lyrics.lines.len() > 0 {
+                return Some(lyrics);
+            }
+        }
+        None
+    }
+
+    pub fn from_sound(sound: &rfmod::Sound) -> Option<Lyrics> {
+        match tags::get_tag_data(sound, "SYLT") {
+            Some(data) => match Lyrics::from_sylt(&data) {
+                Some(l) => return Some(l),
+                None => {}
+            },
+            None => {}
+        }
+        match tags::get_tag_data(sound, "USLT") {
+            Some(data) => {
+                if data.len() < 4 {
+                    return None;
+                }
+                let encoding = data[0];
+                let (_, text) = tags::split_id3_text(encoding, &data[4..]);
+                let text = tags::decode_id3_text(encoding, text);
+                let lyrics = Lyrics::from_lrc(&text);
+
+                if lyrics.lines.len() > 0 {
+                    Some(lyrics)
+                } else {
+                    Some(Lyrics::from_text(&text))
+                }
+            }
+            None => None,
+        }
+    }
+
+    pub fn from_text(text: &str) -> Lyrics {
+        Lyrics {
+            lines: text
+                .lines()
+                .map(|l| LyricLine {
+                    time: 0usize,
+                    text: l.trim().to_owned(),
+                })
+                .collect(),
+            synced: false,
+        }
+    }
+
+    pub fn from_lrc(content: &str) -> Lyrics {
+        let mut lines = Vec::new();
+        let mut offset = 0isize;
+
+        for line in content.lines() {
+            let mut rest = line.trim();
+            let mut times = Vec::new();
+
+            while rest.starts_with('[') {
+                let end = match rest.find(']') {
+                    Some(e) => e,
+                    None => break,
+                };
+                let tag = &rest[1..end];
+
+                rest = &rest[end + 1..];
+                match parse_timestamp(tag) {
+                    Some(t) => times.push(t),
+                    None => {
+                        if tag.starts_with("offset:") {
+                            offset = tag[7..].trim().parse().unwrap_or(0isize);
+                        }
+                    }
+                }
+            }
+            for time in times.iter() {
+                lines.push((*time, rest.trim().to_owned()));
+            }
+        }
+        // A positive offset makes the lyrics appear sooner.
+        let mut lines: Vec<LyricLine> = lines
+            .into_iter()
+            .map(|(time, text)| LyricLine {
+                time: if time as isize - offset < 0 {
+                    0usize
+                } else {
+                    (time as isize - offset) as usize
+                },
+                text: text,
+            })
+            .collect();
+
+        lines.sort_by(|a, b| a.time.cmp(&b.time));
+        Lyrics {
+            lines: lines,
+            synced: true,
+        }
+    }
+
+    fn from_sylt(data: &[u8]) -> Option<Lyrics> {
+        // encoding, language (3 bytes), timestamp format, content type
+        if data.len() < 6 || data[4] != 2 {
+            return None;
+        }
+        let encoding = data[0];
+        let (_, mut rest) = tags::split_id3_text(encoding, &data[6..]);
+        let mut lines = Vec::new();
+
+        while rest.len() > 0 {
+            let (text, after) = tags::split_id3_text(encoding, rest);
+
+            if after.len() < 4 {
+                break;
+            }
+            lines.push(LyricLine {
+                time: ((after[0] as usize) << 24)
+                    | ((after[1] as usize) << 16)
+                    | ((after[2] as usize) << 8)
+                    | after[3] as usize,
+                text: text.trim_matches('\n').to_owned(),
+            });
+            rest = &after[4..];
+        }
+        if lines.len() == 0 {
+            None
+        } else {
+            lines.sort_by(|a, b| a.time.cmp(&b.time));
+            Some(Lyrics {
+                lines: lines,
+                synced: true,
+            })
+        }
+    }
+
+    /// Returns the index of the line being sung at `position` (in ms).
+    pub fn get_line(&self, position: usize) -> Option<usize> {
+        if !self.synced {
+            return None;
+        }
+        let mut current = None;
+
+        for (pos, line) in self.lines.iter().enumerate() {
+            if line.time > position {
+                break;
+            }
+            current = Some(pos);
+        }
+        current
+    }
+}
+
+/// Parses `mm:ss`, `mm:ss.xx` or `mm:ss.xxx` into milliseconds.
+fn parse_timestamp(tag: &str) -> Option<usize> {
+    let mut parts = tag.splitn(2, ':');
+    let minutes = match parts.next().and_then(|m| m.trim().parse::<usize>().ok()) {
+        Some(m) => m,
+        None => return None,
+    };
+    let seconds = match parts.next() {
+        Some(s) => s,
+        None => return None,
+    };
+    let mut parts = seconds.splitn(2, |c| c == '.' || c == ':');
+    let secs = match parts.next().and_then(|s| s.trim().parse::<usize>().ok()) {
+        Some(s) => s,
+        None => return None,
+    };
+    let fraction = match parts.next() {
+        Some(f) => {
+            let f = f.trim();
+
+            if !f.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            // only the milliseconds are kept from longer fractions
+            let f = if f.len() > 3 { &f[..3] } else { f };
+            let value = match f.parse::<usize>() {
+                Ok(v) => v,
+                Err(_) => return None,
+            };
+            match f.len() {
+                1 => value * 100,
+                2 => value * 10,
+                _ => value,
+            }
+        }
+        None => 0usize,
+    };
+
+    Some(minutes * 60000 + secs * 1000 + fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_timestamp, Lyrics};
+
+    #[test]
+    fn timestamp_fractions() {
+        assert_eq!(parse_timestamp("01:02"), Some(62000));
+        assert_eq!(parse_timestamp("01:02.5"), Some(62500));
+        assert_eq!(parse_timestamp("01:02.05"), Some(62050));
+        assert_eq!(parse_timestamp("01:02.005"), Some(62005));
+        assert_eq!(parse_timestamp("01:02.1234"), Some(62123));
+        assert_eq!(parse_timestamp("01:02:50"), Some(62500));
+    }
+
+    #[test]
+    fn timestamp_invalid() {
+        assert_eq!(parse_timestamp("offset:500"), None);
+        assert_eq!(parse_timestamp("ar:someone"), None);
+        assert_eq!(parse_timestamp("01:02.x"), None);
+        assert_eq!(parse_timestamp("01"), None);
+    }
+
+    #[test]
+    fn lrc_several_timestamps() {
+        let lyrics = Lyrics::from_lrc("[00:10.00][00:30.00]chorus\n[00:20.00]verse\n");
+        let lines: Vec<(usize, &str)> = lyrics
+            .lines
+            .iter()
+            .map(|l| (l.time, l.text.as_str()))
+            .collect();
+
+        assert!(lyrics.synced);
+        assert_eq!(
+            lines,
+            vec![(10000, "chorus"), (20000, "verse"), (30000, "chorus")]
+        );
+    }
+
+    #[test]
+    fn lrc_offset() {
+        let lyrics = Lyrics::from_lrc("[offset:500]\n[00:00.20]first\n[00:01.00]second\n");
+        let times: Vec<usize> = lyrics.lines.iter().map(|l| l.time).collect();
+
+        assert_eq!(times, vec![0, 500]);
+    }
+}
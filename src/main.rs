@@ -23,66 +23,187 @@ extern crate num;
 extern crate rfmod;
 extern crate sfml;
 
+use config::Config;
 use graphic_handler::GraphicHandler;
 use playlist::PlayList;
-use rfmod::*;
 use sfml::graphics::Font;
-use sfml::graphics::RenderWindow;
-use sfml::window::{ContextSettings, Style, VideoMode};
 use std::env;
+mod bookmarks;
+mod bpm;
+mod chapters;
+mod config;
+mod crossfade;
+mod decoder;
+mod equalizer;
+mod fft;
+mod gapless;
+mod graphic_bookmarks;
 mod graphic_button;
 mod graphic_element;
+mod graphic_equalizer;
+mod graphic_goniometer;
 mod graphic_handler;
+mod graphic_lyrics;
+mod graphic_now_playing;
+mod graphic_oscilloscope;
 mod graphic_playlist;
 mod graphic_sound_position;
+mod graphic_spectrogram;
 mod graphic_spectrum;
+mod graphic_status;
 mod graphic_timer;
+mod graphic_tooltip;
+mod graphic_vu_meter;
+mod library;
+mod loudness;
+mod lyrics;
+mod mp4;
+mod output;
 mod playlist;
 mod progress_bar;
+mod recorder;
+mod render;
+mod replay_gain;
+mod sleep_timer;
+mod tags;
+mod track;
+mod trajectory;
+mod volume;
+mod waveform;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let mut config = Config::load();
+    let musics = config.parse_args(&args[1..]);
 
-    if args.len() < 2 {
-        println!("USAGE: music_player [music_files ...]");
+    if musics.len() == 1 && musics[0] == "--list-devices" {
+        let fmod = output::create_system(&config);
+
+        for (id, name) in output::get_drivers(&fmod).iter().enumerate() {
+            println!("{} : {}", id, name);
+        }
+        return;
+    }
+    if !musics.is_empty() && musics[0] == "--analyze-bpm" {
+        bpm::analyze_library(&musics[1..]);
+        return;
+    }
+    if musics.len() > 1 && musics[0] == "--render" {
+        if musics.len() < 3 {
+            println!("USAGE: music_player [--option=value ...] --render output.wav music_files ...");
+            return;
+        }
+        match render::render(&config, &musics[2..], &musics[1]) {
+            Ok(_) => println!("{} written", musics[1]),
+            Err(e) => {
+                println!("Rendering failed : {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if musics.len() < 1 {
+        println!("USAGE: music_player [--option=value ...] [music_files ...]");
         println!("For more information: music_player -h");
         println!("Or                  : music_player --help");
         return;
-    } else if args.len() == 2 && (args[1] == "-h" || args[1] == "--help") {
-        println!("usage: music_player [music_files ...]");
+    } else if musics.len() == 1 && (musics[0] == "-h" || musics[0] == "--help") {
+        println!("usage: music_player [--option=value ...] [music_files ...]");
+        println!("   or: music_player --list-devices");
+        println!("   or: music_player [--option=value ...] --render output.wav music_files ...");
+        println!("       (plays the musics into a WAV file, as fast as possible, without audio device)");
+        println!("   or: music_player --analyze-bpm [music_files_or_folders ...]");
+        println!("       (stores the tempo of the musics, or of the library's ones, in the library)");
         println!("Here is the list of the binded keyboards keys:");
         println!("* ESC : exit the program");
         println!("* Up / Down : change the music");
+        println!("* Left / Right : go 5 seconds backward / forward (30 seconds with Shift)");
+        println!("* 0 - 9 : go to 0% - 90% of the music");
         println!("* R : turn on/off song repeat");
+        println!("* G : change the ReplayGain mode (track / album / off)");
+        println!("* E : show / hide the equalizer");
+        println!("* Comma / Period : play slower / faster (from 0.5x to 2x)");
+        println!("* SemiColon / Quote : shift the pitch by one semitone down / up");
+        println!("* Slash : reset the speed and the pitch");
+        println!("* T : keep the pitch when changing the speed or not");
+        println!("* [ / ] : set the A / B point of the loop at the current position");
+        println!("* BackSlash : clear the A-B loop");
+        println!("* Ctrl + Left / Right : move the A point by 100ms");
+        println!("* Alt + Left / Right : move the B point by 100ms");
+        println!("* B : add a bookmark (type its name then press Return)");
+        println!("* PageUp / PageDown : go to the previous / next bookmark");
+        println!("* K : show the bookmarks instead of the lyrics");
+        println!("* X : export the bookmarks as a CUE sheet and a chapters file");
+        println!("* J / L : go 15 seconds backward / forward (30 seconds with Shift)");
+        println!("* F4 : turn on/off the audiobook mode (resume musics where they were left)");
+        println!("* Z : change the sleep timer (15, 30, 45, 60, 90 minutes, end of music, end of album, off)");
         println!("* Add / Subtract : change the music volume");
+        println!("* M : mute / unmute");
+        println!("* V : change the volume curve (linear / cubic / db)");
+        println!("* Numpad4 / Numpad6 : move the balance to the left / right");
+        println!("* Numpad5 : center the balance");
+        println!("* F2 : switch to the next output device");
+        println!("* F9 : start / stop recording what is heard");
+        println!("* F6 : change the analysed channels (left / right, mid, side, sum)");
+        println!("* F7 : change the FFT size (from 64 to 8192)");
+        println!("* F8 : change the FFT window");
+        println!("* W : show / hide the waveform of the music in the progress bar");
+        println!("* F11 : show the selected view alone in fullscreen (ESC to leave it)");
         println!("* Space : pause / unpause current music");
-        println!("* BackSpace : reset user position (in 3D)");
+        println!("* BackSpace : reset user position, height and direction (in 3D)");
+        println!("* P : move the user in 3D (circle, figure-eight, random walk, drawn path, off)");
+        println!("* Home / End : move the user in 3D slower / faster");
         println!("* Delete : remove the current music\n");
         println!("You can also interact with the software like this :");
         println!("* you can scroll the playlist");
-        println!("* you can click on a music to play it");
-        println!("* you can click on the music progress bar to go to precise position");
+        println!("* you can click on a music to play it, or on one of its chapters to go to it");
+        println!("* you can click on a lyrics line to go to its timestamp");
+        println!("* you can click on a bookmark to go to it, or right click to remove it");
+        println!("* you can click on the music progress bar to go to precise position, or drag it");
+        println!("* you can shift + left / right click on the music progress bar to set the A / B point");
         println!("* you can click on the volume progress bar to change the music's volume");
+        println!("  (hovering it shows the volume in dB)");
+        println!("* you can hover the level meters to see the RMS and peak levels, and click on");
+        println!("  them to turn the clipping LED off");
         println!("* you can click to change your 3D position");
+        println!("* you can click on the spectrum to switch between the linear and the bar spectrum");
+        println!("* you can click on the spectrogram to change its colors");
+        println!("* you can click on the tabs to show the spectrum, the spectrogram (Waterfall),");
+        println!("  the oscilloscope (Scope), the goniometer and phase correlation (Stereo) or");
+        println!("  the 3D position");
+        println!("* you can click on the equalizer sliders to change the gain of a band\n");
+        println!("Options can be set in ~/.music_player/config (as \"option = value\") or");
+        println!("on the command line (as \"--option=value\") :");
+        println!("* crossfade : crossfade duration between musics in seconds (0 = gapless)");
+        println!("* skip_fade : crossfade duration when changing the music by hand");
+        println!("* fade_curve : linear, equal-power or log");
+        println!("* replaygain : track, album or off");
+        println!("* replaygain_preamp : gain added to the ReplayGain in dB");
+        println!("* analyze_loudness : compute the gain of musics without ReplayGain tags");
+        println!("* equalizer : name of the equalizer preset");
+        println!("* time_stretch : keep the pitch when changing the speed (true or false)");
+        println!("* audiobook : resume musics where they were left (true or false)");
+        println!("* volume_curve : linear, cubic or db");
+        println!("* balance : stereo balance, from -1 (left) to 1 (right)");
+        println!("* output : id or part of the name of the output device (see --list-devices)");
+        println!("* sample_rate : output sample rate in Hz");
+        println!("* buffer_size : length of the mixing buffers in samples");
+        println!("* speaker_mode : mono, stereo, quad, surround, 5.1 or 7.1");
+        println!("* record_format : format of the recordings, wav or flac");
+        println!("* record_dir : folder of the recordings (~/.music_player/recordings by default)");
+        println!("* spectrum : linear or bars (log-spaced bands in dB with peak hold)");
+        println!("* spectrum_bands : number of bands of the bar spectrum (32 by default)");
+        println!("* spectrogram_colormap : heat, gray or green");
+        println!("* fft_size : size of the spectrum, a power of two from 64 to 8192 (256 by default)");
+        println!("* fft_window : rect, triangle, hamming, hann, blackman or blackman-harris");
+        println!("* fft_channels : split (left / right), mid, side or sum");
+        println!("* waveform : true to show the waveform of the music in the progress bar");
         return;
     }
 
-    let fmod = match Sys::new() {
-        Ok(f) => {
-            f.init_with_parameters(10i32, InitFlag(rfmod::INIT_NORMAL));
-            f
-        }
-        Err(e) => panic!("FmodSys.new : {:?}", e),
-    };
-    let mut window = RenderWindow::new(
-        VideoMode::new(800, 600, 32),
-        "Music Player",
-        Style::CLOSE,
-        &ContextSettings::default(),
-    );
+    let fmod = output::create_system(&config);
+    let mut window = graphic_handler::create_window(false);
     let font = Font::from_file("font/arial.ttf").unwrap();
-    let mut graph = GraphicHandler::new(&window, PlayList::from_slice(&args[1..]), &font);
-    window.set_vertical_sync_enabled(true);
-    window.set_framerate_limit(30u32);
+    let mut graph = GraphicHandler::new(&window, PlayList::from_slice(&musics), &font, config);
     graph.start(&mut window, &fmod);
 }
@@ -0,0 +1,182 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+// atoms bigger than this aren't read in memory
+const MAX_ATOM_SIZE: u64 = 64 * 1024 * 1024;
+
+pub fn read_u32(data: &[u8]) -> u32 {
+    (data[0] as u32) << 24 | (data[1] as u32) << 16 | (data[2] as u32) << 8 | data[3] as u32
+}
+
+pub fn read_u64(data: &[u8]) -> u64 {
+    (read_u32(data) as u64) << 32 | read_u32(&data[4..]) as u64
+}
+
+/// Returns the content of the top level atom `name`, the other atoms being
+/// skipped without being read. `None` is returned if the file doesn't start
+/// with a "ftyp" atom, so other formats are quickly left aside.
+pub fn read_top_atom(file: &mut File, name: &[u8; 4]) -> Option<Vec<u8>> {
+    let length = match file.metadata() {
+        Ok(m) => m.len(),
+        Err(_) => return None,
+    };
+    let mut pos = 0u64;
+
+    if file.seek(SeekFrom::Start(0)).is_err() {
+        return None;
+    }
+    while pos + 8 <= length {
+        let mut header = [0u8; 16];
+
+        if file.read_exact(&mut header[..8]).is_err() {
+            return None;
+        }
+        let mut size = read_u32(&header) as u64;
+        let mut header_size = 8u64;
+
+        if pos == 0 && &header[4..8] != b"ftyp" {
+            return None;
+        }
+        if size == 1 {
+            if file.read_exact(&mut header[8..]).is_err() {
+                return None;
+            }
+            size = read_u64(&header[8..]);
+            header_size = 16;
+        } else if size == 0 {
+            size = length - pos;
+        }
+        if size < header_size || pos + size > length {
+            return None;
+        }
+        if &header[4..8] == name {
+            if size - header_size > MAX_ATOM_SIZE {
+                return None;
+            }
+            let mut data = vec![0u8; (size - header_size) as usize];
+
+            return match file.read_exact(&mut data) {
+                Ok(_) => Some(data),
+                Err(_) => None,
+            };
+        }
+        pos += size;
+        if file.seek(SeekFrom::Start(pos)).is_err() {
+            return None;
+        }
+    }
+    None
+}
+
+/// Follows the atoms of `path` and returns the content of the last one.
+pub fn find_atom<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    if path.is_empty() {
+        return Some(data);
+    }
+    let mut pos = 0usize;
+
+    while pos + 8 <= data.len() {
+        let mut size = read_u32(&data[pos..]) as usize;
+        let mut header = 8usize;
+
+        if size == 1 && pos + 16 <= data.len() {
+            size = read_u64(&data[pos + 8..]) as usize;
+            header = 16;
+        } else if size == 0 {
+            size = data.len() - pos;
+        }
+        if size < header || pos + size > data.len() {
+            return None;
+        }
+        if &data[pos + 4..pos + 8] == path[0] {
+            return find_atom(&data[pos + header..pos + size], &path[1..]);
+        }
+        pos += size;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_atom;
+
+    fn atom(name: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let size = content.len() + 8;
+        let mut data = vec![
+            (size >> 24) as u8,
+            (size >> 16) as u8,
+            (size >> 8) as u8,
+            size as u8,
+        ];
+
+        data.extend_from_slice(name);
+        data.extend_from_slice(content);
+        data
+    }
+
+    #[test]
+    fn nested_atoms() {
+        let mut udta = atom(b"free", b"xxxx");
+
+        udta.extend(atom(b"chpl", b"chapters"));
+        let mut data = atom(b"mvhd", &[0u8; 12]);
+
+        data.extend(atom(b"udta", &udta));
+        assert_eq!(
+            find_atom(&data, &[b"udta", b"chpl"]),
+            Some(&b"chapters"[..])
+        );
+        assert_eq!(find_atom(&data, &[b"udta", b"meta"]), None);
+        assert_eq!(find_atom(&data, &[]), Some(&data[..]));
+    }
+
+    #[test]
+    fn large_and_last_atoms() {
+        // 64 bits size, then an atom going to the end of the data
+        let mut data = vec![0, 0, 0, 1];
+
+        data.extend_from_slice(b"free");
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 20, 1, 2, 3, 4]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(b"moov");
+        data.extend_from_slice(b"content");
+        assert_eq!(find_atom(&data, &[b"free"]), Some(&[1u8, 2, 3, 4][..]));
+        assert_eq!(find_atom(&data, &[b"moov"]), Some(&b"content"[..]));
+    }
+
+    #[test]
+    fn broken_atoms() {
+        // the size goes past the end of the data
+        let data = atom(b"udta", b"chpl");
+
+        assert_eq!(find_atom(&data[..10], &[b"udta"]), None);
+        assert_eq!(
+            find_atom(&[0, 0, 0, 4, b'f', b'r', b'e', b'e'], &[b"free"]),
+            None
+        );
+    }
+}
@@ -0,0 +1,174 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use config::Config;
+use rfmod;
+use std::env;
+use std::path::Path;
+
+pub fn parse_speaker_mode(s: &str) -> Option<rfmod::SpeakerMode> {
+    match s {
+        "mono" => Some(rfmod::SpeakerMode::Mono),
+        "stereo" => Some(rfmod::SpeakerMode::Stereo),
+        "quad" => Some(rfmod::SpeakerMode::Quad),
+        "surround" => Some(rfmod::SpeakerMode::Surround),
+        "5.1" => Some(rfmod::SpeakerMode::_5Point1),
+        "7.1" => Some(rfmod::SpeakerMode::_7Point1),
+        _ => None,
+    }
+}
+
+/// Returns the names of the output devices, the index being the driver id.
+pub fn get_drivers(fmod: &rfmod::Sys) -> Vec<String> {
+    let nb = match fmod.get_num_drivers() {
+        Ok(n) => n,
+        Err(e) => {
+            println!("FmodSys::get_num_drivers failed : {:?}", e);
+            return Vec::new();
+        }
+    };
+
+    (0..nb)
+        .map(|id| match fmod.get_driver_info(id, 256usize) {
+            Ok((_, name)) => name,
+            Err(_) => format!("driver {}", id),
+        })
+        .collect()
+}
+
+/// Finds a driver from its id or from a part of its name.
+pub fn find_driver(fmod: &rfmod::Sys, driver: &str) -> Option<i32> {
+    let drivers = get_drivers(fmod);
+
+    match driver.parse::<usize>() {
+        Ok(id) if id < drivers.len() => return Some(id as i32),
+        _ => {}
+    }
+    let driver = driver.to_lowercase();
+
+    drivers
+        .iter()
+        .position(|d| d.to_lowercase().contains(&driver))
+        .map(|id| id as i32)
+}
+
+pub fn get_driver_name(fmod: &rfmod::Sys) -> Option<String> {
+    match fmod.get_driver() {
+        Ok(id) => get_drivers(fmod).get(id as usize).cloned(),
+        Err(_) => None,
+    }
+}
+
+/// Switches to the next output device. The channels keep playing.
+pub fn next_driver(fmod: &rfmod::Sys) -> Option<String> {
+    let nb = get_drivers(fmod).len() as i32;
+    let current = match fmod.get_driver() {
+        Ok(id) => id,
+        Err(_) => 0i32,
+    };
+
+    if nb < 2 {
+        return None;
+    }
+    match fmod.set_driver((current + 1) % nb) {
+        rfmod::Status::Ok => get_driver_name(fmod),
+        e => {
+            println!("FmodSys::set_driver failed : {:?}", e);
+            None
+        }
+    }
+}
+
+fn set_format(fmod: &rfmod::Sys, config: &Config) {
+    if config.sample_rate.is_some() || config.speaker_mode.is_some() {
+        let rate = config.sample_rate.unwrap_or(48000u32);
+
+        fmod.set_software_format(
+            rate as i32,
+            rfmod::SoundFormat::PCM16,
+            0,
+            0,
+            rfmod::DSPResampler::Linear,
+        );
+    }
+    if let Some(mode) = config.speaker_mode {
+        fmod.set_speaker_mode(mode);
+    }
+}
+
+/// Creates the FMOD system with the output settings of `config`.
+pub fn create_system(config: &Config) -> rfmod::Sys {
+    let fmod = match rfmod::Sys::new() {
+        Ok(f) => f,
+        Err(e) => panic!("FmodSys.new : {:?}", e),
+    };
+
+    if let Some(ref driver) = config.output_driver {
+        match find_driver(&fmod, driver) {
+            Some(id) => {
+                fmod.set_driver(id);
+            }
+            None => println!("Unknown output device : {}", driver),
+        }
+    }
+    set_format(&fmod, config);
+    if let Some(size) = config.buffer_size {
+        // FMOD's default is 4 buffers of 1024 samples
+        fmod.set_DSP_buffer_size(size, 4i32);
+    }
+    fmod.init_with_parameters(10i32, rfmod::InitFlag(rfmod::INIT_NORMAL));
+    fmod
+}
+
+/// Creates a FMOD system which doesn't use any audio device: each call to
+/// `update` mixes the next buffer and appends it to a WAV file. The WAV
+/// writer creates this file in the current directory when it is initialized,
+/// so `dir` is made the current one for that time.
+pub fn create_render_system(config: &Config, dir: &Path) -> Result<rfmod::Sys, String> {
+    let fmod = match rfmod::Sys::new() {
+        Ok(f) => f,
+        Err(e) => return Err(format!("FmodSys.new : {:?}", e)),
+    };
+
+    fmod.set_output(rfmod::OutputType::WavWriterNRT);
+    set_format(&fmod, config);
+    let cwd = match env::current_dir() {
+        Ok(d) => d,
+        Err(e) => return Err(format!("Cannot get the current directory : {}", e)),
+    };
+
+    if let Err(e) = env::set_current_dir(dir) {
+        return Err(format!("Cannot use {} : {}", dir.display(), e));
+    }
+    let status = fmod.init_with_parameters(10i32, rfmod::InitFlag(rfmod::INIT_NORMAL));
+
+    // the musics given with a relative path are opened after that
+    if let Err(e) = env::set_current_dir(&cwd) {
+        return Err(format!("Cannot go back to {} : {}", cwd.display(), e));
+    }
+    match status {
+        rfmod::Status::Ok => Ok(fmod),
+        e => Err(format!("FmodSys.init : {:?}", e)),
+    }
+}
@@ -107,6 +107,19 @@ impl PlayList {
         self.musics[self.actual].clone()
     }
 
+    /// Returns the music that will be played once the current one ends.
+    pub fn peek_next(&self) -> String {
+        if self.repeat || self.actual >= self.musics.len() - 1 {
+            if self.repeat {
+                self.musics[self.actual].clone()
+            } else {
+                self.musics[0].clone()
+            }
+        } else {
+            self.musics[self.actual + 1].clone()
+        }
+    }
+
     pub fn add_music(&mut self, music: String) {
         if !self.musics.contains(&music) {
             self.musics.push(music)
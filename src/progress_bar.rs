@@ -24,8 +24,8 @@
 #![allow(unused_variables)]
 
 use graphic_element::GraphicElement;
-use sfml::graphics::{Color, Font, RectangleShape, RenderTarget, RenderWindow};
-use sfml::graphics::{Shape, Transformable};
+use sfml::graphics::{Color, Font, PrimitiveType, RectangleShape, RenderTarget, RenderWindow};
+use sfml::graphics::{Shape, Transformable, Vertex, VertexArray};
 use sfml::system::Vector2f;
 pub struct ProgressBar<'b> {
     line: RectangleShape<'b>,
@@ -34,16 +34,30 @@ pub struct ProgressBar<'b> {
     real_value: usize,
     cleaner: RectangleShape<'b>,
     need_to_draw: bool,
+    vertical: bool,
+    // highlighted span, in the same unit as the value
+    region: Option<(usize, usize)>,
+    region_shape: RectangleShape<'b>,
+    // ticks drawn over the bar, in the same unit as the value
+    marks: Vec<usize>,
+    mark_shapes: Vec<RectangleShape<'b>>,
+    // peaks (from 0 to 1) drawn instead of the line, the played part being
+    // in the bar's color
+    waveform: Vec<f32>,
+    waveform_shape: VertexArray,
+    color: Color,
     name: String,
 }
 
 impl<'b> ProgressBar<'b> {
     fn init(mut self, color: &Color, position: &Vector2f) -> ProgressBar<'b> {
         self.set_position(position);
+        self.color = color.clone();
         self.line.set_fill_color(color);
         self.cleaner.set_fill_color(&Color::rgb(0, 0, 0));
         self.cleaner.set_outline_color(&Color::rgb(255, 255, 255));
         self.cleaner.set_outline_thickness(1f32);
+        self.region_shape.set_fill_color(&Color::rgba(255, 175, 100, 120));
         self
     }
 
@@ -54,26 +68,231 @@ impl<'b> ProgressBar<'b> {
             position
         };
         if self.maximum > 0usize {
-            let new_value = tmp * (self.cleaner.size().x as usize - 2usize) / self.maximum;
+            let length = if self.vertical {
+                self.cleaner.size().y
+            } else {
+                self.cleaner.size().x
+            };
+            let new_value = tmp * (length as usize - 2usize) / self.maximum;
 
+            // the value has to be kept even if the line doesn't move
+            self.real_value = tmp;
             if new_value != self.value {
                 self.need_to_draw = true;
                 self.value = new_value;
-                self.real_value = position;
-                self.line.set_size(Vector2f {
-                    x: self.value as f32,
-                    y: self.cleaner.size().y as f32 - 2f32,
+                self.place_line();
+                self.place_waveform();
+            }
+        }
+    }
+
+    fn place_line(&mut self) {
+        let pos = self.cleaner.position();
+        let size = self.cleaner.size();
+
+        if self.vertical {
+            // a vertical bar grows from the bottom
+            self.line.set_size(Vector2f {
+                x: size.x - 2f32,
+                y: self.value as f32,
+            });
+            self.line.set_position(Vector2f {
+                x: pos.x + 1f32,
+                y: pos.y + size.y - 1f32 - self.value as f32,
+            });
+        } else {
+            self.line.set_size(Vector2f {
+                x: self.value as f32,
+                y: size.y - 2f32,
+            });
+            self.line.set_position(Vector2f {
+                x: pos.x + 1f32,
+                y: pos.y + 1f32,
+            });
+        }
+    }
+
+    /// Draws one column per pixel, centered on the middle of the bar.
+    fn place_waveform(&mut self) {
+        self.waveform_shape.clear();
+        if self.waveform.is_empty() || self.vertical {
+            return;
+        }
+        let pos = self.cleaner.position();
+        let size = self.cleaner.size();
+        let width = (size.x - 2f32).max(1f32) as usize;
+        let middle = pos.y + size.y / 2f32;
+
+        for x in 0..width {
+            let peak = self.waveform[x * self.waveform.len() / width];
+            let half = (peak * (size.y - 2f32) / 2f32).max(0.5f32);
+            let color = if x < self.value {
+                self.color
+            } else {
+                Color::rgb(90, 90, 90)
+            };
+            let left = pos.x + 1f32 + x as f32;
+
+            for &(dx, dy) in [(0f32, -half), (1f32, -half), (1f32, half), (0f32, half)].iter() {
+                self.waveform_shape.append(&Vertex::with_pos_color(
+                    Vector2f {
+                        x: left + dx,
+                        y: middle + dy,
+                    },
+                    color,
+                ));
+            }
+        }
+    }
+
+    /// Shows the `peaks` of the music instead of a flat line, `None` goes
+    /// back to the line.
+    pub fn set_waveform(&mut self, peaks: Option<Vec<f32>>) {
+        self.waveform = match peaks {
+            Some(p) => p,
+            None => Vec::new(),
+        };
+        self.place_waveform();
+        self.need_to_draw = true;
+    }
+
+    pub fn has_waveform(&self) -> bool {
+        !self.waveform.is_empty()
+    }
+
+    fn place_region(&mut self) {
+        let (start, end) = match self.region {
+            Some(r) if self.maximum > 0usize => r,
+            _ => return,
+        };
+        let pos = self.cleaner.position();
+        let size = self.cleaner.size();
+        let length = if self.vertical { size.y } else { size.x } - 2f32;
+        let start = start.min(self.maximum) as f32 * length / self.maximum as f32;
+        let end = end.min(self.maximum) as f32 * length / self.maximum as f32;
+
+        if self.vertical {
+            self.region_shape.set_size(Vector2f {
+                x: size.x - 2f32,
+                y: end - start,
+            });
+            self.region_shape.set_position(Vector2f {
+                x: pos.x + 1f32,
+                y: pos.y + size.y - 1f32 - end,
+            });
+        } else {
+            self.region_shape.set_size(Vector2f {
+                x: end - start,
+                y: size.y - 2f32,
+            });
+            self.region_shape.set_position(Vector2f {
+                x: pos.x + 1f32 + start,
+                y: pos.y + 1f32,
+            });
+        }
+    }
+
+    fn place_marks(&mut self) {
+        if self.maximum == 0usize {
+            return;
+        }
+        let pos = self.cleaner.position();
+        let size = self.cleaner.size();
+        let length = if self.vertical { size.y } else { size.x } - 2f32;
+
+        for (mark, shape) in self.marks.iter().zip(self.mark_shapes.iter_mut()) {
+            let offset = (*mark).min(self.maximum) as f32 * length / self.maximum as f32;
+
+            if self.vertical {
+                shape.set_size(Vector2f {
+                    x: size.x - 2f32,
+                    y: 2f32,
+                });
+                shape.set_position(Vector2f {
+                    x: pos.x + 1f32,
+                    y: pos.y + size.y - 2f32 - offset,
+                });
+            } else {
+                shape.set_size(Vector2f {
+                    x: 2f32,
+                    y: size.y - 2f32,
+                });
+                shape.set_position(Vector2f {
+                    x: pos.x + offset,
+                    y: pos.y + 1f32,
                 });
             }
         }
     }
 
+    pub fn set_marks(&mut self, marks: &[usize]) {
+        self.marks = marks.to_vec();
+        self.mark_shapes = marks
+            .iter()
+            .map(|_| {
+                let mut shape = RectangleShape::new();
+
+                shape.set_fill_color(&Color::rgb(100, 150, 255));
+                shape
+            })
+            .collect();
+        self.place_marks();
+        self.need_to_draw = true;
+    }
+
+    /// Highlights the span between the two values, `None` removes it.
+    pub fn set_region(&mut self, region: Option<(usize, usize)>) {
+        self.region = match region {
+            Some((a, b)) if a > b => Some((b, a)),
+            r => r,
+        };
+        self.place_region();
+        self.need_to_draw = true;
+    }
+
+    pub fn get_region(&self) -> Option<(usize, usize)> {
+        self.region
+    }
+
+    /// Returns the value matching `position` without changing the progress.
+    pub fn get_value_at(&self, position: &Vector2f) -> usize {
+        let pos = self.cleaner.position();
+        let size = self.cleaner.size();
+        // same scale as the line drawn by set_progress
+        let ratio = if self.vertical {
+            (pos.y + size.y - 1f32 - position.y) as f64 / (size.y - 2f32) as f64
+        } else {
+            (position.x - pos.x - 1f32) as f64 / (size.x - 2f32) as f64
+        };
+
+        (ratio.max(0f64).min(1f64) * self.maximum as f64).round() as usize
+    }
+
+    pub fn set_vertical(&mut self, vertical: bool) {
+        if self.vertical != vertical {
+            let tmp_real_value = self.real_value;
+
+            self.vertical = vertical;
+            self.value = 0usize;
+            self.set_progress(tmp_real_value);
+            self.place_line();
+            self.place_region();
+            self.place_marks();
+        }
+    }
+
+    pub fn is_vertical(&self) -> bool {
+        self.vertical
+    }
+
     pub fn get_real_value(&self) -> usize {
         self.real_value
     }
 
     pub fn set_maximum(&mut self, maximum: usize) {
         self.maximum = maximum;
+        self.place_region();
+        self.place_marks();
     }
 }
 
@@ -95,27 +314,43 @@ impl<'b> GraphicElement<'b> for ProgressBar<'b> {
                 y: size.y as f32 + 1f32,
             }),
             need_to_draw: true,
+            vertical: false,
+            region: None,
+            region_shape: RectangleShape::new(),
+            marks: Vec::new(),
+            mark_shapes: Vec::new(),
+            waveform: Vec::new(),
+            waveform_shape: VertexArray::new(PrimitiveType::Quads, 0),
+            color: color.clone(),
         }.init(color, position)
     }
 
     fn is_inside(&self, position: &Vector2f) -> bool {
-        position.y >= self.line.position().y
-            && position.y <= self.line.position().y + self.cleaner.size().y
-            && position.x >= self.line.position().x
-            && position.x <= self.line.position().x + self.cleaner.size().x
+        position.y >= self.cleaner.position().y
+            && position.y <= self.cleaner.position().y + self.cleaner.size().y
+            && position.x >= self.cleaner.position().x
+            && position.x <= self.cleaner.position().x + self.cleaner.size().x
     }
 
     fn clicked(&mut self, position: &Vector2f) {
-        let in_order =
-            (position.x - self.line.position().x) / (self.cleaner.size().x - 1f32) * 100f32;
-        let tmp_maximum = self.maximum;
+        let value = self.get_value_at(position);
 
-        self.set_progress((in_order * tmp_maximum as f32 / 100f32) as usize);
+        self.set_progress(value);
     }
 
     fn draw(&mut self, window: &mut RenderWindow) {
         window.draw(&self.cleaner);
-        window.draw(&self.line);
+        if self.waveform.is_empty() {
+            window.draw(&self.line);
+        } else {
+            window.draw(&self.waveform_shape);
+        }
+        if self.region.is_some() {
+            window.draw(&self.region_shape);
+        }
+        for shape in self.mark_shapes.iter() {
+            window.draw(shape);
+        }
         self.need_to_draw = false;
     }
 
@@ -136,6 +371,10 @@ impl<'b> GraphicElement<'b> for ProgressBar<'b> {
         });
         let tmp_real_value = self.real_value;
         self.set_progress(tmp_real_value);
+        self.place_line();
+        self.place_waveform();
+        self.place_region();
+        self.place_marks();
     }
 
     fn get_position(&self) -> Vector2f {
@@ -149,14 +388,14 @@ impl<'b> GraphicElement<'b> for ProgressBar<'b> {
 
     fn set_position(&mut self, position: &Vector2f) {
         self.need_to_draw = true;
-        self.line.set_position(Vector2f {
-            x: position.x + 2f32,
-            y: position.y + 2f32,
-        });
         self.cleaner.set_position(Vector2f {
             x: position.x + 1f32,
             y: position.y + 1f32,
         });
+        self.place_line();
+        self.place_waveform();
+        self.place_region();
+        self.place_marks();
     }
 
     fn cursor_moved(&mut self, position: &Vector2f) {}
@@ -0,0 +1,480 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use rfmod;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// number of samples (per channel) in a FLAC frame
+const FLAC_BLOCK_SIZE: usize = 4096;
+
+// mixed buffers waiting to be written, the next ones are lost if it's full
+const QUEUE_SIZE: usize = 256;
+
+/// Given to the DSP as user data, so the mixer thread never waits for a lock
+/// or for the disk.
+struct Sink {
+    sender: SyncSender<(usize, Vec<f32>)>,
+    dropped: Arc<AtomicUsize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordFormat {
+    Wav,
+    Flac,
+}
+
+impl RecordFormat {
+    pub fn from_str(s: &str) -> Option<RecordFormat> {
+        match s {
+            "wav" => Some(RecordFormat::Wav),
+            "flac" => Some(RecordFormat::Flac),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            RecordFormat::Wav => "wav",
+            RecordFormat::Flac => "flac",
+        }
+    }
+}
+
+/// Writes what is heard (after the equalizer and the 3D positioning) in a
+/// file, through a DSP added at the end of FMOD's mixing chain.
+pub struct Recorder {
+    dsp: rfmod::Dsp,
+    path: PathBuf,
+    rate: usize,
+    frames: Arc<AtomicUsize>,
+    bytes: Arc<AtomicUsize>,
+    dropped: Arc<AtomicUsize>,
+    // kept alive as long as the DSP uses it
+    sink: Box<Sink>,
+    thread: JoinHandle<io::Result<()>>,
+}
+
+impl Recorder {
+    /// Starts recording in `dir`, the file being named after the current
+    /// date and time.
+    pub fn start(
+        fmod: &rfmod::Sys,
+        format: RecordFormat,
+        dir: &PathBuf,
+    ) -> Result<Recorder, String> {
+        if let Err(e) = fs::create_dir_all(dir) {
+            return Err(format!("Cannot create {} : {}", dir.display(), e));
+        }
+        let mut path = dir.clone();
+
+        path.push(format!("recording-{}.{}", get_timestamp(), format.to_str()));
+        let file = match File::create(&path) {
+            Ok(f) => f,
+            Err(e) => return Err(format!("Cannot create {} : {}", path.display(), e)),
+        };
+        let rate = match fmod.get_software_format() {
+            Ok((rate, ..)) => rate as usize,
+            Err(_) => 48000usize,
+        };
+        let mut description = rfmod::DspDescription::new("recorder");
+
+        description.read = Some(read_callback);
+        let dsp = match fmod.create_DSP(&mut description) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("FmodSys::create_DSP failed : {:?}", e)),
+        };
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_SIZE);
+        let frames = Arc::new(AtomicUsize::new(0));
+        let bytes = Arc::new(AtomicUsize::new(0));
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let writer = AudioFile::new(file, format, rate, frames.clone(), bytes.clone());
+        let mut sink = Box::new(Sink {
+            sender: sender,
+            dropped: dropped.clone(),
+        });
+
+        match dsp.set_user_data(&mut *sink) {
+            rfmod::Status::Ok => {}
+            e => return Err(format!("Dsp::set_user_data failed : {:?}", e)),
+        }
+        if let Err(e) = fmod.add_DSP(&dsp) {
+            return Err(format!("FmodSys::add_DSP failed : {:?}", e));
+        }
+        Ok(Recorder {
+            dsp: dsp,
+            path: path,
+            rate: rate,
+            frames: frames,
+            bytes: bytes,
+            dropped: dropped,
+            sink: sink,
+            thread: thread::spawn(move || writer.run(receiver)),
+        })
+    }
+
+    /// Returns the recorded duration, in ms.
+    pub fn get_elapsed(&self) -> usize {
+        self.frames.load(Ordering::Relaxed) * 1000 / self.rate
+    }
+
+    /// Returns the size of the file, in bytes.
+    pub fn get_size(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Stops the recording and completes the file. Returns its path.
+    pub fn stop(self) -> Result<String, String> {
+        self.dsp.remove();
+        // dropping the sender ends the writer thread
+        drop(self.sink);
+        let path = self.path.to_string_lossy().into_owned();
+        let dropped = self.dropped.load(Ordering::Relaxed);
+
+        if dropped > 0 {
+            println!("{} : {} blocks lost, the disk is too slow", path, dropped);
+        }
+
+        match self.thread.join() {
+            Ok(Ok(_)) => Ok(path),
+            Ok(Err(e)) => Err(format!("Cannot write {} : {}", path, e)),
+            Err(_) => Err(format!("Cannot write {} : writer thread panicked", path)),
+        }
+    }
+}
+
+/// Lets the sound through and sends a copy of it to the writer thread.
+fn read_callback(
+    dsp_state: &rfmod::DspState,
+    in_buffer: &[f32],
+    out_buffer: &mut [f32],
+    length: u32,
+    in_channels: i32,
+) -> rfmod::Status {
+    let len = length as usize * in_channels as usize;
+
+    out_buffer[..len].copy_from_slice(&in_buffer[..len]);
+    match dsp_state.instance.get_user_data::<Sink>() {
+        Ok(sink) => match sink.sender.try_send((in_channels as usize, in_buffer[..len].to_vec())) {
+            Err(TrySendError::Full(_)) => {
+                sink.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        },
+        Err(_) => {}
+    }
+    rfmod::Status::Ok
+}
+
+/// Returns the current UTC date and time as "YYYY-MM-DD_HH-MM-SS".
+fn get_timestamp() -> String {
+    let secs = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs(),
+        Err(_) => 0u64,
+    };
+    // converts the number of days since 1970-01-01 to a civil date
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        year,
+        month,
+        day,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+fn to_i16(sample: f32) -> i16 {
+    (sample.max(-1f32).min(1f32) * 32767f32) as i16
+}
+
+/// 16 bits WAV or FLAC file. The header is written with the first samples,
+/// since the number of channels is only known then, and completed at the
+/// end.
+struct AudioFile {
+    out: BufWriter<File>,
+    format: RecordFormat,
+    rate: usize,
+    channels: usize,
+    frames: Arc<AtomicUsize>,
+    bytes: Arc<AtomicUsize>,
+    // samples waiting to fill a FLAC frame
+    pending: Vec<i16>,
+    nb_flac_frames: u64,
+}
+
+impl AudioFile {
+    fn new(
+        file: File,
+        format: RecordFormat,
+        rate: usize,
+        frames: Arc<AtomicUsize>,
+        bytes: Arc<AtomicUsize>,
+    ) -> AudioFile {
+        AudioFile {
+            out: BufWriter::new(file),
+            format: format,
+            rate: rate,
+            channels: 0,
+            frames: frames,
+            bytes: bytes,
+            pending: Vec::new(),
+            nb_flac_frames: 0,
+        }
+    }
+
+    fn run(mut self, receiver: Receiver<(usize, Vec<f32>)>) -> io::Result<()> {
+        for (channels, samples) in receiver.iter() {
+            if self.channels == 0 {
+                self.channels = channels;
+                try!(self.write_header());
+            } else if channels != self.channels {
+                // the output was changed, the file can't follow
+                continue;
+            }
+            try!(self.write_samples(&samples));
+        }
+        if self.channels == 0 {
+            self.channels = 2;
+            try!(self.write_header());
+        }
+        self.finish()
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        try!(self.out.write_all(data));
+        self.bytes.fetch_add(data.len(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        match self.format {
+            RecordFormat::Wav => {
+                let block_align = self.channels * 2;
+                let mut header = Vec::with_capacity(44);
+
+                header.extend_from_slice(b"RIFF");
+                write_le(&mut header, 36, 4);
+                header.extend_from_slice(b"WAVEfmt ");
+                write_le(&mut header, 16, 4);
+                // PCM
+                write_le(&mut header, 1, 2);
+                write_le(&mut header, self.channels as u64, 2);
+                write_le(&mut header, self.rate as u64, 4);
+                write_le(&mut header, (self.rate * block_align) as u64, 4);
+                write_le(&mut header, block_align as u64, 2);
+                write_le(&mut header, 16, 2);
+                header.extend_from_slice(b"data");
+                write_le(&mut header, 0, 4);
+                self.write(&header)
+            }
+            RecordFormat::Flac => {
+                let mut header = Vec::with_capacity(42);
+
+                header.extend_from_slice(b"fLaC");
+                // last metadata block, STREAMINFO, 34 bytes
+                header.extend_from_slice(&[0x80, 0, 0, 34]);
+                write_be(&mut header, FLAC_BLOCK_SIZE as u64, 2);
+                write_be(&mut header, FLAC_BLOCK_SIZE as u64, 2);
+                // unknown frame sizes
+                write_be(&mut header, 0, 6);
+                header.extend_from_slice(&self.get_flac_info(0));
+                // no MD5 signature
+                header.extend_from_slice(&[0u8; 16]);
+                self.write(&header)
+            }
+        }
+    }
+
+    /// Sample rate, channels, bits per sample and total samples, as packed
+    /// in STREAMINFO.
+    fn get_flac_info(&self, total: u64) -> Vec<u8> {
+        let value = (self.rate as u64) << 44
+            | ((self.channels as u64 - 1) & 7) << 41
+            | 15u64 << 36
+            | (total & 0xF_FFFF_FFFF);
+        let mut info = Vec::with_capacity(8);
+
+        write_be(&mut info, value, 8);
+        info
+    }
+
+    fn write_samples(&mut self, samples: &[f32]) -> io::Result<()> {
+        self.frames.fetch_add(samples.len() / self.channels, Ordering::Relaxed);
+        match self.format {
+            RecordFormat::Wav => {
+                let mut data = Vec::with_capacity(samples.len() * 2);
+
+                for sample in samples.iter() {
+                    write_le(&mut data, to_i16(*sample) as u16 as u64, 2);
+                }
+                self.write(&data)
+            }
+            RecordFormat::Flac => {
+                self.pending.extend(samples.iter().map(|s| to_i16(*s)));
+                let block_len = FLAC_BLOCK_SIZE * self.channels;
+
+                while self.pending.len() >= block_len {
+                    let block: Vec<i16> = self.pending.drain(..block_len).collect();
+
+                    try!(self.write_flac_frame(&block));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes a frame made of one verbatim (uncompressed) subframe per
+    /// channel.
+    fn write_flac_frame(&mut self, block: &[i16]) -> io::Result<()> {
+        let nb_samples = block.len() / self.channels;
+        let mut frame = vec![0xFF, 0xF8];
+
+        // block size stored at the end of the header, sample rate from
+        // STREAMINFO
+        frame.push(0x70);
+        // independent channels, 16 bits per sample
+        frame.push(((self.channels as u8 - 1) << 4) | 0x08);
+        write_utf8_number(&mut frame, self.nb_flac_frames);
+        write_be(&mut frame, nb_samples as u64 - 1, 2);
+        let crc = crc8(&frame);
+
+        frame.push(crc);
+        for channel in 0..self.channels {
+            // verbatim subframe, no wasted bits
+            frame.push(0x02);
+            for sample in block.iter().skip(channel).step_by(self.channels) {
+                write_be(&mut frame, *sample as u16 as u64, 2);
+            }
+        }
+        let crc = crc16(&frame);
+
+        write_be(&mut frame, crc as u64, 2);
+        self.nb_flac_frames += 1;
+        self.write(&frame)
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        match self.format {
+            RecordFormat::Wav => {
+                let data_size = self.bytes.load(Ordering::Relaxed) as u64 - 44;
+                let mut size = Vec::with_capacity(4);
+
+                try!(self.out.seek(SeekFrom::Start(4)));
+                write_le(&mut size, data_size + 36, 4);
+                try!(self.out.write_all(&size));
+                size.clear();
+                try!(self.out.seek(SeekFrom::Start(40)));
+                write_le(&mut size, data_size, 4);
+                try!(self.out.write_all(&size));
+            }
+            RecordFormat::Flac => {
+                if !self.pending.is_empty() {
+                    let block = self.pending.split_off(0);
+
+                    try!(self.write_flac_frame(&block));
+                }
+                let total = self.frames.load(Ordering::Relaxed) as u64;
+                let info = self.get_flac_info(total);
+
+                // STREAMINFO starts at 8, the packed info 10 bytes after
+                try!(self.out.seek(SeekFrom::Start(18)));
+                try!(self.out.write_all(&info));
+            }
+        }
+        self.out.flush()
+    }
+}
+
+fn write_le(out: &mut Vec<u8>, value: u64, nb_bytes: usize) {
+    for i in 0..nb_bytes {
+        out.push((value >> (8 * i)) as u8);
+    }
+}
+
+fn write_be(out: &mut Vec<u8>, value: u64, nb_bytes: usize) {
+    for i in (0..nb_bytes).rev() {
+        out.push((value >> (8 * i)) as u8);
+    }
+}
+
+/// FLAC frame numbers are coded like UTF-8 characters, extended to 36 bits.
+fn write_utf8_number(out: &mut Vec<u8>, value: u64) {
+    if value < 0x80 {
+        out.push(value as u8);
+        return;
+    }
+    let mut nb_bytes = 2usize;
+
+    while nb_bytes < 7 && value >= 1u64 << (5 * nb_bytes + 1) {
+        nb_bytes += 1;
+    }
+    let first_mask = !(0xFFu8 >> nb_bytes);
+
+    out.push(first_mask | (value >> (6 * (nb_bytes - 1))) as u8);
+    for i in (0..nb_bytes - 1).rev() {
+        out.push(0x80 | ((value >> (6 * i)) & 0x3F) as u8);
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+
+    for byte in data.iter() {
+        crc ^= *byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+
+    for byte in data.iter() {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+    }
+    crc
+}
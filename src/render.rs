@@ -0,0 +1,325 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use config::Config;
+use crossfade::Crossfade;
+use equalizer::Equalizer;
+use library::Library;
+use loudness;
+use output;
+use rfmod;
+use std::env;
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::process;
+use track::Track;
+use volume;
+
+/// File written by FMOD's WAV writer, in the folder given to the system.
+const FMOD_OUTPUT: &'static str = "fmodoutput.wav";
+// how long before its end the next music is scheduled, in ms
+const PRELOAD_TIME: usize = 2000;
+
+/// Mixes the musics through the same chain as the player (gain, equalizer,
+/// crossfade, 3D position) without audio device, as fast as possible, and
+/// writes the result in `path`. The volume and the listener are the default
+/// ones so the output only depends on the files and the config.
+pub fn render(config: &Config, musics: &[String], path: &str) -> Result<(), String> {
+    // a folder of its own, so FMOD's file doesn't end up in the current one
+    let mut dir = env::temp_dir();
+
+    dir.push(format!("music_player_render_{}", process::id()));
+    if let Err(e) = fs::create_dir_all(&dir) {
+        return Err(format!("Cannot create {} : {}", dir.display(), e));
+    }
+    let res = match render_in(config, musics, &dir) {
+        Ok(_) => move_file(&dir.join(FMOD_OUTPUT), Path::new(path)),
+        Err(e) => Err(e),
+    };
+
+    let _ = fs::remove_dir_all(&dir);
+    res
+}
+
+/// The WAV file is completed when the system is released, at the end.
+fn render_in(config: &Config, musics: &[String], dir: &Path) -> Result<(), String> {
+    let fmod = try!(output::create_render_system(config, dir));
+    let mut renderer = Renderer::new(config, &fmod);
+
+    renderer.run(musics)
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    // rename doesn't work across file systems
+    match fs::copy(from, to) {
+        Ok(_) => {
+            let _ = fs::remove_file(from);
+            Ok(())
+        }
+        Err(e) => Err(format!("Cannot write {} : {}", to.display(), e)),
+    }
+}
+
+struct Renderer<'a> {
+    config: &'a Config,
+    fmod: &'a rfmod::Sys,
+    equalizer: Equalizer,
+    library: Library,
+    output_rate: u64,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(config: &'a Config, fmod: &'a rfmod::Sys) -> Renderer<'a> {
+        let mut equalizer = Equalizer::new();
+
+        if !equalizer.set_preset_by_name(&config.equalizer) {
+            println!("Unknown equalizer preset : {}", config.equalizer);
+        }
+        equalizer.attach(fmod);
+        Renderer {
+            config: config,
+            fmod: fmod,
+            equalizer: equalizer,
+            library: Library::load(),
+            output_rate: match fmod.get_software_format() {
+                Ok((rate, ..)) => rate as u64,
+                Err(_) => 48000u64,
+            },
+        }
+    }
+
+    fn get_clock(&self) -> u64 {
+        match self.fmod.get_DSP_clock() {
+            Ok((hi, lo)) => (hi as u64) << 32 | lo as u64,
+            Err(_) => 0u64,
+        }
+    }
+
+    fn get_gain(&self, track: &Track) -> f32 {
+        track
+            .replay_gain
+            .get_factor(self.config.replay_gain, self.config.replay_gain_preamp)
+    }
+
+    /// Loads the first music which can be opened from `*index`, which is
+    /// moved after it. The track is returned paused.
+    fn load_next(&self, musics: &[String], index: &mut usize) -> Option<Track> {
+        while *index < musics.len() {
+            let name = musics[*index].clone();
+
+            *index += 1;
+            match self.load_track(name) {
+                Ok(t) => return Some(t),
+                Err(e) => println!("{}", e),
+            }
+        }
+        None
+    }
+
+    fn load_track(&self, name: String) -> Result<Track, String> {
+        let sound = match self.fmod.create_sound(
+            &name,
+            Some(rfmod::Mode(rfmod::SOFTWARE | rfmod::_3D)),
+            None,
+        ) {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(format!(
+                    "FmodSys::create_sound failed on this file : {}\nError : {:?}",
+                    name, e
+                ))
+            }
+        };
+
+        sound.set_3D_min_max_distance(5f32, 10000f32);
+        sound.set_mode(rfmod::Mode(rfmod::LOOP_OFF));
+        let mut track = try!(Track::new(name, sound));
+
+        track.chan.set_3D_attributes(
+            &rfmod::Vector {
+                x: 0f32,
+                y: 0f32,
+                z: 0f32,
+            },
+            &Default::default(),
+        );
+        track.apply_saved_speed(self.fmod, &self.library, self.config.time_stretch);
+        volume::set_balance(&track.chan, self.config.balance);
+        self.check_replay_gain(&mut track);
+        track.chan.set_volume(self.get_gain(&track));
+        Ok(track)
+    }
+
+    /// Same as the player, except that the analysis is done right away.
+    fn check_replay_gain(&self, track: &mut Track) {
+        if !track.replay_gain.is_empty() {
+            return;
+        }
+        if let Some(info) = self.library.get(&track.name) {
+            if info.gain.is_some() {
+                track.replay_gain.track_gain = info.gain;
+                track.replay_gain.track_peak = info.peak;
+                return;
+            }
+        }
+        if self.config.analyze_loudness {
+            match loudness::analyze_file(&track.name) {
+                Ok((gain, peak)) => {
+                    track.replay_gain.track_gain = Some(gain);
+                    track.replay_gain.track_peak = Some(peak);
+                }
+                Err(e) => println!("Loudness analysis failed : {}", e),
+            }
+        }
+    }
+
+    /// Called when a music starts to be heard.
+    fn start_track(&mut self, track: &Track) {
+        let preset = match self.library.get(&track.name) {
+            Some(info) => info.equalizer.clone(),
+            None => None,
+        };
+        let preset = preset.unwrap_or(self.config.equalizer.clone());
+
+        if !self.equalizer.set_preset_by_name(&preset) {
+            println!("Unknown equalizer preset : {}", preset);
+        }
+        println!("Rendering {}", track.name);
+    }
+
+    fn run(&mut self, musics: &[String]) -> Result<(), String> {
+        let mut index = 0usize;
+        let mut track = match self.load_next(musics, &mut index) {
+            Some(t) => t,
+            None => return Err("No music to render".to_owned()),
+        };
+        let mut next: Option<Track> = None;
+        // the crossfade and the DSP clock when it started
+        let mut fade: Option<(Crossfade, u64)> = None;
+        let fade_time = (self.config.crossfade * 1000f32) as usize;
+
+        self.fmod.set_3D_listener_attributes(
+            0,
+            &rfmod::Vector::new(),
+            &rfmod::Vector::new(),
+            &rfmod::Vector {
+                x: 0f32,
+                y: 0f32,
+                z: 1f32,
+            },
+            &rfmod::Vector {
+                x: 0f32,
+                y: 1f32,
+                z: 0f32,
+            },
+        );
+        self.start_track(&track);
+        track.chan.set_paused(false);
+        loop {
+            // in NRT mode, each update mixes one buffer
+            self.fmod.update();
+            let clock = self.get_clock();
+            let over = match fade {
+                Some((ref mut f, start)) => {
+                    f.set_elapsed((clock - start) as f32 / self.output_rate as f32);
+                    f.outgoing
+                        .chan
+                        .set_volume(self.get_gain(&f.outgoing) * f.get_out_volume());
+                    track.chan.set_volume(self.get_gain(&track) * f.get_in_volume());
+                    f.is_over()
+                }
+                None => false,
+            };
+
+            if over {
+                // dropping the crossfade stops its outgoing music
+                fade = None;
+                track.chan.set_volume(self.get_gain(&track));
+            }
+            let playing = match track.chan.is_playing() {
+                Ok(b) => b,
+                Err(_) => false,
+            };
+
+            if !playing {
+                match next.take() {
+                    Some(n) => {
+                        track = n;
+                        self.start_track(&track);
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+            if next.is_some() || fade.is_some() || index >= musics.len() {
+                continue;
+            }
+            let position = match track.chan.get_position(rfmod::TIMEUNIT_MS) {
+                Ok(p) => p,
+                Err(_) => 0usize,
+            };
+            let length = match track.sound.get_length(rfmod::TIMEUNIT_MS) {
+                Ok(l) => l as usize,
+                Err(_) => 0usize,
+            };
+
+            // positions are in media time, which goes faster than the real one
+            let track_fade = (fade_time as f32 * track.speed) as usize;
+
+            // musics shorter than the crossfade are just played gapless
+            if track_fade > 0 && length > track_fade * 2 {
+                if position + track_fade >= length {
+                    if let Some(n) = self.load_next(musics, &mut index) {
+                        let old = mem::replace(&mut track, n);
+
+                        self.start_track(&track);
+                        track.chan.set_volume(0f32);
+                        track.chan.set_paused(false);
+                        fade = Some((
+                            Crossfade::new(old, self.config.fade_curve, self.config.crossfade),
+                            clock,
+                        ));
+                    }
+                }
+            } else if position + (PRELOAD_TIME as f32 * track.speed) as usize >= length {
+                let clock = match track.get_end_clock(self.fmod, self.output_rate) {
+                    Some(c) => c,
+                    None => continue,
+                };
+
+                if let Some(n) = self.load_next(musics, &mut index) {
+                    n.set_delay(rfmod::DelayType::DSPClockStart, clock);
+                    track.set_delay(rfmod::DelayType::DSPClockEnd, clock);
+                    n.chan.set_paused(false);
+                    next = Some(n);
+                }
+            }
+        }
+        Ok(())
+    }
+}
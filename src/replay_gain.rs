@@ -0,0 +1,158 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use rfmod;
+use tags;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplayGainMode {
+    Off,
+    Track,
+    Album,
+}
+
+impl ReplayGainMode {
+    pub fn from_str(s: &str) -> Option<ReplayGainMode> {
+        match s {
+            "off" => Some(ReplayGainMode::Off),
+            "track" => Some(ReplayGainMode::Track),
+            "album" => Some(ReplayGainMode::Album),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            ReplayGainMode::Off => "off",
+            ReplayGainMode::Track => "track",
+            ReplayGainMode::Album => "album",
+        }
+    }
+
+    pub fn next(&self) -> ReplayGainMode {
+        match *self {
+            ReplayGainMode::Off => ReplayGainMode::Track,
+            ReplayGainMode::Track => ReplayGainMode::Album,
+            ReplayGainMode::Album => ReplayGainMode::Off,
+        }
+    }
+}
+
+/// Gains are in dB, peaks are linear (1 being full scale).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    /// Reads the REPLAYGAIN_* tags, whether they are stored as Vorbis/APE
+    /// comments or as ID3v2 TXXX frames.
+    pub fn from_sound(sound: &rfmod::Sound) -> ReplayGain {
+        let mut gain = ReplayGain::default();
+        let names = [
+            "REPLAYGAIN_TRACK_GAIN",
+            "REPLAYGAIN_TRACK_PEAK",
+            "REPLAYGAIN_ALBUM_GAIN",
+            "REPLAYGAIN_ALBUM_PEAK",
+        ];
+
+        for name in names.iter() {
+            let value = match tags::get_tag_string(sound, name) {
+                Some(v) => Some(v),
+                None => tags::get_tag_string(sound, &name.to_lowercase()),
+            };
+            match value {
+                Some(v) => gain.set(name, &v),
+                None => {}
+            }
+        }
+        let mut index = 0i32;
+
+        while let Some(data) = tags::get_tag_data_at(sound, "TXXX", index) {
+            if data.len() > 1 {
+                let (description, value) = tags::split_id3_text(data[0], &data[1..]);
+                let value = tags::decode_id3_text(data[0], value);
+
+                gain.set(&description.to_uppercase(), value.trim_matches('\0'));
+            }
+            index += 1;
+        }
+        gain
+    }
+
+    fn set(&mut self, name: &str, value: &str) {
+        // gains are written like "-6.52 dB"
+        let value = match value
+            .trim()
+            .trim_end_matches("dB")
+            .trim_end_matches("db")
+            .trim()
+            .parse::<f32>()
+        {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+
+        match name {
+            "REPLAYGAIN_TRACK_GAIN" => self.track_gain = Some(value),
+            "REPLAYGAIN_TRACK_PEAK" => self.track_peak = Some(value),
+            "REPLAYGAIN_ALBUM_GAIN" => self.album_gain = Some(value),
+            "REPLAYGAIN_ALBUM_PEAK" => self.album_peak = Some(value),
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.track_gain.is_none() && self.album_gain.is_none()
+    }
+
+    /// Returns the volume factor to apply. The album values fall back on the
+    /// track ones (and the other way around) when missing. The factor is
+    /// lowered if the peak would clip.
+    pub fn get_factor(&self, mode: ReplayGainMode, preamp: f32) -> f32 {
+        let (gain, peak) = match mode {
+            ReplayGainMode::Off => return 1f32,
+            ReplayGainMode::Track => (
+                self.track_gain.or(self.album_gain),
+                self.track_peak.or(self.album_peak),
+            ),
+            ReplayGainMode::Album => (
+                self.album_gain.or(self.track_gain),
+                self.album_peak.or(self.track_peak),
+            ),
+        };
+        let factor = match gain {
+            Some(g) => 10f32.powf((g + preamp) / 20f32),
+            None => return 1f32,
+        };
+
+        match peak {
+            Some(p) if p > 0f32 && factor * p > 1f32 => 1f32 / p,
+            _ => factor,
+        }
+    }
+}
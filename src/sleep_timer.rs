@@ -0,0 +1,135 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use std::time::{Duration, Instant};
+
+/// Time (in ms) during which the volume goes down before the player stops.
+pub const FADE_TIME: usize = 30000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SleepMode {
+    Off,
+    Minutes(u64),
+    EndOfTrack,
+    EndOfAlbum,
+}
+
+impl SleepMode {
+    pub fn next(&self) -> SleepMode {
+        match *self {
+            SleepMode::Off => SleepMode::Minutes(15),
+            SleepMode::Minutes(15) => SleepMode::Minutes(30),
+            SleepMode::Minutes(30) => SleepMode::Minutes(45),
+            SleepMode::Minutes(45) => SleepMode::Minutes(60),
+            SleepMode::Minutes(60) => SleepMode::Minutes(90),
+            SleepMode::Minutes(_) => SleepMode::EndOfTrack,
+            SleepMode::EndOfTrack => SleepMode::EndOfAlbum,
+            SleepMode::EndOfAlbum => SleepMode::Off,
+        }
+    }
+}
+
+pub struct SleepTimer {
+    mode: SleepMode,
+    deadline: Option<Instant>,
+}
+
+impl SleepTimer {
+    pub fn new() -> SleepTimer {
+        SleepTimer {
+            mode: SleepMode::Off,
+            deadline: None,
+        }
+    }
+
+    pub fn get_mode(&self) -> SleepMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: SleepMode) {
+        self.mode = mode;
+        self.deadline = match mode {
+            SleepMode::Minutes(m) => Some(Instant::now() + Duration::from_secs(m * 60)),
+            _ => None,
+        };
+    }
+
+    /// Tells if the player has to stop once the current music ends.
+    /// `last_of_album` is true when the next music is from another album.
+    pub fn stops_after_track(&self, last_of_album: bool) -> bool {
+        match self.mode {
+            SleepMode::EndOfTrack => true,
+            SleepMode::EndOfAlbum => last_of_album,
+            _ => false,
+        }
+    }
+
+    /// Returns the time left (in ms) before the player stops, if it is
+    /// already known. `track_left` is the time left in the current music.
+    pub fn get_remaining(&self, track_left: usize, last_of_album: bool) -> Option<usize> {
+        match self.mode {
+            SleepMode::Minutes(_) => match self.deadline {
+                Some(d) => {
+                    let now = Instant::now();
+
+                    if d > now {
+                        let left = d - now;
+                        let millis = left.subsec_nanos() as usize / 1000000;
+
+                        Some(left.as_secs() as usize * 1000 + millis)
+                    } else {
+                        Some(0usize)
+                    }
+                }
+                None => None,
+            },
+            SleepMode::Off => None,
+            _ => {
+                if self.stops_after_track(last_of_album) {
+                    Some(track_left)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Returns the progress (from 0 to 1) of the fade out.
+    pub fn get_fade_progress(remaining: usize) -> f32 {
+        if remaining >= FADE_TIME {
+            0f32
+        } else {
+            1f32 - remaining as f32 / FADE_TIME as f32
+        }
+    }
+
+    pub fn get_text(&self, remaining: Option<usize>) -> Option<String> {
+        match (self.mode, remaining) {
+            (SleepMode::Off, _) => None,
+            (_, Some(r)) => Some(format!("Zz {:02}:{:02}", r / 1000 / 60, r / 1000 % 60)),
+            (SleepMode::EndOfAlbum, None) => Some("Zz album".to_owned()),
+            (_, None) => Some("Zz track".to_owned()),
+        }
+    }
+}
@@ -0,0 +1,380 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use gapless;
+use mp4;
+use rfmod;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::slice;
+
+// bytes read at the beginning of Ogg files, where the comment header is
+const OGG_HEADER_SIZE: u64 = 65536;
+
+pub fn get_tag_data(sound: &rfmod::Sound, name: &str) -> Option<Vec<u8>> {
+    get_tag_data_at(sound, name, 0i32)
+}
+
+pub fn get_tag_data_at(sound: &rfmod::Sound, name: &str, index: i32) -> Option<Vec<u8>> {
+    match sound.get_tag(name, index) {
+        Ok(tag) => {
+            if tag.data.is_null() || tag.data_len == 0 {
+                None
+            } else {
+                Some(
+                    unsafe { slice::from_raw_parts(tag.data as *const u8, tag.data_len as usize) }
+                        .to_vec(),
+                )
+            }
+        }
+        Err(_) => None,
+    }
+}
+
+pub fn get_tag_string(sound: &rfmod::Sound, name: &str) -> Option<String> {
+    match get_tag_data(sound, name) {
+        Some(data) => {
+            let s = String::from_utf8_lossy(&data)
+                .trim_matches('\0')
+                .trim()
+                .to_owned();
+
+            if s.is_empty() {
+                None
+            } else {
+                Some(s)
+            }
+        }
+        None => None,
+    }
+}
+
+/// Decodes an ID3v2 string. `encoding` is the frame's text encoding byte:
+/// 0 is ISO-8859-1, 1 is UTF-16 with BOM, 2 is UTF-16BE and 3 is UTF-8.
+pub fn decode_id3_text(encoding: u8, data: &[u8]) -> String {
+    match encoding {
+        1 | 2 => {
+            let mut big_endian = encoding == 2;
+            let mut start = 0usize;
+
+            if data.len() >= 2 {
+                if data[0] == 0xFE && data[1] == 0xFF {
+                    big_endian = true;
+                    start = 2;
+                } else if data[0] == 0xFF && data[1] == 0xFE {
+                    big_endian = false;
+                    start = 2;
+                }
+            }
+            let mut units = Vec::with_capacity(data.len() / 2);
+            let mut it = start;
+
+            while it + 1 < data.len() {
+                units.push(if big_endian {
+                    (data[it] as u16) << 8 | data[it + 1] as u16
+                } else {
+                    (data[it + 1] as u16) << 8 | data[it] as u16
+                });
+                it += 2;
+            }
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(data).into_owned(),
+        _ => data.iter().map(|c| *c as char).collect(),
+    }
+}
+
+/// Reads a null-terminated ID3v2 string and returns it with the data that
+/// follows the terminator.
+pub fn split_id3_text<'a>(encoding: u8, data: &'a [u8]) -> (String, &'a [u8]) {
+    let mut it = 0usize;
+
+    if encoding == 1 || encoding == 2 {
+        while it + 1 < data.len() {
+            if data[it] == 0 && data[it + 1] == 0 {
+                return (decode_id3_text(encoding, &data[..it]), &data[it + 2..]);
+            }
+            it += 2;
+        }
+    } else {
+        while it < data.len() {
+            if data[it] == 0 {
+                return (decode_id3_text(encoding, &data[..it]), &data[it + 1..]);
+            }
+            it += 1;
+        }
+    }
+    (decode_id3_text(encoding, data), &data[data.len()..])
+}
+
+/// Reads an ID3v2 text frame (like "TALB"), made of the encoding byte
+/// followed by the text.
+pub fn get_id3_text(sound: &rfmod::Sound, frame: &str) -> Option<String> {
+    match get_tag_data(sound, frame) {
+        Some(ref data) if data.len() > 1 => {
+            let text = decode_id3_text(data[0], &data[1..]);
+            let text = text.trim_matches('\0').trim();
+
+            if text.is_empty() {
+                None
+            } else {
+                Some(text.to_owned())
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns the major version of the ID3v2 tag of the file, like 3 for
+/// ID3v2.3, only reading its header.
+pub fn read_id3_version(path: &str) -> Option<u8> {
+    let mut header = [0u8; 10];
+
+    match File::open(path) {
+        Ok(mut f) => {
+            if f.read_exact(&mut header).is_err() {
+                return None;
+            }
+        }
+        Err(_) => return None,
+    }
+    if &header[0..3] == b"ID3" {
+        Some(header[3])
+    } else {
+        None
+    }
+}
+
+/// Reads the album of a file without opening it with FMOD: only the tags at
+/// the beginning of MP3 (ID3v2), FLAC, Ogg and MP4 files are read.
+pub fn read_album(path: &str) -> Option<String> {
+    let mut file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return None,
+    };
+
+    if let Some(moov) = mp4::read_top_atom(&mut file, b"moov") {
+        return read_mp4_album(&moov);
+    }
+    let mut header = [0u8; 10];
+
+    if file.seek(SeekFrom::Start(0)).is_err() || file.read_exact(&mut header).is_err() {
+        return None;
+    }
+    if &header[0..3] == b"ID3" {
+        let mut data = vec![0u8; gapless::get_id3_size(&header) - 10];
+
+        if file.read_exact(&mut data).is_err() {
+            return None;
+        }
+        // since ID3v2.4, the unsynchronisation is done frame by frame
+        if header[5] & 0x80 != 0 && header[3] < 4 {
+            data = remove_unsync(&data);
+        }
+        return read_id3_album(header[3], header[5], &data);
+    }
+    if &header[0..4] == b"fLaC" {
+        return read_flac_album(&mut file);
+    }
+    if &header[0..4] == b"OggS" {
+        let mut data = Vec::new();
+
+        if file.seek(SeekFrom::Start(0)).is_err() {
+            return None;
+        }
+        if file.by_ref().take(OGG_HEADER_SIZE).read_to_end(&mut data).is_err() {
+            return None;
+        }
+        return read_ogg_album(&data);
+    }
+    None
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim_matches('\0').trim();
+
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_owned())
+    }
+}
+
+/// The album is in moov/udta/meta/ilst/\xa9alb/data, after 8 bytes of type
+/// and locale.
+fn read_mp4_album(moov: &[u8]) -> Option<String> {
+    // "meta" starts with its version and flags
+    let meta = match mp4::find_atom(moov, &[b"udta", b"meta"]) {
+        Some(m) if m.len() > 4 => m,
+        _ => return None,
+    };
+
+    match mp4::find_atom(&meta[4..], &[b"ilst", b"\xa9alb", b"data"]) {
+        Some(data) if data.len() > 8 => non_empty(&String::from_utf8_lossy(&data[8..])),
+        _ => None,
+    }
+}
+
+/// Looks for the TALB (or TAL in ID3v2.2) frame. The frame sizes are
+/// syncsafe integers since ID3v2.4.
+fn read_id3_album(version: u8, flags: u8, data: &[u8]) -> Option<String> {
+    let (header_size, id): (usize, &[u8]) = if version == 2 {
+        (6, &b"TAL"[..])
+    } else {
+        (10, &b"TALB"[..])
+    };
+    let mut pos = 0usize;
+
+    // skips the extended header
+    if flags & 0x40 != 0 && version > 2 && data.len() >= 4 {
+        pos = if version == 4 {
+            gapless::read_syncsafe(data)
+        } else {
+            mp4::read_u32(data) as usize + 4
+        };
+    }
+    while pos + header_size <= data.len() && data[pos] != 0 {
+        let size = match version {
+            2 => {
+                (data[pos + 3] as usize) << 16
+                    | (data[pos + 4] as usize) << 8
+                    | data[pos + 5] as usize
+            }
+            3 => mp4::read_u32(&data[pos + 4..]) as usize,
+            _ => gapless::read_syncsafe(&data[pos + 4..]),
+        };
+        let start = pos + header_size;
+
+        if start + size > data.len() {
+            return None;
+        }
+        if &data[pos..pos + id.len()] == id && size > 1 {
+            let mut frame = data[start..start + size].to_vec();
+
+            if version == 4 && data[pos + 9] & 0x02 != 0 {
+                frame = remove_unsync(&frame);
+            }
+            return non_empty(&decode_id3_text(frame[0], &frame[1..]));
+        }
+        pos = start + size;
+    }
+    None
+}
+
+/// The unsynchronisation inserts a 0 after each 0xFF byte, so no false
+/// MPEG frame sync can be found in the tag.
+fn remove_unsync(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+
+    for (pos, &b) in data.iter().enumerate() {
+        if b == 0 && pos > 0 && data[pos - 1] == 0xFF {
+            continue;
+        }
+        out.push(b);
+    }
+    out
+}
+
+/// Walks the metadata blocks which follow the "fLaC" marker, until the
+/// VORBIS_COMMENT one (type 4).
+fn read_flac_album(file: &mut File) -> Option<String> {
+    let mut pos = 4u64;
+
+    loop {
+        let mut header = [0u8; 4];
+
+        if file.seek(SeekFrom::Start(pos)).is_err() || file.read_exact(&mut header).is_err() {
+            return None;
+        }
+        let size = (header[1] as u64) << 16 | (header[2] as u64) << 8 | header[3] as u64;
+
+        if header[0] & 0x7f == 4 {
+            let mut data = vec![0u8; size as usize];
+
+            return match file.read_exact(&mut data) {
+                Ok(_) => read_vorbis_album(&data),
+                Err(_) => None,
+            };
+        }
+        // the last block has the highest bit set
+        if header[0] & 0x80 != 0 {
+            return None;
+        }
+        pos += 4 + size;
+    }
+}
+
+/// The comment header is the second packet of Vorbis and Opus streams.
+fn read_ogg_album(data: &[u8]) -> Option<String> {
+    for &(marker, skip) in [(&b"\x03vorbis"[..], 7usize), (&b"OpusTags"[..], 8usize)].iter() {
+        let found = data.windows(marker.len()).position(|w| w == marker);
+
+        if let Some(pos) = found {
+            return read_vorbis_album(&data[pos + skip..]);
+        }
+    }
+    None
+}
+
+fn read_u32_le(data: &[u8]) -> usize {
+    (data[3] as usize) << 24
+        | (data[2] as usize) << 16
+        | (data[1] as usize) << 8
+        | data[0] as usize
+}
+
+/// A Vorbis comment is a vendor string followed by `KEY=value` strings, each
+/// one preceded by its little endian length.
+fn read_vorbis_album(data: &[u8]) -> Option<String> {
+    if data.len() < 8 {
+        return None;
+    }
+    let mut pos = 4 + read_u32_le(data);
+
+    if pos + 4 > data.len() {
+        return None;
+    }
+    let count = read_u32_le(&data[pos..]);
+
+    pos += 4;
+    for _ in 0..count {
+        if pos + 4 > data.len() {
+            return None;
+        }
+        let size = read_u32_le(&data[pos..]);
+
+        pos += 4;
+        if pos + size > data.len() {
+            return None;
+        }
+        let comment = String::from_utf8_lossy(&data[pos..pos + size]);
+        let mut parts = comment.splitn(2, '=');
+
+        if parts.next().unwrap_or("").eq_ignore_ascii_case("album") {
+            return non_empty(parts.next().unwrap_or(""));
+        }
+        pos += size;
+    }
+    None
+}
@@ -0,0 +1,208 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use gapless::{self, EncoderTrim};
+use library::Library;
+use replay_gain::ReplayGain;
+use rfmod;
+
+/// A sound with the channel playing it.
+pub struct Track {
+    pub name: String,
+    pub sound: rfmod::Sound,
+    pub chan: rfmod::Channel,
+    pub trim: Option<EncoderTrim>,
+    pub replay_gain: ReplayGain,
+    pub speed: f32,
+    /// Pitch shift in semitones.
+    pub pitch: i32,
+    // default frequency of the sound, changed to play faster or slower
+    frequency: f32,
+    pitch_dsp: Option<rfmod::Dsp>,
+}
+
+impl Track {
+    /// Creates the channel paused so it can be configured before being heard.
+    pub fn new(name: String, sound: rfmod::Sound) -> Result<Track, String> {
+        let mut chan = rfmod::Channel::new();
+
+        match sound.play_with_parameters(true, &mut chan) {
+            rfmod::Status::Ok => {}
+            e => return Err(format!("sound.play : {:?}", e)),
+        }
+        let frequency = match sound.get_defaults() {
+            Ok((f, _, _, _)) => f,
+            Err(_) => match chan.get_frequency() {
+                Ok(f) => f,
+                Err(_) => 44100f32,
+            },
+        };
+        let track = Track {
+            trim: gapless::read_trim(&name),
+            replay_gain: ReplayGain::from_sound(&sound),
+            name: name,
+            sound: sound,
+            chan: chan,
+            speed: 1f32,
+            pitch: 0i32,
+            frequency: frequency,
+            pitch_dsp: None,
+        };
+
+        match track.trim {
+            Some(t) => {
+                track.chan.set_position(t.delay as usize, rfmod::TIMEUNIT_PCM);
+            }
+            None => {}
+        }
+        Ok(track)
+    }
+
+    /// Returns the position (in PCM samples) where the audible part ends.
+    pub fn get_end_pcm(&self) -> Option<u64> {
+        match self.sound.get_length(rfmod::TIMEUNIT_PCM) {
+            Ok(l) => {
+                let padding = match self.trim {
+                    Some(t) => t.padding as u64,
+                    None => 0u64,
+                };
+
+                if l as u64 > padding {
+                    Some(l as u64 - padding)
+                } else {
+                    Some(l as u64)
+                }
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the DSP clock at which the track will end.
+    pub fn get_end_clock(&self, fmod: &rfmod::Sys, output_rate: u64) -> Option<u64> {
+        let end = match self.get_end_pcm() {
+            Some(e) => e,
+            None => return None,
+        };
+        let position = match self.chan.get_position(rfmod::TIMEUNIT_PCM) {
+            Ok(p) => p as u64,
+            Err(_) => return None,
+        };
+        let frequency = match self.chan.get_frequency() {
+            Ok(f) if f > 0f32 => f as f64,
+            _ => return None,
+        };
+        let clock = match fmod.get_DSP_clock() {
+            Ok((hi, lo)) => (hi as u64) << 32 | lo as u64,
+            Err(_) => return None,
+        };
+        let remaining = if end > position { end - position } else { 0u64 };
+
+        Some(clock + (remaining as f64 * output_rate as f64 / frequency) as u64)
+    }
+
+    /// Plays the music at the speed it had the last time.
+    pub fn apply_saved_speed(&mut self, fmod: &rfmod::Sys, library: &Library, stretch: bool) {
+        let (speed, pitch) = match library.get(&self.name) {
+            Some(info) => (info.speed.unwrap_or(1f32), info.pitch.unwrap_or(0i32)),
+            None => (1f32, 0i32),
+        };
+
+        if speed != 1f32 || pitch != 0 {
+            self.set_speed(fmod, speed, limit_pitch(speed, pitch, stretch), stretch);
+        }
+    }
+
+    /// Plays the track `speed` times faster. When `stretch` is set, the
+    /// pitch is kept by a pitch shifter, otherwise the music is just
+    /// resampled. `pitch` (in semitones) is applied on top of it.
+    pub fn set_speed(&mut self, fmod: &rfmod::Sys, speed: f32, pitch: i32, stretch: bool) {
+        self.speed = speed;
+        self.pitch = pitch;
+        self.chan.set_frequency(self.frequency * speed);
+
+        let mut factor = 2f32.powf(pitch as f32 / 12f32);
+
+        if stretch {
+            factor /= speed;
+        }
+        if (factor - 1f32).abs() < 0.001f32 {
+            match self.pitch_dsp {
+                Some(ref d) => {
+                    d.set_bypass(true);
+                }
+                None => {}
+            }
+            return;
+        }
+        if self.pitch_dsp.is_none() {
+            match fmod.create_DSP_by_type(rfmod::DspType::PitchShift) {
+                Ok(dsp) => {
+                    if let Err(e) = self.chan.add_DSP(&dsp) {
+                        println!("Channel::add_DSP failed : {:?}", e);
+                    }
+                    self.pitch_dsp = Some(dsp);
+                }
+                Err(e) => {
+                    println!("FmodSys::create_DSP_by_type failed : {:?}", e);
+                    return;
+                }
+            }
+        }
+        match self.pitch_dsp {
+            Some(ref d) => {
+                // FMOD_DSP_PITCHSHIFT_PITCH goes from 0.5 to 2.0, the pitch is
+                // already limited by limit_pitch so this only fixes rounding
+                d.set_parameter(0i32, factor.max(0.5f32).min(2f32));
+                d.set_bypass(false);
+            }
+            None => {}
+        }
+    }
+
+    pub fn set_delay(&self, delay_type: rfmod::DelayType, clock: u64) {
+        self.chan.set_delay(
+            delay_type,
+            (clock >> 32) as usize,
+            (clock & 0xFFFFFFFF) as usize,
+        );
+    }
+
+    pub fn stop(&self) {
+        self.chan.stop();
+    }
+}
+
+/// The pitch shifter only goes from one octave down to one octave up. With
+/// the time stretch, it also has to compensate the speed, so the pitch (in
+/// semitones) is limited to what it can still do.
+pub fn limit_pitch(speed: f32, pitch: i32, stretch: bool) -> i32 {
+    if !stretch {
+        return pitch;
+    }
+    let octaves = speed.log2();
+    let min = (12f32 * (octaves - 1f32)).ceil() as i32;
+    let max = (12f32 * (octaves + 1f32)).floor() as i32;
+
+    pitch.max(min).min(max)
+}
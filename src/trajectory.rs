@@ -0,0 +1,296 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+// speed (in units per second) of the listener at the normal speed
+const BASE_SPEED: f32 = 8f32;
+const MIN_SPEED: f32 = 0.25f32;
+const MAX_SPEED: f32 = 8f32;
+// size of the circle and of the figure-eight, the 3D view going up to 30
+const RADIUS: f32 = 20f32;
+// the random walk stays around this distance from the center
+const WALK_RADIUS: f32 = 25f32;
+// how fast (in radians per second) the random walk can turn
+const WALK_TURN: f32 = 1.5f32;
+// longest time (in seconds) between two updates, so a stall doesn't make
+// the listener jump
+const MAX_STEP: f32 = 0.1f32;
+const NB_OUTLINE_POINTS: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrajectoryKind {
+    Off,
+    Circle,
+    FigureEight,
+    RandomWalk,
+    /// The path drawn in the 3D view.
+    Drawn,
+}
+
+impl TrajectoryKind {
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            TrajectoryKind::Off => "off",
+            TrajectoryKind::Circle => "circle",
+            TrajectoryKind::FigureEight => "figure-eight",
+            TrajectoryKind::RandomWalk => "random walk",
+            TrajectoryKind::Drawn => "drawn path",
+        }
+    }
+
+    /// The drawn path is skipped if there is none.
+    pub fn next(&self, has_path: bool) -> TrajectoryKind {
+        match *self {
+            TrajectoryKind::Off => TrajectoryKind::Circle,
+            TrajectoryKind::Circle => TrajectoryKind::FigureEight,
+            TrajectoryKind::FigureEight => TrajectoryKind::RandomWalk,
+            TrajectoryKind::RandomWalk if has_path => TrajectoryKind::Drawn,
+            _ => TrajectoryKind::Off,
+        }
+    }
+}
+
+/// Moves the listener over time. Positions are in the units of the 3D
+/// view, `x` going to the right and `y` to the front.
+pub struct Trajectory {
+    kind: TrajectoryKind,
+    speed: f32,
+    // distance travelled along the circle, the figure-eight or the drawn path
+    distance: f32,
+    position: (f32, f32),
+    // direction (in radians) and destination of the random walk
+    heading: f32,
+    target: (f32, f32),
+    path: Vec<(f32, f32)>,
+    seed: u64,
+    last_update: Option<Instant>,
+}
+
+impl Trajectory {
+    pub fn new() -> Trajectory {
+        let seed = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() ^ d.subsec_nanos() as u64,
+            Err(_) => 0u64,
+        };
+
+        Trajectory {
+            kind: TrajectoryKind::Off,
+            speed: 1f32,
+            distance: 0f32,
+            position: (0f32, 0f32),
+            heading: 0f32,
+            target: (0f32, 0f32),
+            path: Vec::new(),
+            // xorshift doesn't work with 0
+            seed: seed | 1u64,
+            last_update: None,
+        }
+    }
+
+    pub fn get_kind(&self) -> TrajectoryKind {
+        self.kind
+    }
+
+    /// Starts `kind`. The random walk starts `from` the listener's position,
+    /// the other ones from their first point.
+    pub fn set_kind(&mut self, kind: TrajectoryKind, from: (f32, f32)) {
+        self.kind = if kind == TrajectoryKind::Drawn && self.path.len() < 2 {
+            TrajectoryKind::Off
+        } else {
+            kind
+        };
+        self.distance = 0f32;
+        self.position = from;
+        self.target = from;
+        self.last_update = None;
+    }
+
+    pub fn has_path(&self) -> bool {
+        self.path.len() > 1
+    }
+
+    /// Replaces the drawn path and follows it.
+    pub fn set_path(&mut self, path: Vec<(f32, f32)>) {
+        let from = self.position;
+
+        self.path = path;
+        self.set_kind(TrajectoryKind::Drawn, from);
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Doubles or halves the speed.
+    pub fn change_speed(&mut self, faster: bool) {
+        let speed = if faster {
+            self.speed * 2f32
+        } else {
+            self.speed / 2f32
+        };
+
+        self.speed = speed.max(MIN_SPEED).min(MAX_SPEED);
+    }
+
+    /// Moves the listener by the time spent since the last update and
+    /// returns its position and its velocity (in units per second).
+    pub fn update(&mut self) -> Option<((f32, f32), (f32, f32))> {
+        if self.kind == TrajectoryKind::Off {
+            return None;
+        }
+        let now = Instant::now();
+        let elapsed = match self.last_update {
+            Some(l) => {
+                let e = now.duration_since(l);
+
+                (e.as_secs() as f32 + e.subsec_nanos() as f32 / 1_000_000_000f32).min(MAX_STEP)
+            }
+            None => 0f32,
+        };
+        let step = BASE_SPEED * self.speed * elapsed;
+        let old = self.position;
+
+        self.last_update = Some(now);
+        self.distance += step;
+        self.position = match self.kind {
+            TrajectoryKind::Circle => {
+                let angle = self.distance / RADIUS;
+
+                (RADIUS * angle.cos(), RADIUS * angle.sin())
+            }
+            TrajectoryKind::FigureEight => {
+                let angle = self.distance / RADIUS;
+
+                (RADIUS * angle.sin(), RADIUS * angle.sin() * angle.cos())
+            }
+            TrajectoryKind::RandomWalk => self.walk(step, elapsed),
+            TrajectoryKind::Drawn => self.get_path_position(),
+            TrajectoryKind::Off => old,
+        };
+        // the first update jumps to the start of the path, it isn't a move
+        let velocity = if elapsed > 0f32 {
+            (
+                (self.position.0 - old.0) / elapsed,
+                (self.position.1 - old.1) / elapsed,
+            )
+        } else {
+            (0f32, 0f32)
+        };
+
+        Some((self.position, velocity))
+    }
+
+    /// Turns smoothly towards a random target, a new one being picked once
+    /// it is reached.
+    fn walk(&mut self, step: f32, elapsed: f32) -> (f32, f32) {
+        let (x, y) = self.position;
+        let (dx, dy) = (self.target.0 - x, self.target.1 - y);
+
+        if dx * dx + dy * dy < 4f32 {
+            let angle = self.random() * 2f32 * PI;
+            let distance = self.random().sqrt() * WALK_RADIUS;
+
+            self.target = (distance * angle.cos(), distance * angle.sin());
+            return self.position;
+        }
+        let mut turn = dy.atan2(dx) - self.heading;
+
+        while turn > PI {
+            turn -= 2f32 * PI;
+        }
+        while turn < -PI {
+            turn += 2f32 * PI;
+        }
+        let max_turn = WALK_TURN * elapsed;
+
+        self.heading += turn.max(-max_turn).min(max_turn);
+        (x + step * self.heading.cos(), y + step * self.heading.sin())
+    }
+
+    /// The drawn path is closed, so it is followed again and again.
+    fn get_path_position(&mut self) -> (f32, f32) {
+        let nb_points = self.path.len();
+        let lengths: Vec<f32> = (0..nb_points)
+            .map(|it| {
+                let (a, b) = (self.path[it], self.path[(it + 1) % nb_points]);
+
+                ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+            })
+            .collect();
+        let total: f32 = lengths.iter().sum();
+
+        if total <= 0f32 {
+            return self.path[0];
+        }
+        self.distance %= total;
+        let mut left = self.distance;
+
+        for (it, length) in lengths.iter().enumerate() {
+            if left <= *length && *length > 0f32 {
+                let (a, b) = (self.path[it], self.path[(it + 1) % nb_points]);
+                let ratio = left / length;
+
+                return (a.0 + (b.0 - a.0) * ratio, a.1 + (b.1 - a.1) * ratio);
+            }
+            left -= *length;
+        }
+        self.path[0]
+    }
+
+    /// Returns the points to draw in the 3D view, if the trajectory has a
+    /// shape.
+    pub fn get_outline(&self) -> Vec<(f32, f32)> {
+        match self.kind {
+            TrajectoryKind::Circle | TrajectoryKind::FigureEight => (0..NB_OUTLINE_POINTS + 1)
+                .map(|it| {
+                    let angle = it as f32 * 2f32 * PI / NB_OUTLINE_POINTS as f32;
+
+                    if self.kind == TrajectoryKind::Circle {
+                        (RADIUS * angle.cos(), RADIUS * angle.sin())
+                    } else {
+                        (RADIUS * angle.sin(), RADIUS * angle.sin() * angle.cos())
+                    }
+                })
+                .collect(),
+            TrajectoryKind::Drawn => {
+                let mut outline = self.path.clone();
+
+                outline.push(self.path[0]);
+                outline
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns a number between 0 and 1 (xorshift64*).
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed >> 12;
+        self.seed ^= self.seed << 25;
+        self.seed ^= self.seed >> 27;
+        (self.seed.wrapping_mul(0x2545F4914F6CDD1Du64) >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
@@ -0,0 +1,97 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+#![allow(dead_code)]
+
+use rfmod;
+
+// lowest gain of the dB curve, the bar at 0 is silent anyway
+const MIN_DB: f32 = -60f32;
+
+/// How the volume bar is turned into the channel's volume.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VolumeCurve {
+    Linear,
+    Cubic,
+    Db,
+}
+
+impl VolumeCurve {
+    pub fn from_str(s: &str) -> Option<VolumeCurve> {
+        match s {
+            "linear" => Some(VolumeCurve::Linear),
+            "cubic" => Some(VolumeCurve::Cubic),
+            "db" => Some(VolumeCurve::Db),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            VolumeCurve::Linear => "linear",
+            VolumeCurve::Cubic => "cubic",
+            VolumeCurve::Db => "db",
+        }
+    }
+
+    pub fn next(&self) -> VolumeCurve {
+        match *self {
+            VolumeCurve::Linear => VolumeCurve::Cubic,
+            VolumeCurve::Cubic => VolumeCurve::Db,
+            VolumeCurve::Db => VolumeCurve::Linear,
+        }
+    }
+
+    /// Returns the linear volume for a bar `value` between 0 and 1.
+    pub fn get_volume(&self, value: f32) -> f32 {
+        let value = value.max(0f32).min(1f32);
+
+        match *self {
+            VolumeCurve::Linear => value,
+            VolumeCurve::Cubic => value * value * value,
+            VolumeCurve::Db => {
+                if value <= 0f32 {
+                    0f32
+                } else {
+                    10f32.powf((1f32 - value) * MIN_DB / 20f32)
+                }
+            }
+        }
+    }
+}
+
+/// Returns the volume in dB, `None` when silent.
+pub fn to_db(volume: f32) -> Option<f32> {
+    if volume <= 0f32 {
+        None
+    } else {
+        Some(20f32 * volume.log10())
+    }
+}
+
+/// Sets the stereo balance (from -1 to 1) of a channel. The 3D position is
+/// faded out as the balance goes to one side, since FMOD ignores the 2D pan
+/// of 3D sounds.
+pub fn set_balance(chan: &rfmod::Channel, balance: f32) {
+    chan.set_3D_pan_level(1f32 - balance.abs());
+    chan.set_pan(balance);
+}
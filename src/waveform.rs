@@ -0,0 +1,142 @@
+/*
+* Rust-music-player - Copyright (c) 2014 Gomez Guillaume.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+
+#![allow(dead_code)]
+
+use config;
+use decoder::Decoder;
+use library;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of peaks computed for a music, whatever its length.
+pub const NB_PEAKS: usize = 2048;
+
+/// Returns the peaks of the music, from the cache if the file didn't change
+/// since they were computed. It decodes the whole file otherwise, so it
+/// should be called from another thread than the player.
+pub fn load(music: &str) -> Result<Vec<f32>, String> {
+    let id = match library::get_file_id(music) {
+        Some(i) => i,
+        None => return Err(format!("Cannot read {}", music)),
+    };
+    let path = get_cache_path(music);
+
+    if let Some(peaks) = read_cache(&path, id) {
+        return Ok(peaks);
+    }
+    let peaks = try!(compute(music));
+
+    write_cache(&path, id, &peaks);
+    Ok(peaks)
+}
+
+/// Decodes the music and keeps the loudest sample of each part.
+pub fn compute(music: &str) -> Result<Vec<f32>, String> {
+    let mut decoder = try!(Decoder::new(music));
+    let length = decoder.get_length();
+
+    if length == 0 {
+        return Err(format!("{} is empty", music));
+    }
+    let channels = decoder.channels;
+    let mut peaks = vec![0f32; NB_PEAKS];
+    let mut frame = 0usize;
+
+    while let Some(samples) = decoder.read() {
+        for chunk in samples.chunks(channels) {
+            let index = (frame * NB_PEAKS / length).min(NB_PEAKS - 1);
+            let peak = chunk.iter().fold(0f32, |a, b| a.max(b.abs()));
+
+            if peak > peaks[index] {
+                peaks[index] = peak.min(1f32);
+            }
+            frame += 1;
+        }
+    }
+    Ok(peaks)
+}
+
+/// The peaks are stored in `~/.music_player/waveforms`, in a file named
+/// after a hash of the music's absolute path.
+fn get_cache_path(music: &str) -> PathBuf {
+    let key = match fs::canonicalize(music) {
+        Ok(p) => p.to_string_lossy().into_owned(),
+        Err(_) => music.to_owned(),
+    };
+    // FNV-1a, so the name doesn't change between two builds
+    let hash = key.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3u64)
+    });
+    let mut path = config::get_data_dir();
+
+    path.push("waveforms");
+    path.push(format!("{:016x}", hash));
+    path
+}
+
+/// The file starts with the size and the modification time of the music,
+/// followed by one byte per peak.
+fn read_cache(path: &Path, id: (u64, u64)) -> Option<Vec<f32>> {
+    let mut content = Vec::new();
+
+    match File::open(path) {
+        Ok(mut f) => {
+            if f.read_to_end(&mut content).is_err() {
+                return None;
+            }
+        }
+        Err(_) => return None,
+    }
+    let end = match content.iter().position(|b| *b == b'\n') {
+        Some(e) => e,
+        None => return None,
+    };
+    let header = String::from_utf8_lossy(&content[..end]).into_owned();
+
+    if header != format!("{} {}", id.0, id.1) || content.len() - end - 1 != NB_PEAKS {
+        return None;
+    }
+    Some(content[end + 1..].iter().map(|b| *b as f32 / 255f32).collect())
+}
+
+fn write_cache(path: &Path, id: (u64, u64), peaks: &[f32]) {
+    if let Some(dir) = path.parent() {
+        if let Err(e) = fs::create_dir_all(dir) {
+            println!("Cannot create {} : {}", dir.display(), e);
+            return;
+        }
+    }
+    let mut content = format!("{} {}\n", id.0, id.1).into_bytes();
+
+    content.extend(peaks.iter().map(|p| (p * 255f32).round() as u8));
+    match File::create(path) {
+        Ok(mut f) => {
+            if let Err(e) = f.write_all(&content) {
+                println!("Cannot write {} : {}", path.display(), e);
+            }
+        }
+        Err(e) => println!("Cannot create {} : {}", path.display(), e),
+    }
+}